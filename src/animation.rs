@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+
+use crate::config::DisplayConfig;
+use crate::keybindings::{Action, KeyBindings};
+
+const MAX_SPEED: f32 = 64.0;
+
+// Actions freezing the day/night tint at a share of the day, for reproducible screenshots
+const TIME_OF_DAY_PRESETS: [(Action, f32, &str); 4] = [
+    (Action::FreezeNoon, 0.0, "noon"),
+    (Action::FreezeDusk, 0.25, "dusk"),
+    (Action::FreezeMidnight, 0.5, "midnight"),
+    (Action::FreezeDawn, 0.75, "dawn"),
+];
+
+pub struct AnimationPlugin;
+
+// Clock driving animated overlays, separate from Time so it can be paused and sped up
+#[derive(Resource)]
+pub struct GenTime {
+    pub elapsed: f32,
+    pub paused: bool,
+    pub speed: f32,
+}
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GenTime {
+            elapsed: 0.0,
+            paused: false,
+            speed: 1.0,
+        })
+            .add_system(update_animation_controls)
+            .add_system(freeze_time_of_day)
+            .add_system(advance_gen_time);
+    }
+}
+
+fn advance_gen_time(time: Res<Time>, mut gen_time: ResMut<GenTime>) {
+    if !gen_time.paused {
+        gen_time.elapsed += time.delta_seconds() * gen_time.speed;
+    }
+}
+
+// K pauses the clock, comma and period halve and double its speed
+fn update_animation_controls(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut gen_time: ResMut<GenTime>) {
+    if bindings.just_pressed(&keyboard, Action::PauseClock) {
+        gen_time.paused = !gen_time.paused;
+    }
+
+    if bindings.just_pressed(&keyboard, Action::SlowDownClock) {
+        gen_time.speed = (gen_time.speed / 2.0).max(1.0 / MAX_SPEED);
+    }
+
+    if bindings.just_pressed(&keyboard, Action::SpeedUpClock) {
+        gen_time.speed = (gen_time.speed * 2.0).min(MAX_SPEED);
+    }
+}
+
+// F1 to F4 hold the tint at noon, dusk, midnight or dawn, the same key again lets it follow the clock
+fn freeze_time_of_day(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut display: ResMut<DisplayConfig>) {
+    for (action, phase, name) in TIME_OF_DAY_PRESETS {
+        if !bindings.just_pressed(&keyboard, action) {
+            continue;
+        }
+
+        if display.frozen_time_of_day == Some(phase) {
+            display.frozen_time_of_day = None;
+            info!("Time of day follows the clock");
+        } else {
+            display.frozen_time_of_day = Some(phase);
+            info!("Time of day frozen at {}", name);
+        }
+    }
+}
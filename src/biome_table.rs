@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::tilemap::Biome;
+
+pub const BIOME_TABLE_PATH: &str = "assets/biomes.csv";
+pub const DEFAULT_BIOME_CSV: &str = include_str!("../assets/biomes.csv");
+// Table for BiomeModel::Simple, built in since it is not meant to be edited
+pub const SIMPLE_BIOME_CSV: &str = include_str!("../assets/biomes_simple.csv");
+
+// Temperature [C] and percipitation [mm] ranges are half open, matching (min, max]
+#[derive(Clone)]
+pub struct BiomeRule {
+    pub biome: Biome,
+    pub min_temperature: f64,
+    pub max_temperature: f64,
+    pub min_percipitation: f64,
+    pub max_percipitation: f64,
+}
+
+#[derive(Clone, Default)]
+pub struct BiomeTable {
+    pub rules: Vec<BiomeRule>,
+}
+
+impl BiomeRule {
+    fn contains(&self, temperature: f64, percipitation: f64) -> bool {
+        temperature > self.min_temperature && temperature <= self.max_temperature
+            && percipitation > self.min_percipitation && percipitation <= self.max_percipitation
+    }
+}
+
+impl BiomeTable {
+    pub fn from_csv(csv: &str) -> Result<Self> {
+        let mut rules = Vec::new();
+
+        for (line_number, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("biome,") {
+                continue;
+            }
+
+            let parse_error = |message: String| Error::Parse {
+                line: line_number + 1,
+                message,
+            };
+
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            if fields.len() != 5 {
+                return Err(parse_error(format!("expected 5 fields, found {}", fields.len())));
+            }
+
+            let biome = Biome::from_name(fields[0])
+                .ok_or_else(|| parse_error(format!("unknown biome '{}'", fields[0])))?;
+
+            let bound = |field: &str, unbounded: f64| -> Result<f64> {
+                if field.is_empty() {
+                    return Ok(unbounded);
+                }
+                field.parse::<f64>().map_err(|_| parse_error(format!("invalid number '{}'", field)))
+            };
+
+            rules.push(BiomeRule {
+                biome,
+                min_temperature: bound(fields[1], f64::NEG_INFINITY)?,
+                max_temperature: bound(fields[2], f64::INFINITY)?,
+                min_percipitation: bound(fields[3], f64::NEG_INFINITY)?,
+                max_percipitation: bound(fields[4], f64::INFINITY)?,
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    pub fn classify(&self, temperature: f64, percipitation: f64) -> Option<Biome> {
+        self.rules.iter()
+            .find(|rule| rule.contains(temperature, percipitation))
+            .map(|rule| rule.biome)
+    }
+
+    // Checks one point inside every cell between the rule boundaries for gaps and overlaps
+    pub fn validate(&self) -> Vec<String> {
+        let temperatures = Self::cell_points(self.rules.iter().flat_map(|rule| [rule.min_temperature, rule.max_temperature]));
+        let percipitations = Self::cell_points(self.rules.iter().flat_map(|rule| [rule.min_percipitation, rule.max_percipitation]));
+
+        let mut problems = Vec::new();
+
+        for &temperature in &temperatures {
+            for &percipitation in &percipitations {
+                let matches: Vec<Biome> = self.rules.iter()
+                    .filter(|rule| rule.contains(temperature, percipitation))
+                    .map(|rule| rule.biome)
+                    .collect();
+
+                if matches.is_empty() {
+                    problems.push(format!("gap at temperature {}, percipitation {}", temperature, percipitation));
+                } else if matches.len() > 1 {
+                    problems.push(format!("overlap at temperature {}, percipitation {}: {:?}", temperature, percipitation, matches));
+                }
+            }
+        }
+
+        problems
+    }
+
+    fn cell_points(bounds: impl Iterator<Item = f64>) -> Vec<f64> {
+        let mut bounds: Vec<f64> = bounds.filter(|bound| bound.is_finite()).collect();
+        bounds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        bounds.dedup();
+
+        if bounds.is_empty() {
+            return vec![0.0];
+        }
+
+        let mut points = vec![bounds[0] - 1.0];
+        for pair in bounds.windows(2) {
+            points.push((pair[0] + pair[1]) / 2.0);
+        }
+        points.push(bounds[bounds.len() - 1] + 1.0);
+
+        points
+    }
+}
+
+pub fn read_biome_table(path: &str) -> Result<BiomeTable> {
+    BiomeTable::from_csv(&std::fs::read_to_string(path)?)
+}
+
+// Reads the classification from disk, falling back to the embedded copy of the default table
+pub fn load_biome_table() -> BiomeTable {
+    let table = match read_biome_table(BIOME_TABLE_PATH) {
+        Ok(table) => table,
+        Err(Error::Io(_)) => BiomeTable::from_csv(DEFAULT_BIOME_CSV).unwrap_or_default(),
+        Err(error) => {
+            warn!("Failed to load {}: {}, using the default biome table", BIOME_TABLE_PATH, error);
+            BiomeTable::from_csv(DEFAULT_BIOME_CSV).unwrap_or_default()
+        }
+    };
+
+    for problem in table.validate() {
+        warn!("Biome table {}", problem);
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Frozen desert below 0 C, and above it a dry and a wet half split at 500 mm
+    const SMALL_CSV: &str = "\
+# comments and the header are skipped
+biome,min_temperature,max_temperature,min_percipitation,max_percipitation
+PolarDesert,,0,,
+TemperateDesert,0,,,500
+TemperateRainForest,0,,500,
+";
+
+    #[test]
+    fn small_table_classifies_sample_points() {
+        let table = BiomeTable::from_csv(SMALL_CSV).unwrap();
+        assert_eq!(table.rules.len(), 3);
+        assert!(table.validate().is_empty());
+
+        for (temperature, percipitation, expected) in [
+            (-10.0, 2000.0, Biome::PolarDesert),
+            (0.0, 100.0, Biome::PolarDesert),
+            (12.0, 100.0, Biome::TemperateDesert),
+            (12.0, 500.0, Biome::TemperateDesert),
+            (12.0, 500.5, Biome::TemperateRainForest),
+        ] {
+            assert_eq!(table.classify(temperature, percipitation), Some(expected), "{} C, {} mm", temperature, percipitation);
+        }
+    }
+
+    #[test]
+    fn gaps_and_overlaps_are_reported() {
+        let gap = BiomeTable::from_csv("PolarDesert,,0,,\nTemperateDesert,5,,,\n").unwrap();
+        assert!(gap.validate().iter().any(|problem| problem.starts_with("gap")));
+        assert_eq!(gap.classify(2.0, 100.0), None);
+
+        let overlap = BiomeTable::from_csv("PolarDesert,,10,,\nTemperateDesert,0,,,\n").unwrap();
+        assert!(overlap.validate().iter().any(|problem| problem.starts_with("overlap")));
+    }
+
+    #[test]
+    fn default_tables_have_no_gaps_or_overlaps() {
+        for csv in [DEFAULT_BIOME_CSV, SIMPLE_BIOME_CSV] {
+            assert_eq!(BiomeTable::from_csv(csv).unwrap().validate(), Vec::<String>::new());
+        }
+    }
+}
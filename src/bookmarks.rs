@@ -0,0 +1,317 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::config::{MapConfig, FONT_PATH};
+use crate::error::{Error, Result};
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::{Seed, SeedEntry, SeedEntrySystem, XShift, YShift, Zoom};
+
+pub const BOOKMARKS_PATH: &str = "bookmarks.csv";
+
+// Digit keys jump to the first nine bookmarks, and Shift + digit deletes them
+const BOOKMARK_ACTIONS: [Action; 9] = [
+    Action::Bookmark1,
+    Action::Bookmark2,
+    Action::Bookmark3,
+    Action::Bookmark4,
+    Action::Bookmark5,
+    Action::Bookmark6,
+    Action::Bookmark7,
+    Action::Bookmark8,
+    Action::Bookmark9,
+];
+const DELETE_BOOKMARK_ACTIONS: [Action; 9] = [
+    Action::DeleteBookmark1,
+    Action::DeleteBookmark2,
+    Action::DeleteBookmark3,
+    Action::DeleteBookmark4,
+    Action::DeleteBookmark5,
+    Action::DeleteBookmark6,
+    Action::DeleteBookmark7,
+    Action::DeleteBookmark8,
+    Action::DeleteBookmark9,
+];
+
+// A named view of a map, enough to regenerate and return to it
+#[derive(Clone)]
+pub struct Bookmark {
+    pub name: String,
+    pub seed: u32,
+    pub zoom: f64,
+    pub x_shift: f64,
+    pub y_shift: f64,
+}
+
+#[derive(Resource, Default)]
+pub struct Bookmarks(pub Vec<Bookmark>);
+
+// Name typed so far while saving a bookmark, None when not naming one
+#[derive(Resource, Default)]
+struct BookmarkEntry(Option<String>);
+
+#[derive(Component)]
+struct BookmarkPanel;
+
+pub struct BookmarkPlugin;
+
+impl Plugin for BookmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bookmarks::default())
+            .insert_resource(BookmarkEntry::default())
+            .add_startup_system(spawn_bookmark_panel)
+            .add_startup_system(setup_bookmarks)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_bookmark_entry.after(InputSystem).before(SeedEntrySystem),
+            )
+            .add_system(select_bookmark)
+            .add_system(update_bookmark_panel);
+    }
+}
+
+impl Bookmarks {
+    // Saving under an existing name replaces that bookmark in place
+    pub fn save(&mut self, bookmark: Bookmark) {
+        match self.0.iter_mut().find(|existing| existing.name == bookmark.name) {
+            Some(existing) => *existing = bookmark,
+            None => self.0.push(bookmark),
+        }
+    }
+
+    // One bookmark per line, the name last so it may contain commas
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("seed,zoom,x_shift,y_shift,name\n");
+
+        for bookmark in &self.0 {
+            csv.push_str(&format!("{},{},{},{},{}\n",
+                bookmark.seed, bookmark.zoom, bookmark.x_shift, bookmark.y_shift, bookmark.name));
+        }
+
+        csv
+    }
+
+    pub fn from_csv(csv: &str) -> Result<Self> {
+        let mut bookmarks = Bookmarks::default();
+
+        for (line_number, line) in csv.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with("seed,") {
+                continue;
+            }
+
+            let parse_error = |message: String| Error::Parse {
+                line: line_number + 1,
+                message,
+            };
+
+            let fields: Vec<&str> = line.splitn(5, ',').collect();
+            if fields.len() != 5 {
+                return Err(parse_error(format!("expected 5 fields, found {}", fields.len())));
+            }
+
+            let number = |field: &str| -> Result<f64> {
+                field.trim().parse::<f64>().map_err(|_| parse_error(format!("invalid number '{}'", field)))
+            };
+
+            bookmarks.save(Bookmark {
+                name: fields[4].to_string(),
+                seed: fields[0].trim().parse::<u32>().map_err(|_| parse_error(format!("invalid seed '{}'", fields[0])))?,
+                zoom: number(fields[1])?,
+                x_shift: number(fields[2])?,
+                y_shift: number(fields[3])?,
+            });
+        }
+
+        Ok(bookmarks)
+    }
+}
+
+pub fn read_bookmarks(path: &str) -> Result<Bookmarks> {
+    Bookmarks::from_csv(&std::fs::read_to_string(path)?)
+}
+
+pub fn write_bookmarks(path: &str, bookmarks: &Bookmarks) -> Result<()> {
+    std::fs::write(path, bookmarks.to_csv())?;
+    Ok(())
+}
+
+// A missing file just means nothing was bookmarked yet
+fn setup_bookmarks(config: Res<MapConfig>, mut bookmarks: ResMut<Bookmarks>) {
+    if !config.persist_bookmarks {
+        return;
+    }
+
+    match read_bookmarks(BOOKMARKS_PATH) {
+        Ok(loaded) => *bookmarks = loaded,
+        Err(Error::Io(_)) => {}
+        Err(error) => warn!("Failed to load {}: {}", BOOKMARKS_PATH, error),
+    }
+}
+
+fn spawn_bookmark_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font: asset_server.load(FONT_PATH),
+            font_size: 20.0,
+            color: Color::WHITE,
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(35.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        BookmarkPanel,
+    ));
+}
+
+// M starts naming the current view, Enter saves it and Escape cancels.
+// Runs before the seed entry so that Enter confirms the name instead of starting a seed
+#[allow(clippy::too_many_arguments)]
+fn update_bookmark_entry(
+    mut keyboard: ResMut<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut entry: ResMut<BookmarkEntry>,
+    mut bookmarks: ResMut<Bookmarks>,
+    seed_entry: Res<SeedEntry>,
+    seed: Res<Seed>,
+    zoom: Res<Zoom>,
+    x_shift: Res<XShift>,
+    y_shift: Res<YShift>,
+) {
+    let typed: Vec<char> = characters.iter().map(|event| event.char).collect();
+
+    let name = match entry.0.as_mut() {
+        Some(name) => name,
+        None => {
+            if seed_entry.0.is_none() && bindings.just_pressed(&keyboard, Action::AddBookmark) {
+                entry.0 = Some(String::new());
+                keyboard.clear();
+            }
+            return;
+        }
+    };
+
+    name.extend(typed.into_iter().filter(|c| !c.is_control()));
+
+    if keyboard.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) {
+        let name = entry.0.take().unwrap_or_default();
+        let name = name.trim();
+
+        if !name.is_empty() {
+            bookmarks.save(Bookmark {
+                name: name.to_string(),
+                seed: seed.0,
+                zoom: zoom.0,
+                x_shift: x_shift.0,
+                y_shift: y_shift.0,
+            });
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        entry.0 = None;
+    }
+
+    keyboard.clear();
+}
+
+// Digits jump to a bookmark, Shift + digit deletes it
+fn select_bookmark(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut bookmarks: ResMut<Bookmarks>,
+    mut seed: ResMut<Seed>,
+    mut zoom: ResMut<Zoom>,
+    mut x_shift: ResMut<XShift>,
+    mut y_shift: ResMut<YShift>,
+) {
+    for (index, (select, delete)) in BOOKMARK_ACTIONS.into_iter().zip(DELETE_BOOKMARK_ACTIONS).enumerate() {
+        if index >= bookmarks.0.len() {
+            break;
+        }
+
+        if bindings.just_pressed(&keyboard, delete) {
+            bookmarks.0.remove(index);
+            return;
+        }
+
+        if !bindings.just_pressed(&keyboard, select) {
+            continue;
+        }
+
+        let bookmark = &bookmarks.0[index];
+        *seed = Seed::number(bookmark.seed);
+        zoom.0 = bookmark.zoom;
+        x_shift.0 = bookmark.x_shift;
+        y_shift.0 = bookmark.y_shift;
+        return;
+    }
+}
+
+// Name of a key as shown next to its bookmark, Key1 as 1
+fn key_label(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    name.strip_prefix("Key").unwrap_or(&name).to_string()
+}
+
+fn update_bookmark_panel(
+    config: Res<MapConfig>,
+    bindings: Res<KeyBindings>,
+    bookmarks: Res<Bookmarks>,
+    entry: Res<BookmarkEntry>,
+    mut query: Query<&mut Text, With<BookmarkPanel>>,
+) {
+    if !bookmarks.is_changed() && !entry.is_changed() && !bindings.is_changed() {
+        return;
+    }
+
+    if bookmarks.is_changed() && !bookmarks.is_added() && config.persist_bookmarks {
+        if let Err(error) = write_bookmarks(BOOKMARKS_PATH, &bookmarks) {
+            warn!("Failed to save {}: {}", BOOKMARKS_PATH, error);
+        }
+    }
+
+    let mut lines: Vec<String> = bookmarks.0.iter().enumerate()
+        .map(|(index, bookmark)| match BOOKMARK_ACTIONS.get(index) {
+            Some(action) => format!("{} {}", key_label(bindings.key(*action)), bookmark.name),
+            None => format!("  {}", bookmark.name),
+        })
+        .collect();
+
+    if let Some(name) = &entry.0 {
+        lines.push(format!("Bookmark: {}_", name));
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bookmarks survive a save and load unchanged, including a name with commas in it
+    #[test]
+    fn bookmarks_round_trip_through_csv() {
+        let bookmarks = Bookmarks(vec![
+            Bookmark { name: "home".to_string(), seed: 829201, zoom: 1.0, x_shift: 0.0, y_shift: 0.0 },
+            Bookmark { name: "coast, north, by the lake".to_string(), seed: 42, zoom: 3.5, x_shift: -0.125, y_shift: 0.3 },
+        ]);
+        let loaded = Bookmarks::from_csv(&bookmarks.to_csv()).unwrap();
+
+        assert_eq!(loaded.0.len(), bookmarks.0.len());
+        for (loaded, saved) in loaded.0.iter().zip(&bookmarks.0) {
+            assert_eq!(loaded.name, saved.name);
+            assert_eq!(loaded.seed, saved.seed);
+            assert_eq!((loaded.zoom, loaded.x_shift, loaded.y_shift), (saved.zoom, saved.x_shift, saved.y_shift));
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::camera::cursor_world_position;
+use crate::config::MapConfig;
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::{Biome, MapGen};
+
+const MAX_BRUSH_RADIUS: i32 = 32;
+
+pub struct BrushPlugin;
+
+// Painted biomes by map cell, see MapGen::map_cell, with how strongly each covers the generated tile
+#[derive(Resource, Default)]
+pub struct BiomeOverrides(pub HashMap<(i32, i32), (Biome, f32)>);
+
+#[derive(Resource)]
+struct Brush {
+    enabled: bool,
+    eraser: bool,
+    radius: i32,
+    biome: usize,
+}
+
+impl Plugin for BrushPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BiomeOverrides::default())
+            .insert_resource(Brush {
+                enabled: false,
+                eraser: false,
+                radius: 3,
+                biome: 1,
+            })
+            .add_system(update_brush_settings)
+            .add_system(paint_brush);
+    }
+}
+
+// Tiles within the radius, full strength inside and fading over the outer tile ring
+pub fn brush_tiles(center: (i32, i32), radius: i32) -> Vec<((i32, i32), f32)> {
+    let mut tiles = Vec::new();
+
+    for dy in -radius..(radius + 1) {
+        for dx in -radius..(radius + 1) {
+            let distance = ((dx * dx + dy * dy) as f32).sqrt();
+            if distance > radius as f32 {
+                continue;
+            }
+
+            let edge = (distance - (radius - 1) as f32).max(0.0);
+            tiles.push(((center.0 + dx, center.1 + dy), 1.0 - edge / 2.0));
+        }
+    }
+
+    tiles
+}
+
+// P toggles the brush, E the eraser, B cycles the biome and [ ] resize it
+fn update_brush_settings(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut brush: ResMut<Brush>) {
+    if bindings.just_pressed(&keyboard, Action::ToggleBrush) {
+        brush.enabled = !brush.enabled;
+    }
+
+    if bindings.just_pressed(&keyboard, Action::ToggleEraser) {
+        brush.eraser = !brush.eraser;
+    }
+
+    if bindings.just_pressed(&keyboard, Action::CycleBrushBiome) {
+        brush.biome = (brush.biome + 1) % Biome::ALL.len();
+        info!("Brush biome: {:?}", Biome::ALL[brush.biome]);
+    }
+
+    if bindings.just_pressed(&keyboard, Action::ShrinkBrush) && brush.radius > 0 {
+        brush.radius -= 1;
+    }
+
+    if bindings.just_pressed(&keyboard, Action::GrowBrush) && brush.radius < MAX_BRUSH_RADIUS {
+        brush.radius += 1;
+    }
+}
+
+fn paint_brush(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    brush: Res<Brush>,
+    config: Res<MapConfig>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    mapgen: Option<Res<MapGen>>,
+    mut overrides: ResMut<BiomeOverrides>,
+) {
+    let mapgen = match mapgen {
+        Some(mapgen) if brush.enabled && mouse.pressed(MouseButton::Left) => mapgen,
+        _ => return,
+    };
+
+    let cursor = match (windows.get_primary(), camera.get_single()) {
+        (Some(window), Ok((transform, projection))) => cursor_world_position(window, transform, projection),
+        _ => None,
+    };
+
+    let cursor = match cursor {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    let center = config.world_to_tile(cursor.x as f64, cursor.y as f64);
+    let biome = Biome::ALL[brush.biome];
+
+    for ((x, y), strength) in brush_tiles(center, brush.radius) {
+        let cell = mapgen.map_cell(x, y);
+        if brush.eraser {
+            overrides.0.remove(&cell);
+            continue;
+        }
+
+        let strength = match overrides.0.get(&cell) {
+            Some(&(painted, previous)) if painted == biome => strength.max(previous),
+            _ => strength,
+        };
+        overrides.0.insert(cell, (biome, strength));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A radius 2 brush covers the 13 tiles within 2 of its center, fully out to 1 and fading beyond
+    #[test]
+    fn radius_two_brush_marks_the_expected_tiles() {
+        let mut tiles = brush_tiles((5, -3), 2);
+        tiles.sort_by_key(|&((x, y), _)| (y, x));
+
+        let mut expected = Vec::new();
+        for dy in -2..=2 {
+            for dx in -2_i32..=2 {
+                let strength = match dx * dx + dy * dy {
+                    0 | 1 => 1.0,
+                    2 => 1.0 - (2.0_f32.sqrt() - 1.0) / 2.0,
+                    4 => 0.5,
+                    _ => continue,
+                };
+                expected.push(((5 + dx, -3 + dy), strength));
+            }
+        }
+
+        assert_eq!(tiles.len(), 13);
+        for ((coords, strength), (expected_coords, expected_strength)) in tiles.into_iter().zip(expected) {
+            assert_eq!(coords, expected_coords);
+            assert!((strength - expected_strength).abs() < 1e-6, "{:?} at strength {}, not {}", coords, strength, expected_strength);
+        }
+    }
+}
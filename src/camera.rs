@@ -0,0 +1,116 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::window::WindowResized;
+
+use crate::config::MapConfig;
+use crate::keybindings::{Action, KeyBindings};
+
+// Smallest projection scale the mouse wheel can magnify to
+const MIN_CAMERA_SCALE: f32 = 0.05;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_camera)
+            .add_system(zoom_camera)
+            .add_system(fit_map_to_window);
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+}
+
+// Magnifies the rendered map without regenerating it, unlike the Up/Down zoom
+fn zoom_camera(
+    mut wheel: EventReader<MouseWheel>,
+    mut query: Query<&mut OrthographicProjection, With<Camera>>,
+) {
+    let scroll: f32 = wheel.iter().map(|event| match event.unit {
+        MouseScrollUnit::Line => event.y,
+        MouseScrollUnit::Pixel => event.y / 100.0,
+    }).sum();
+
+    if scroll == 0.0 {
+        return;
+    }
+
+    // A view fitted to a map larger than the window starts above 1, and scrolling shouldn't jump from there
+    for mut projection in query.iter_mut() {
+        projection.scale = (projection.scale * 0.9_f32.powf(scroll)).clamp(MIN_CAMERA_SCALE, projection.scale.max(1.0));
+    }
+}
+
+// Projection scale at which the whole map, 2 * map_axis_len world units a side, just fits a window
+// of the given logical size
+pub fn fit_scale(map_axis_len: f64, window_width: f32, window_height: f32) -> f32 {
+    let extent = 2.0 * map_axis_len as f32;
+
+    (extent / window_width.max(1.0)).max(extent / window_height.max(1.0))
+}
+
+// Frames the whole map around the origin on the fit key, and with fit_map_to_window also at startup
+// and whenever the window is resized. Only the camera moves, the map isn't regenerated
+fn fit_map_to_window(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Res<Windows>,
+    config: Res<MapConfig>,
+    mut resized: EventReader<WindowResized>,
+    mut fitted_once: Local<bool>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
+) {
+    let was_resized = resized.iter().count() > 0;
+    let automatic = config.fit_map_to_window && (was_resized || !*fitted_once);
+    if !automatic && !bindings.just_pressed(&keyboard, Action::FitMapToWindow) {
+        return;
+    }
+
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    *fitted_once = true;
+
+    let scale = fit_scale(config.map_axis_len(), window.width(), window.height());
+    for (mut transform, mut projection) in query.iter_mut() {
+        transform.translation.x = 0.0;
+        transform.translation.y = 0.0;
+        projection.scale = scale;
+    }
+}
+
+// World position under the cursor, accounting for the camera's pan and magnification
+pub fn cursor_world_position(window: &Window, transform: &Transform, projection: &OrthographicProjection) -> Option<Vec2> {
+    let cursor = window.cursor_position()?;
+    let window_size = Vec2::new(window.width(), window.height());
+
+    Some((cursor - window_size / 2.0) * projection.scale + transform.translation.truncate())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The fitted view of maps of several sizes in windows of several shapes holds the whole map, touching
+    // the window on at least one axis so it isn't framed smaller than it has to be
+    #[test]
+    fn fitted_view_holds_the_whole_map() {
+        for (map_size, tile_scale) in [(250, 0.25), (64, 1.0), (1001, 0.1)] {
+            let config = MapConfig { map_size, tile_scale, ..Default::default() };
+            let extent = 2.0 * config.map_axis_len() as f32;
+            let tolerance = extent * 1e-4;
+
+            for (width, height) in [(1280.0, 720.0), (720.0, 1280.0), (800.0, 800.0), (3840.0, 400.0)] {
+                let scale = fit_scale(config.map_axis_len(), width, height);
+                let (visible_width, visible_height) = (width * scale, height * scale);
+
+                assert!(extent <= visible_width + tolerance && extent <= visible_height + tolerance,
+                    "map {} overflows a {}x{} window", map_size, width, height);
+                assert!((visible_width - extent).abs() <= tolerance || (visible_height - extent).abs() <= tolerance,
+                    "map {} leaves room on both axes of a {}x{} window", map_size, width, height);
+            }
+        }
+    }
+}
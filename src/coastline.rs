@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+
+use crate::config::{DisplayConfig, MapConfig};
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::MapGrid;
+
+// Line thickness as a share of a tile's width
+const COASTLINE_WIDTH: f32 = 0.15;
+
+// Above the tiles, below path markers and landmass labels
+const COASTLINE_Z: f32 = 0.7;
+
+pub struct CoastlinePlugin;
+
+#[derive(Resource)]
+struct ShowCoastline(bool);
+
+#[derive(Component)]
+struct CoastlineSegment;
+
+impl Plugin for CoastlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowCoastline(false))
+            .add_system(toggle_coastline)
+            .add_system(update_coastline);
+    }
+}
+
+fn toggle_coastline(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut show: ResMut<ShowCoastline>) {
+    if bindings.just_pressed(&keyboard, Action::ToggleCoastline) {
+        show.0 = !show.0;
+    }
+}
+
+// Redraws the shoreline along the shared side of every land and ocean tile pair whenever
+// the map, the toggle or the color changes
+fn update_coastline(
+    mut commands: Commands,
+    config: Res<MapConfig>,
+    display: Res<DisplayConfig>,
+    show: Res<ShowCoastline>,
+    grid: Option<Res<MapGrid>>,
+    segments: Query<Entity, With<CoastlineSegment>>,
+) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
+
+    if !grid.is_changed() && !show.is_changed() && !display.is_changed() {
+        return;
+    }
+
+    for entity in segments.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    if !show.0 {
+        return;
+    }
+
+    let length = (config.world_units_per_tile() * grid.stride as f64) as f32;
+    let thickness = length * COASTLINE_WIDTH;
+
+    for (from, to) in grid.coastline_edges() {
+        let (from_x, from_y) = config.tile_to_world(from.0, from.1);
+        let (to_x, to_y) = config.tile_to_world(to.0, to.1);
+
+        let size = if from.1 == to.1 {
+            Vec2::new(thickness, length)
+        } else {
+            Vec2::new(length, thickness)
+        };
+
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: display.coastline_color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_xyz(((from_x + to_x) / 2.0) as f32, ((from_y + to_y) / 2.0) as f32, COASTLINE_Z),
+                ..default()
+            },
+            CoastlineSegment,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::{Biome, TileSample};
+
+    // Land rows of a synthetic grid, top row first: a bay in the top left corner with 6 shore edges, a
+    // one tile lake with 4 and an ocean corner tile with 2
+    const GRID: [&str; 5] = [
+        "OOOLL",
+        "OLLLL",
+        "OLOLL",
+        "LLLLL",
+        "LLLLO",
+    ];
+
+    // Every side a land tile shares with an ocean tile is one edge, and no other side is
+    #[test]
+    fn coastline_follows_every_shore_once() {
+        let samples = GRID.iter().rev()
+            .flat_map(|row| row.chars())
+            .map(|letter| {
+                let biome = if letter == 'O' { Biome::Ocean } else { Biome::TemperateSteppe };
+                TileSample { height: 0.0, temperature: 0.0, percipitation: 0.0, falloff: 0.0, biome }
+            })
+            .collect();
+        let grid = MapGrid { half_size: GRID.len() as i32 / 2, stride: 1, tiles: Vec::new(), samples };
+        let edges = grid.coastline_edges();
+
+        assert_eq!(edges.len(), 12);
+        for &(from, to) in &edges {
+            let is_ocean = |(x, y): (i32, i32)| grid.samples[grid.index(x, y).unwrap()].biome == Biome::Ocean;
+            assert_ne!(is_ocean(from), is_ocean(to), "{:?} {:?} is no shore", from, to);
+        }
+    }
+}
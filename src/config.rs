@@ -0,0 +1,1205 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::biome_table::BiomeTable;
+use crate::error::{Error, Result};
+use crate::land_mask::LandMask;
+use crate::overlay::CLIMATE_ZONE_COLORS;
+use crate::tilemap::{Biome, Seed, SeedOrigin, MAX_SHIFT};
+
+pub const FONT_PATH: &str = "fonts/DejaVuSansMono.ttf";
+
+// Erosion passes run on the oldest possible world
+const MAX_EROSION_ITERATIONS: u32 = 20;
+
+// Tile scale the noise space is laid out at, so tile_scale only sizes the sprites and a zoom shows the
+// same terrain at any tile_scale. It is the default tile_scale, keeping maps made at that scale as they were
+const NOISE_TILE_SCALE: f64 = 0.25;
+
+// For f64 fields that may be infinite, which JSON has no number for. Infinities are written as "inf" and
+// "-inf", and read back from those or from plain numbers
+mod unbounded {
+    use bevy::utils::HashMap;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::tilemap::Biome;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Number(f64),
+        Text(String),
+    }
+
+    impl Value {
+        fn new(value: f64) -> Self {
+            match value {
+                value if value == f64::INFINITY => Value::Text("inf".to_string()),
+                value if value == f64::NEG_INFINITY => Value::Text("-inf".to_string()),
+                value => Value::Number(value),
+            }
+        }
+
+        fn number<E: serde::de::Error>(self) -> Result<f64, E> {
+            match self {
+                Value::Number(value) => Ok(value),
+                Value::Text(text) => text.parse().map_err(E::custom),
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        Value::new(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Value::deserialize(deserializer)?.number()
+    }
+
+    // The same for each value of a per-biome map, as movement_costs
+    pub mod biome_map {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(map: &HashMap<Biome, f32>, serializer: S) -> Result<S::Ok, S::Error> {
+            map.iter().map(|(&biome, &value)| (biome, Value::new(value as f64))).collect::<HashMap<_, _>>().serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<Biome, f32>, D::Error> {
+            HashMap::<Biome, Value>::deserialize(deserializer)?.into_iter()
+                .map(|(biome, value)| Ok((biome, value.number()? as f32)))
+                .collect()
+        }
+    }
+}
+
+// Movement cost of tiles that cannot be entered
+pub const IMPASSABLE_COST: f32 = f32::INFINITY;
+
+// Altitude band [m] in which the lapse rate reverses sign, so air warms with height
+#[derive(Clone, Deserialize, Serialize)]
+pub struct InversionBand {
+    pub bottom: f64,
+    pub top: f64,
+}
+
+// How the channel values the biome tiles compute are read
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorSpace {
+    // As sRGB, the way Color::rgb takes them
+    Srgb,
+    // As linear light, converted with Color::rgb_linear
+    Linear,
+}
+
+// How scaled tiles are sampled from the atlas. Nearest keeps hard pixel-art edges at any
+// magnification; linear smooths them but blurs the art and bleeds neighbouring atlas tiles at the seams
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TileFiltering {
+    Nearest,
+    Linear,
+}
+
+// How the on-screen seed is written
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SeedFormat {
+    Decimal,
+    Hex,
+    // The text a hashed seed came from, numbers in decimal
+    Original,
+}
+
+// Classification of land into biomes: the full Holdridge life zones, or just snow, tundra, desert,
+// grassland and forest
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum BiomeModel {
+    Holdridge,
+    Simple,
+}
+
+// How humidity maps onto percipitation. Linear spreads it evenly up to 16000 mm, which leaves most
+// tiles near the ends of the range; log spreads it by ratio, as the biome breakpoints at 125, 250,
+// 500 mm and so on are, so the middle biomes get more room
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum PercipitationScale {
+    Linear,
+    Log,
+}
+
+// What the coast searches find past the map edge, where the map has no tiles: ocean, land, or the
+// map mirrored back across the edge
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum EdgePolicy {
+    Ocean,
+    Land,
+    Mirror,
+}
+
+// Which grid neighbours join a biome region: the four sharing an edge, or the diagonals as well
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+impl Connectivity {
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            Connectivity::Four => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+            Connectivity::Eight => &[(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)],
+        }
+    }
+}
+
+// How land tiles are colored: by their biome, or straight from their climate through
+// MapConfig::continuous_coloring, which shades smoothly across biome borders. Either way the tiles
+// keep their biome for everything else that reads it
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum ColoringMode {
+    Biome,
+    Continuous,
+}
+
+// Land colors of the continuous coloring mode at the corners of the climate it spans, blended
+// bilinearly by temperature and percipitation, then toward peak_color from peak_start_height up to
+// the highest terrain
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+pub struct ContinuousColoring {
+    pub cold_dry: Color,
+    pub cold_wet: Color,
+    pub hot_dry: Color,
+    pub hot_wet: Color,
+    pub peak_color: Color,
+    pub peak_start_height: f64,
+}
+
+// Cloud layer drifting over the biome view, downwind along MapConfig::wind's direction
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Clouds {
+    // Share of the sky covered, 0 clear to 1 overcast
+    pub density: f32,
+    // Drift [tiles per GenTime second]
+    pub speed: f32,
+    // Width [tiles] of a typical cloud
+    pub size: f64,
+    // Whitening under full cover, low so the map shows through
+    pub opacity: f32,
+    // GenTime [s] the clouds stay where they were at instead of drifting, for screenshots
+    pub frozen_at: Option<f32>,
+}
+
+// Background layer behind the map, scrolling by a share of each pan so it reads as far away
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParallaxLayer {
+    // Image under assets/, None for a generated starfield
+    pub texture: Option<String>,
+    // Share of the pan the layer follows, 0 staying put to 1 keeping pace with the terrain
+    pub speed: f32,
+    // World units the texture spans before repeating
+    pub repeat: f32,
+    pub tint: Color,
+}
+
+// Ocean colors at and above warm_temperature [C] and at and below cold_temperature [C], blended
+// between by the sea surface temperature, so tropical seas run turquoise and polar ones dark
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+pub struct OceanColoring {
+    pub warm_color: Color,
+    pub cold_color: Color,
+    pub warm_temperature: f64,
+    pub cold_temperature: f64,
+}
+
+// Speed against fidelity in one setting, overriding the fields it bundles: noise octaves, the tile
+// entity budget the level of detail follows and the biome smoothing passes
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum Quality {
+    // Fast enough for interactive tuning
+    Draft,
+    // The defaults of the bundled fields
+    Normal,
+    // For final exports
+    High,
+}
+
+// Lines the parameter overlay can show, see DisplayConfig::parameter_fields
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParameterField {
+    Seed,
+    Zoom,
+    Shift,
+    NoiseOffset,
+    MapSize,
+    WorldAge,
+    BiomeModel,
+    Quality,
+    Wind,
+}
+
+// What the tile colors show
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugView {
+    Biome,
+    // Grayscale land and blue water by height, see height_exaggeration
+    Height,
+    // Temperature in red, height in green and moisture in blue, each normalized to its full generated range
+    Combined,
+    // Cheap tiles in green through expensive ones in red, impassable ones black
+    MovementCost,
+    // The radial falloff scaling the height noise, black where it sinks everything
+    Falloff,
+}
+
+// How an exported tile's sub-tile samples make its color, see MapConfig::export_subsamples
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum SubsampleMode {
+    // The color of the most common biome among the samples
+    Majority,
+    // The average of the samples' colors
+    Blend,
+}
+
+// How the Up/Down zoom feeds into the noise scale of every NoiseMap
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub enum ZoomScaling {
+    // scale * zoom: the coordinate change and the scale change cancel, so zooming reveals finer detail
+    Linear,
+    // Scale is untouched, zooming magnifies the same terrain
+    None,
+    // scale / zoom: zooming in shows coarser, smoother features
+    Inverse,
+}
+
+// Prevailing wind that every wind-driven climate effect reads, so they all agree on one direction
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+pub struct Wind {
+    // Compass bearing [degrees] the wind blows from, 0 from the north and 90 from the east
+    pub direction: f64,
+    // 0 is calm and turns every wind effect off, 1 is full strength
+    pub strength: f64,
+}
+
+// Aerial perspective laid over the biome and height views, fading tiles toward color with altitude
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Haze {
+    pub color: Color,
+    // Height [m] the haze starts at, thickening linearly to the highest terrain
+    pub start_height: f64,
+    // Share of the haze color at the highest terrain, 1 hides the peaks entirely
+    pub strength: f32,
+}
+
+// Shadows cast by tall terrain across the tiles behind it, away from the sun
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Shadows {
+    // Compass bearing [degrees] the sun shines from, 0 north and 90 east
+    pub azimuth: f64,
+    // Sun height [degrees] above the horizon, low suns cast long shadows
+    pub elevation: f64,
+    // Ground distance [m] across one tile, setting how steep the terrain is next to the sun's rays
+    pub tile_length: f64,
+    // Share of the light shadowed tiles lose
+    pub darkness: f32,
+}
+
+// Units: the atlas holds tile_size x tile_size pixel tiles, each sprite is drawn tile_scale times
+// that size, and one world unit is one screen pixel at the default camera scale. Generation works in
+// noise units instead, which don't follow tile_scale. Presets are read into it from RON, fields they
+// leave out keeping their defaults
+#[derive(Resource, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MapConfig {
+    // Tiles from one map edge to the other, the spawned grid spans map_size / 2 tiles each way
+    pub map_size: u32,
+    // Atlas pixels per tile
+    pub tile_size: f64,
+    // Sprite scale applied to the atlas tiles
+    pub tile_scale: f64,
+    pub inversions: Vec<InversionBand>,
+    #[serde(skip)]
+    pub biome_table: Option<BiomeTable>,
+    // Black and white image, white for land, that generation keeps land and ocean to
+    pub land_mask_path: Option<String>,
+    #[serde(skip)]
+    pub land_mask: Option<LandMask>,
+    // The simple model ignores biome_table, marsh, mangroves, alpine rock, volcanoes and ice sheets
+    pub biome_model: BiomeModel,
+    pub coloring_mode: ColoringMode,
+    pub continuous_coloring: ContinuousColoring,
+    // Same warm and cold colors give the whole ocean one color, in either coloring mode
+    pub ocean_coloring: OceanColoring,
+    // Generate a small map twice at startup and log an error if the two differ
+    pub check_determinism: bool,
+    // Log the biomes this config can never generate at startup, see MapGen::reachable_biomes
+    pub report_unreachable_biomes: bool,
+    // Start on the seed shared by everyone today instead of the fixed default
+    pub daily_seed: bool,
+    // Seeds whose height fields are mixed by weight in place of the map seed's, weights summing to 1.
+    // Empty uses the map seed alone; temperature and humidity always follow the map seed
+    pub seed_blend: Vec<(u32, f64)>,
+    // Seconds the seed must stay unchanged before the map regenerates, so a burst of seed steps
+    // regenerates once. 0 regenerates on every change
+    pub seed_debounce: f32,
+    // Load bookmarks from and save them to BOOKMARKS_PATH
+    pub persist_bookmarks: bool,
+    // Reopen the window at the size and position it was closed at, saved to WINDOW_PATH
+    pub persist_window: bool,
+    // How fast the humidity from the equatorial seas falls off with latitude, and the lowest it may fall to.
+    // The unbounded default lets the poles pull humidity down rather than just add nothing
+    pub water_dist_falloff: f64,
+    #[serde(with = "unbounded")]
+    pub water_dist_min: f64,
+    // Shares of humidity from nearby warm water, latitude distance to the equatorial seas and noise.
+    // Must not be negative and sum to 1
+    pub humidity_weights: (f64, f64, f64),
+    // Changes the generated maps, not just their colors: the log scale moves much of the land into
+    // drier biomes than the linear default gives it
+    pub percipitation_scale: PercipitationScale,
+    // Octaves of the humidity noise are summed without normalizing, so a higher persistance raises the
+    // field toward its clamp at 1 as much as it adds detail
+    pub humidity_octaves: u32,
+    // Octaves summed for the height and temperature noise
+    pub height_octaves: u32,
+    pub temperature_octaves: u32,
+    pub humidity_persistance: f64,
+    pub humidity_lacunarity: f64,
+    // Noise space offset the falloff's height noise is sampled at, independent of the terrain's. None
+    // samples it at the mirrored point (-x, -y) as older versions did, which made coastlines near the
+    // center echo each other through the origin; set it to reproduce maps made before the change
+    pub falloff_noise_offset: Option<(f64, f64)>,
+    // Percipitation [mm] the temperature-based percipitation cap rises by per m of height, so uplift
+    // on mountains can hold more rain than the lowlands at the same temperature
+    pub orographic_cap: f64,
+    // How sharply percipitation meets the cap. Finite values roll it off over 1000 / hardness mm below
+    // the cap so hot, wet tiles keep their differences; infinity clamps it flat at the cap
+    #[serde(with = "unbounded")]
+    pub percipitation_cap_hardness: f64,
+    // Drives the rain shadow: mountains upwind of a tile take moisture out of its percipitation
+    pub wind: Wind,
+    // Percipitation [mm] above a desert over which computed grass colors fade toward sand, 0 for a hard edge
+    pub desert_blend_width: f64,
+    // Land at or above this height [m] is bare alpine rock at any latitude
+    pub alpine_min_height: f64,
+    // Warm, very wet land below this height [m] becomes marsh
+    pub marsh_max_height: f64,
+    pub marsh_min_percipitation: f64,
+    pub marsh_min_temperature: f64,
+    // Hot, nearly rainless land below this height [m] dries out into salt flats
+    pub salt_flat_max_height: f64,
+    pub salt_flat_max_percipitation: f64,
+    pub salt_flat_min_temperature: f64,
+    // Frozen land with at least this percipitation [mm] is an ice sheet instead of a polar desert.
+    // Below freezing the air's cap holds percipitation under zero, so the split sits there too
+    pub ice_sheet_min_percipitation: f64,
+    // Share of the volcano hotspot cells, a few per map at the default zoom, that hold a volcano. Only
+    // the parts of a volcano's cone at least volcano_min_height [m] high show up, whatever the climate
+    pub volcano_density: f64,
+    pub volcano_min_height: f64,
+    // Forests give way to tundra above treeline_height [m] plus treeline_warmth_rise [m per C] of the
+    // tile's temperature, so warm mountains keep trees higher up. Fine noise moves the line up or down
+    // by up to treeline_raggedness [m]; 0 draws it as a clean contour
+    pub treeline_height: f64,
+    pub treeline_warmth_rise: f64,
+    pub treeline_raggedness: f64,
+    // Warm, wet land below this height [m] within mangrove_coast_distance tiles of the ocean grows mangroves
+    pub mangrove_max_height: f64,
+    pub mangrove_min_percipitation: f64,
+    pub mangrove_min_temperature: f64,
+    pub mangrove_coast_distance: u32,
+    // Decides how coastal the map borders look when the falloff leaves land at the edge
+    pub edge_policy: EdgePolicy,
+    // Widest beach [tiles], reached on the gentlest coasts
+    pub max_beach_width: f64,
+    // Coast slope [m per tile] at and above which no beach forms
+    pub beach_max_slope: f64,
+    // 0 for a freshly raised world, 1 for the oldest, see erosion_iterations and vegetation_density
+    pub world_age: f64,
+    // Slope [m per tile] erosion wears steeper terrain down to
+    pub erosion_talus: f64,
+    // Random and launch seeds with less land than this share are rerolled, up to max_reseed_attempts
+    // seeds in all. 0 accepts any seed
+    pub min_land_fraction: f64,
+    pub max_reseed_attempts: u32,
+    // Shift + the random seed key looks, within max_reseed_attempts seeds, for one showing at least this
+    // share of land and this many land biomes in the visible part of the current view
+    pub view_min_land_fraction: f64,
+    pub view_min_biomes: usize,
+    // Smoothing passes over the biome grid, each tile taking the most common land biome within radius tiles.
+    // 0 passes keeps every patch
+    pub biome_filter_radius: i32,
+    pub biome_filter_passes: u32,
+    // Above this many tile sprites, only every n-th tile is spawned, n times larger
+    pub max_tile_entities: usize,
+    // A new map first shows every preview_stride-th tile, replaced by the full map once a background
+    // thread has generated it. Ignored in low memory mode and unless coarser than the full map's stride
+    pub preview_stride: i32,
+    // Spawn tiles as they are generated without keeping a MapGrid resource, saving a copy of every tile
+    // and sample. Everything reading the grid goes without: the inspector, coastline, path finding,
+    // explore mode, landmass labels and the tile export. Needs biome_filter_passes at 0, as the
+    // filter looks at every tile's neighbours before any tile is final
+    pub low_memory: bool,
+    // Tiles sampled outside the grid, by hover reports and other point queries, that are kept for reuse
+    // until the map is regenerated, least recently used first out. 0 samples every query afresh
+    pub tile_cache_size: usize,
+    // CSV file each generated map adds a row of stage timings to, see profile::Stage. Low memory
+    // mode streams its tiles unprofiled
+    pub profile_path: Option<String>,
+    // Seconds the app's map generation may take before it gives up on the map with an error, None
+    // waits however long it takes. Checked between rows and passes, so it overruns by at most one
+    pub generation_budget: Option<f64>,
+    // Neighbours that join tiles of one biome into a region, see GridFields::regions
+    pub region_connectivity: Connectivity,
+    // Height [m] at and above which the river overlay starts rivers, see hydrology.rs
+    pub river_source_height: f64,
+    // Rivers reaching a lake carry on out over its lowest rim instead of ending in it
+    pub lake_overflow: bool,
+    pub zoom_scaling: ZoomScaling,
+    // Applied over the fields it bundles at startup and whenever it is switched
+    pub quality: Quality,
+    // View the app launches at, shifts within MAX_SHIFT either way
+    pub initial_zoom: f64,
+    pub initial_x_shift: f64,
+    pub initial_y_shift: f64,
+    // Zoom keeps the terrain under the cursor in place instead of the view center
+    pub zoom_to_cursor: bool,
+    // Frame the whole map in the window at startup and on every resize, as the fit key does
+    pub fit_map_to_window: bool,
+    // Gamma exported images are encoded with on top of the sRGB tile colors, 1 writes them as is
+    pub export_gamma: f32,
+    // Width [px] of the square each tile is drawn as in exported images
+    pub export_tile_pixels: u32,
+    // Biome samples per exported tile along each axis, classified afresh across the tile and combined
+    // by export_subsample_mode. 1 exports each tile as the grid has it
+    pub export_subsamples: u32,
+    pub export_subsample_mode: SubsampleMode,
+    // Write a manifest.json of checksums beside exports of several files, see export::Manifest
+    pub export_manifest: bool,
+    // Point of each tile sprite placed on its tile position
+    #[serde(skip)]
+    pub tile_anchor: Anchor,
+    // Round tile positions to whole screen pixels, see snap_tiles_to_pixels
+    pub pixel_snap: bool,
+    // Flip and turn tile art by a per-tile hash, except on biomes whose art has a direction
+    pub tile_flips: bool,
+    pub directional_biomes: HashSet<Biome>,
+    // Cost of crossing a flat tile of each biome, biomes left out cost 1
+    #[serde(with = "unbounded::biome_map")]
+    pub movement_costs: HashMap<Biome, f32>,
+    // Extra cost per m of height change across a tile, multiplying the biome cost
+    pub slope_movement_cost: f32,
+    // Relative chance of MapGen::pick_spawn choosing a qualifying tile of each biome, biomes left out
+    // weigh 1 and 0 rules a biome out
+    pub spawn_weights: HashMap<Biome, f32>,
+}
+
+// Settings that only recolor the spawned tiles, so changing them never regenerates the map
+#[derive(Resource, Clone)]
+pub struct DisplayConfig {
+    pub climate_zone_colors: [Color; 6],
+    // Ordered dither amplitude in 8-bit color steps, 0 disables it
+    pub dither_strength: f32,
+    pub day_night: bool,
+    // Length of a full day/night cycle in GenTime seconds
+    pub day_length: f32,
+    // Share of a day (0 noon, 0.5 midnight) the day/night tint stays at instead of following GenTime
+    pub frozen_time_of_day: Option<f32>,
+    pub debug_view: DebugView,
+    pub seed_format: SeedFormat,
+    // Applies to the biome colors only, the overlays and debug views are always sRGB
+    pub color_space: ColorSpace,
+    // Adjustments to the biome colors, 1 leaves them as generated
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    // Vertical exaggeration of the height view, display only
+    pub height_exaggeration: f32,
+    // None leaves the views clear
+    pub haze: Option<Haze>,
+    // None leaves the sky clear
+    pub clouds: Option<Clouds>,
+    // None casts no shadows. Recomputed over the whole grid whenever the map changes
+    pub shadows: Option<Shadows>,
+    // Drawn back to front behind the map, empty leaves the background plain
+    pub parallax: Vec<ParallaxLayer>,
+    // Crossfade [GenTime seconds] from the old map to the new one when the seed changes, 0 swaps instantly
+    pub seed_fade: f32,
+    pub coastline_color: Color,
+    // Biomes outlined where they meet other biomes, picked in the legend. Empty outlines none
+    pub outlined_biomes: Vec<Biome>,
+    pub outline_color: Color,
+    pub tile_filtering: TileFiltering,
+    // Samples per pixel, 1 or 4 as wgpu supports. 4 smooths sprite edges at some GPU cost
+    pub msaa_samples: u32,
+    // Log the biome at the view center after every regeneration
+    pub report_center_biome: bool,
+    // Lines of the parameter overlay, in order
+    pub parameter_fields: Vec<ParameterField>,
+}
+
+// Open land is cheapest, forests and tundra slower, marsh, mangroves, alpine rock, volcanoes and ice sheets slowest and the ocean impassable
+fn default_movement_costs() -> HashMap<Biome, f32> {
+    Biome::ALL.into_iter().map(|biome| (biome, default_movement_cost(biome))).collect()
+}
+
+fn default_movement_cost(biome: Biome) -> f32 {
+    match biome {
+        Biome::Ocean => IMPASSABLE_COST,
+
+        Biome::Marsh | Biome::Mangrove | Biome::Alpine | Biome::Volcanic | Biome::IceSheet => 4.0,
+
+        Biome::BorealWetForest | Biome::BorealRainForest
+        | Biome::TemperateWetForest | Biome::TemperateRainForest
+        | Biome::SubtropicalWetForest | Biome::SubtropicalRainForest
+        | Biome::TropicalWetForest | Biome::TropicalRainForest => 3.0,
+
+        Biome::SubpolarDryTundra | Biome::SubpolarMoistTundra | Biome::SubpolarWetTundra | Biome::SubpolarRainTundra
+        | Biome::BorealMoistForest
+        | Biome::TemperateMoistForest
+        | Biome::SubtropicalThornWoodland | Biome::SubtropicalDryForest | Biome::SubtropicalMoistForest
+        | Biome::TropicalThornWoodland | Biome::TropicalVeryDryForest | Biome::TropicalDryForest
+        | Biome::TropicalMoistForest => 2.0,
+
+        Biome::PolarDesert
+        | Biome::BorealDesert | Biome::BorealDryScrub
+        | Biome::TemperateDesert | Biome::TemperateDesertScrub
+        | Biome::SubtropicalDesert | Biome::SubtropicalDesertScrub
+        | Biome::TropicalDesert | Biome::TropicalDesertScrub => 1.5,
+
+        Biome::TemperateSteppe | Biome::Beach | Biome::SaltFlat => 1.0,
+    }
+}
+
+// Mild, wet land favoured, barren and frozen land left as a last resort
+fn default_spawn_weights() -> HashMap<Biome, f32> {
+    let mut weights = HashMap::default();
+
+    for biome in Biome::ALL {
+        let name = format!("{:?}", biome);
+        let weight = if biome == Biome::Ocean {
+            0.0
+        } else if biome == Biome::PolarDesert || biome == Biome::IceSheet || biome == Biome::Alpine || biome == Biome::Volcanic {
+            0.1
+        } else if name.ends_with("Desert") || name.ends_with("Tundra") {
+            0.5
+        } else if (name.starts_with("Temperate") || name.starts_with("Subtropical")) && !name.ends_with("Scrub") {
+            3.0
+        } else {
+            1.0
+        };
+
+        weights.insert(biome, weight);
+    }
+
+    weights
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self {
+            map_size: 250,
+            tile_size: 16.0,
+            tile_scale: 0.25,
+            inversions: Vec::new(),
+            biome_table: None,
+            land_mask_path: None,
+            land_mask: None,
+            biome_model: BiomeModel::Holdridge,
+            coloring_mode: ColoringMode::Biome,
+            continuous_coloring: ContinuousColoring {
+                cold_dry: Color::rgb(0.80, 0.80, 0.78),
+                cold_wet: Color::rgb(0.45, 0.55, 0.50),
+                hot_dry: Color::rgb(0.85, 0.72, 0.45),
+                hot_wet: Color::rgb(0.10, 0.40, 0.12),
+                peak_color: Color::rgb(0.95, 0.95, 0.97),
+                peak_start_height: 3000.0,
+            },
+            ocean_coloring: OceanColoring {
+                warm_color: Color::rgb(0.0, 0.5, 0.8),
+                cold_color: Color::rgb(0.05, 0.2, 0.4),
+                warm_temperature: 25.0,
+                cold_temperature: 0.0,
+            },
+            check_determinism: false,
+            report_unreachable_biomes: false,
+            daily_seed: false,
+            seed_blend: Vec::new(),
+            seed_debounce: 0.0,
+            persist_bookmarks: false,
+            persist_window: false,
+            water_dist_falloff: 5.5,
+            water_dist_min: f64::NEG_INFINITY,
+            humidity_weights: (0.40, 0.30, 0.30),
+            percipitation_scale: PercipitationScale::Linear,
+            humidity_octaves: 8,
+            height_octaves: 24,
+            temperature_octaves: 24,
+            humidity_persistance: 0.08,
+            humidity_lacunarity: 1.2,
+            falloff_noise_offset: Some((7919.0, -6481.0)),
+            orographic_cap: 0.0,
+            percipitation_cap_hardness: f64::INFINITY,
+            wind: Wind { direction: 270.0, strength: 0.0 },
+            desert_blend_width: 200.0,
+            alpine_min_height: 4500.0,
+            marsh_max_height: 60.0,
+            marsh_min_percipitation: 4000.0,
+            marsh_min_temperature: 12.0,
+            salt_flat_max_height: 100.0,
+            salt_flat_max_percipitation: 40.0,
+            salt_flat_min_temperature: 20.0,
+            ice_sheet_min_percipitation: -1500.0,
+            volcano_density: 0.3,
+            volcano_min_height: 2000.0,
+            treeline_height: 2500.0,
+            treeline_warmth_rise: 50.0,
+            treeline_raggedness: 200.0,
+            mangrove_max_height: 20.0,
+            mangrove_min_percipitation: 4000.0,
+            mangrove_min_temperature: 20.0,
+            mangrove_coast_distance: 1,
+            edge_policy: EdgePolicy::Ocean,
+            max_beach_width: 3.0,
+            beach_max_slope: 150.0,
+            world_age: 0.0,
+            erosion_talus: 40.0,
+            min_land_fraction: 0.0,
+            max_reseed_attempts: 20,
+            view_min_land_fraction: 0.1,
+            view_min_biomes: 4,
+            biome_filter_radius: 1,
+            biome_filter_passes: 0,
+            max_tile_entities: 100_000,
+            preview_stride: 1,
+            low_memory: false,
+            tile_cache_size: 4096,
+            profile_path: None,
+            generation_budget: None,
+            region_connectivity: Connectivity::Four,
+            river_source_height: 1500.0,
+            lake_overflow: false,
+            zoom_scaling: ZoomScaling::Linear,
+            quality: Quality::Normal,
+            initial_zoom: 1.0,
+            initial_x_shift: 0.0,
+            initial_y_shift: 0.0,
+            zoom_to_cursor: false,
+            fit_map_to_window: false,
+            export_gamma: 1.0,
+            export_tile_pixels: 1,
+            export_subsamples: 1,
+            export_subsample_mode: SubsampleMode::Majority,
+            export_manifest: true,
+            tile_anchor: Anchor::Center,
+            pixel_snap: false,
+            tile_flips: false,
+            directional_biomes: HashSet::default(),
+            movement_costs: default_movement_costs(),
+            slope_movement_cost: 0.01,
+            spawn_weights: default_spawn_weights(),
+        }
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            climate_zone_colors: CLIMATE_ZONE_COLORS,
+            dither_strength: 0.0,
+            day_night: false,
+            day_length: 60.0,
+            frozen_time_of_day: None,
+            debug_view: DebugView::Biome,
+            seed_format: SeedFormat::Decimal,
+            coastline_color: Color::rgb(0.05, 0.1, 0.2),
+            outlined_biomes: Vec::new(),
+            outline_color: Color::rgb(1.0, 0.85, 0.2),
+            tile_filtering: TileFiltering::Nearest,
+            msaa_samples: 4,
+            color_space: ColorSpace::Srgb,
+            brightness: 1.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            height_exaggeration: 1.0,
+            haze: None,
+            clouds: None,
+            shadows: None,
+            parallax: Vec::new(),
+            seed_fade: 0.0,
+            report_center_biome: false,
+            parameter_fields: vec![
+                ParameterField::Seed,
+                ParameterField::Zoom,
+                ParameterField::Shift,
+                ParameterField::NoiseOffset,
+                ParameterField::MapSize,
+                ParameterField::WorldAge,
+                ParameterField::BiomeModel,
+                ParameterField::Quality,
+                ParameterField::Wind,
+            ],
+        }
+    }
+}
+
+impl Wind {
+    // Unit vector toward where the wind comes from, y pointing north
+    pub fn upwind(&self) -> (f64, f64) {
+        let bearing = self.direction.to_radians();
+        (bearing.sin(), bearing.cos())
+    }
+}
+
+impl PercipitationScale {
+    // Percipitation [mm] before the rain shadow and cap. Both scales give 0 at humidity 0 and MAX at 1;
+    // the log scale roughly doubles with every eighth of the humidity range, as the biome breakpoints
+    // do. Humidity below 0, where the poles pull it down, stays linear on either
+    pub fn percipitation(self, humidity: f64) -> f64 {
+        const MAX: f64 = 16000.0;
+        const LOG_STEP: f64 = 62.5;
+
+        match self {
+            PercipitationScale::Log if humidity > 0.0 => LOG_STEP * ((MAX / LOG_STEP + 1.0).powf(humidity) - 1.0),
+            _ => MAX * humidity,
+        }
+    }
+}
+
+impl ZoomScaling {
+    pub fn factor(self, zoom: f64) -> f64 {
+        match self {
+            ZoomScaling::Linear => zoom,
+            ZoomScaling::None => 1.0,
+            ZoomScaling::Inverse => 1.0 / zoom,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ZoomScaling::Linear => ZoomScaling::None,
+            ZoomScaling::None => ZoomScaling::Inverse,
+            ZoomScaling::Inverse => ZoomScaling::Linear,
+        }
+    }
+}
+
+impl ColorSpace {
+    pub fn interpret(self, color: Color) -> Color {
+        match self {
+            ColorSpace::Srgb => color,
+            ColorSpace::Linear => Color::rgba_linear(color.r(), color.g(), color.b(), color.a()),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ColorSpace::Srgb => ColorSpace::Linear,
+            ColorSpace::Linear => ColorSpace::Srgb,
+        }
+    }
+}
+
+impl SeedFormat {
+    pub fn format(self, seed: &Seed) -> String {
+        match (self, &seed.1) {
+            (SeedFormat::Hex, _) => format!("{:#010x}", seed.0),
+            (SeedFormat::Original, SeedOrigin::Text(text)) => format!("\"{}\"", text),
+            _ => seed.0.to_string(),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SeedFormat::Decimal => SeedFormat::Hex,
+            SeedFormat::Hex => SeedFormat::Original,
+            SeedFormat::Original => SeedFormat::Decimal,
+        }
+    }
+}
+
+impl TileFiltering {
+    pub fn next(self) -> Self {
+        match self {
+            TileFiltering::Nearest => TileFiltering::Linear,
+            TileFiltering::Linear => TileFiltering::Nearest,
+        }
+    }
+}
+
+impl BiomeModel {
+    pub fn next(self) -> Self {
+        match self {
+            BiomeModel::Holdridge => BiomeModel::Simple,
+            BiomeModel::Simple => BiomeModel::Holdridge,
+        }
+    }
+}
+
+impl ColoringMode {
+    pub fn next(self) -> Self {
+        match self {
+            ColoringMode::Biome => ColoringMode::Continuous,
+            ColoringMode::Continuous => ColoringMode::Biome,
+        }
+    }
+}
+
+impl Quality {
+    pub fn next(self) -> Self {
+        match self {
+            Quality::Draft => Quality::Normal,
+            Quality::Normal => Quality::High,
+            Quality::High => Quality::Draft,
+        }
+    }
+
+    // Height and temperature octaves past 24 add nothing visible, their weight having fallen below
+    // a millionth, so High spends its extra time on humidity detail, tiles and smoothing instead.
+    // Smoothing stays off in low memory mode, which can't filter
+    pub fn apply(self, config: &mut MapConfig) {
+        let (noise_octaves, humidity_octaves, max_tile_entities, biome_filter_passes) = match self {
+            Quality::Draft => (8, 4, 25_000, 0),
+            Quality::Normal => (24, 8, 100_000, 0),
+            Quality::High => (24, 12, 400_000, 1),
+        };
+
+        config.quality = self;
+        config.height_octaves = noise_octaves;
+        config.temperature_octaves = noise_octaves;
+        config.humidity_octaves = humidity_octaves;
+        config.max_tile_entities = max_tile_entities;
+        config.biome_filter_passes = if config.low_memory { 0 } else { biome_filter_passes };
+    }
+}
+
+impl DebugView {
+    pub fn next(self) -> Self {
+        match self {
+            DebugView::Biome => DebugView::Height,
+            DebugView::Height => DebugView::Combined,
+            DebugView::Combined => DebugView::MovementCost,
+            DebugView::MovementCost => DebugView::Falloff,
+            DebugView::Falloff => DebugView::Biome,
+        }
+    }
+}
+
+impl MapConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.map_size == 0 {
+            return Err(Error::Config("map_size must be positive".to_string()));
+        }
+
+        if self.tile_size <= 0.0 || self.tile_scale <= 0.0 {
+            return Err(Error::Config("tile_size and tile_scale must be positive".to_string()));
+        }
+
+        if self.max_beach_width < 0.0 || self.beach_max_slope <= 0.0 {
+            return Err(Error::Config("max_beach_width must not be negative and beach_max_slope must be positive".to_string()));
+        }
+
+        if !self.seed_blend.is_empty() {
+            let total: f64 = self.seed_blend.iter().map(|(_, weight)| weight).sum();
+            if (total - 1.0).abs() > 1e-6 || self.seed_blend.iter().any(|(_, weight)| *weight < 0.0) {
+                return Err(Error::Config(format!("seed_blend weights must not be negative and sum to 1, not {}", total)));
+            }
+        }
+
+        if !(self.export_gamma > 0.0 && self.export_gamma.is_finite()) {
+            return Err(Error::Config(format!("export_gamma must be positive, not {}", self.export_gamma)));
+        }
+
+        if let Some(budget) = self.generation_budget.filter(|budget| !(*budget > 0.0 && budget.is_finite())) {
+            return Err(Error::Config(format!("generation_budget must be positive, not {}", budget)));
+        }
+
+        if let Some((biome, weight)) = self.spawn_weights.iter().find(|(_, weight)| !weight.is_finite() || **weight < 0.0) {
+            return Err(Error::Config(format!("spawn weight of {:?} must be finite and not negative, not {}", biome, weight)));
+        }
+
+        if self.export_tile_pixels == 0 {
+            return Err(Error::Config("export_tile_pixels must be positive".to_string()));
+        }
+
+        if self.export_subsamples == 0 {
+            return Err(Error::Config("export_subsamples must be positive".to_string()));
+        }
+
+        let (water, distance, noise) = self.humidity_weights;
+        if (water + distance + noise - 1.0).abs() > 1e-6 || water < 0.0 || distance < 0.0 || noise < 0.0 {
+            return Err(Error::Config(format!("humidity_weights {:?} must not be negative and sum to 1", self.humidity_weights)));
+        }
+
+        if !(self.initial_zoom > 0.0 && self.initial_zoom.is_finite()) {
+            return Err(Error::Config(format!("initial_zoom must be positive, not {}", self.initial_zoom)));
+        }
+
+        for shift in [self.initial_x_shift, self.initial_y_shift] {
+            if !(-MAX_SHIFT..=MAX_SHIFT).contains(&shift) {
+                return Err(Error::Config(format!("initial shift {} is outside -{} to {}", shift, MAX_SHIFT, MAX_SHIFT)));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.wind.strength) || !self.wind.direction.is_finite() {
+            return Err(Error::Config(format!("wind {:?} needs a finite direction and a strength from 0 to 1", self.wind)));
+        }
+
+        let ocean = &self.ocean_coloring;
+        if ocean.warm_temperature.is_nan() || ocean.cold_temperature.is_nan() || ocean.warm_temperature <= ocean.cold_temperature {
+            return Err(Error::Config(format!("ocean_coloring's warm_temperature {} must be above its cold_temperature {}",
+                ocean.warm_temperature, ocean.cold_temperature)));
+        }
+
+        if self.percipitation_cap_hardness.is_nan() || self.percipitation_cap_hardness <= 0.0 {
+            return Err(Error::Config(format!("percipitation_cap_hardness must be positive, not {}", self.percipitation_cap_hardness)));
+        }
+
+        if !(0.0..=1.0).contains(&self.volcano_density) || self.volcano_min_height.is_nan() {
+            return Err(Error::Config(format!("volcano_density {} is outside 0 to 1", self.volcano_density)));
+        }
+
+        if self.treeline_height.is_nan() || !self.treeline_warmth_rise.is_finite()
+            || !(self.treeline_raggedness >= 0.0 && self.treeline_raggedness.is_finite()) {
+            return Err(Error::Config("treeline_warmth_rise must be finite and treeline_raggedness must not be negative".to_string()));
+        }
+
+        if self.low_memory && self.biome_filter_passes > 0 {
+            return Err(Error::Config("low_memory needs biome_filter_passes at 0".to_string()));
+        }
+
+        if !(0.0..=1.0).contains(&self.min_land_fraction) {
+            return Err(Error::Config(format!("min_land_fraction {} is outside 0 to 1", self.min_land_fraction)));
+        }
+
+        if !(0.0..=1.0).contains(&self.view_min_land_fraction) {
+            return Err(Error::Config(format!("view_min_land_fraction {} is outside 0 to 1", self.view_min_land_fraction)));
+        }
+
+        if !(0.0..=1.0).contains(&self.world_age) {
+            return Err(Error::Config(format!("world_age {} is outside 0 to 1", self.world_age)));
+        }
+
+        for band in &self.inversions {
+            if band.top <= band.bottom {
+                return Err(Error::Config(format!("inversion band top {} is not above its bottom {}", band.top, band.bottom)));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Width of one tile in world units
+    pub fn world_units_per_tile(&self) -> f64 {
+        self.tile_size * self.tile_scale
+    }
+
+    // Tiles across the map. Always odd, from -map_size / 2 to map_size / 2 inclusive, so tile (0, 0) sits
+    // on the world origin and the grid is symmetric about it. An even map_size gains a tile: 250 renders
+    // and exports 251 tiles across, with the map a tile wider than the setting reads
+    pub fn tiles_per_axis(&self) -> u32 {
+        self.map_size / 2 * 2 + 1
+    }
+
+    // World distance from the map center to the outer edge of its outermost tiles
+    pub fn map_axis_len(&self) -> f64 {
+        self.world_units_per_tile() * self.tiles_per_axis() as f64 / 2.0
+    }
+
+    // Width of one tile in noise space at zoom 1, whatever the tile_scale
+    pub fn noise_units_per_tile(&self) -> f64 {
+        self.tile_size * NOISE_TILE_SCALE
+    }
+
+    // map_axis_len in noise units, the length latitude and falloff are normalized by
+    pub fn noise_axis_len(&self) -> f64 {
+        self.noise_units_per_tile() * self.tiles_per_axis() as f64 / 2.0
+    }
+
+    pub fn movement_cost(&self, biome: Biome) -> f32 {
+        self.movement_costs.get(&biome).copied().unwrap_or(1.0)
+    }
+
+    pub fn spawn_weight(&self, biome: Biome) -> f32 {
+        self.spawn_weights.get(&biome).copied().unwrap_or(1.0)
+    }
+
+    // Beach width [tiles] on a coast of the given slope [m per tile], narrowing linearly to none at beach_max_slope
+    pub fn beach_width(&self, slope: f64) -> f64 {
+        self.max_beach_width * (1.0 - slope / self.beach_max_slope).max(0.0)
+    }
+
+    // Whether land at height [m] on a coast of the given slope [m per tile] is within the beach width of the
+    // coast, its distance to the coast estimated as height over slope
+    pub fn within_beach(&self, height: f64, slope: f64) -> bool {
+        height > 0.0 && height < slope * self.beach_width(slope)
+    }
+
+    // World age maps linearly onto erosion, from none at age 0 to MAX_EROSION_ITERATIONS at age 1
+    pub fn erosion_iterations(&self) -> u32 {
+        (self.world_age * MAX_EROSION_ITERATIONS as f64).round() as u32
+    }
+
+    pub fn generation_budget(&self) -> Option<Duration> {
+        self.generation_budget.map(Duration::from_secs_f64)
+    }
+
+    // World age maps linearly onto vegetation, from today's cover at age 0 to half again as dense at age 1
+    pub fn vegetation_density(&self) -> f64 {
+        1.0 + 0.5 * self.world_age
+    }
+
+    // Smallest tile step keeping the sprite count within max_tile_entities
+    pub fn tile_stride(&self) -> i32 {
+        let tiles_per_axis = self.tiles_per_axis() as f64;
+        let stride = (tiles_per_axis * tiles_per_axis / self.max_tile_entities.max(1) as f64).sqrt().ceil();
+
+        stride.max(1.0) as i32
+    }
+
+    pub fn tile_to_world(&self, x: i32, y: i32) -> (f64, f64) {
+        (x as f64 * self.world_units_per_tile(), y as f64 * self.world_units_per_tile())
+    }
+
+    // Grid coordinates of the tile whose sprite covers the world position
+    pub fn world_to_tile(&self, x: f64, y: f64) -> (i32, i32) {
+        ((x / self.world_units_per_tile()).round() as i32, (y / self.world_units_per_tile()).round() as i32)
+    }
+
+    // Most percipitation [mm] air of temperature [C] can hold, raised at altitude [m] by orographic uplift
+    pub fn percipitation_cap(&self, temperature: f64, absl_height: f64) -> f64 {
+        500.0 * temperature - 80.0 + self.orographic_cap * absl_height
+    }
+
+    // Humidity term of the distance to the equatorial seas at latitude y_dis, 1 at the equator
+    pub fn water_dist(&self, y_dis: f64) -> f64 {
+        (1.0 - self.water_dist_falloff * y_dis.abs()).max(self.water_dist_min)
+    }
+
+    // Height [m] the lapse rate cools over, with inverted bands counting against it
+    pub fn lapse_height(&self, absl_height: f64) -> f64 {
+        let mut inverted = 0.0;
+
+        for band in &self.inversions {
+            let overlap = absl_height.min(band.top) - band.bottom;
+            if overlap > 0.0 {
+                inverted += overlap;
+            }
+        }
+
+        absl_height - 2.0 * inverted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tiles across, world units per tile, map_axis_len and noise_axis_len of representative configs,
+    // the default first
+    #[test]
+    fn map_lengths_are_pinned() {
+        for (map_size, tile_size, tile_scale, tiles, world_units_per_tile, map_axis_len, noise_axis_len) in [
+            (250, 16.0, 0.25, 251, 4.0, 502.0, 502.0),
+            (64, 16.0, 1.0, 65, 16.0, 520.0, 130.0),
+            (101, 32.0, 0.5, 101, 16.0, 808.0, 404.0),
+        ] {
+            let config = MapConfig { map_size, tile_size, tile_scale, ..Default::default() };
+
+            assert_eq!(config.tiles_per_axis(), tiles);
+            assert_eq!(config.world_units_per_tile(), world_units_per_tile);
+            assert_eq!(config.map_axis_len(), map_axis_len);
+            assert_eq!(config.noise_axis_len(), noise_axis_len);
+        }
+    }
+
+    // Coasts rising from the sea at a steady slope [m per tile], gentle, steep and cliff, one per row of a
+    // synthetic grid whose first column is ocean, with the beach tiles each should get at the default width
+    #[test]
+    fn gentle_coasts_get_wider_beaches() {
+        let config = MapConfig::default();
+        let width = 12;
+
+        for (slope, beach_tiles) in [(5.0, 3), (100.0, 1), (200.0, 0)] {
+            let heights: Vec<f64> = (0..width).map(|column| slope * (column as f64 - 0.5)).collect();
+            let beaches = (1..width - 1)
+                .filter(|&column| config.within_beach(heights[column], (heights[column + 1] - heights[column - 1]) / 2.0))
+                .count();
+            assert_eq!(beaches, beach_tiles, "a coast of {} m per tile", slope);
+        }
+    }
+
+    // The outermost tiles' centers sit half a tile inside map_axis_len
+    #[test]
+    fn outermost_tiles_reach_the_map_edge() {
+        let config = MapConfig::default();
+        let edge = config.map_axis_len() - config.world_units_per_tile() / 2.0;
+        let last = config.map_size as i32 / 2;
+
+        assert_eq!(config.tile_to_world(last, -last), (edge, -edge));
+        assert_eq!(config.world_to_tile(edge, -edge), (last, -last));
+    }
+
+    // Older worlds erode longer, from none at age 0 up to MAX_EROSION_ITERATIONS at age 1
+    #[test]
+    fn erosion_grows_with_world_age() {
+        let iterations: Vec<u32> = (0..=20)
+            .map(|step| MapConfig { world_age: step as f64 / 20.0, ..Default::default() }.erosion_iterations())
+            .collect();
+
+        assert!(iterations.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", iterations);
+        assert_eq!(iterations[0], 0);
+        assert_eq!(iterations[20], MAX_EROSION_ITERATIONS);
+    }
+
+    // Clamped at 0 the latitude term stops falling where it would turn negative, about 0.18 from the
+    // equator at the default falloff, and matches the unclamped term nearer the equator
+    #[test]
+    fn water_dist_clamp_engages_past_the_seas() {
+        let unclamped = MapConfig::default();
+        let clamped = MapConfig { water_dist_min: 0.0, ..Default::default() };
+        let engaged = 1.0 / unclamped.water_dist_falloff;
+
+        for step in -20..=20 {
+            let y_dis = step as f64 / 20.0;
+            let (free, bounded) = (unclamped.water_dist(y_dis), clamped.water_dist(y_dis));
+
+            assert!(bounded >= 0.0, "{} at latitude {}", bounded, y_dis);
+            if y_dis.abs() < engaged {
+                assert_eq!(bounded, free, "clamp engaged at latitude {}", y_dis);
+            } else {
+                assert_eq!(bounded, 0.0, "clamp not engaged at latitude {}", y_dis);
+                assert!(free <= 0.0);
+            }
+        }
+        assert_eq!(unclamped.water_dist(1.0), 1.0 - unclamped.water_dist_falloff);
+    }
+
+    // With a positive orographic coefficient a high tile holds more rain than a low one at the same
+    // temperature, and without one altitude makes no difference
+    #[test]
+    fn orographic_cap_raises_the_cap_at_altitude() {
+        let orographic = MapConfig { orographic_cap: 0.5, ..Default::default() };
+        let flat = MapConfig::default();
+
+        for temperature in [-5.0, 5.0, 15.0, 25.0] {
+            assert!(orographic.percipitation_cap(temperature, 3000.0) > orographic.percipitation_cap(temperature, 100.0));
+            assert_eq!(flat.percipitation_cap(temperature, 3000.0), flat.percipitation_cap(temperature, 100.0));
+        }
+    }
+
+    // The default costs leave the ocean impassable and nothing cheaper than temperate steppe
+    #[test]
+    fn default_costs_block_the_ocean_and_favour_steppe() {
+        let config = MapConfig::default();
+        assert_eq!(config.movement_cost(Biome::TemperateSteppe), 1.0);
+
+        for biome in Biome::ALL {
+            let cost = config.movement_cost(biome);
+            match biome {
+                Biome::Ocean => assert_eq!(cost, f32::INFINITY),
+                _ => assert!(cost >= 1.0 && cost.is_finite(), "{:?} costs {}", biome, cost),
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::config::{MapConfig, FONT_PATH};
+use crate::keybindings::{Action, KeyBindings};
+
+// Labels only appear once a tile covers this many screen pixels
+const MIN_LABEL_TILE_PIXELS: f32 = 28.0;
+const MAX_LABELS: usize = 2500;
+const LABEL_FONT_SIZE: f32 = 32.0;
+
+pub struct CoordLabelPlugin;
+
+#[derive(Resource)]
+struct ShowTileCoords(bool);
+
+#[derive(Component)]
+struct CoordLabel;
+
+impl Plugin for CoordLabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowTileCoords(false))
+            .add_system(toggle_coord_labels)
+            .add_system(update_coord_labels);
+    }
+}
+
+fn toggle_coord_labels(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut show: ResMut<ShowTileCoords>) {
+    if bindings.just_pressed(&keyboard, Action::ToggleCoordLabels) {
+        show.0 = !show.0;
+    }
+}
+
+// Grid range (min_x, max_x, min_y, max_y) of tiles on screen, None when too small to label
+pub fn visible_tiles(config: &MapConfig, window: &Window, transform: &Transform, projection: &OrthographicProjection) -> Option<(i32, i32, i32, i32)> {
+    let tile_pixels = config.world_units_per_tile() as f32 / projection.scale;
+    if tile_pixels < MIN_LABEL_TILE_PIXELS {
+        return None;
+    }
+
+    let half_width = window.width() / 2.0 * projection.scale;
+    let half_height = window.height() / 2.0 * projection.scale;
+    let center = transform.translation;
+    let half_size = config.map_size as i32 / 2;
+
+    let (min_x, min_y) = config.world_to_tile((center.x - half_width) as f64, (center.y - half_height) as f64);
+    let (max_x, max_y) = config.world_to_tile((center.x + half_width) as f64, (center.y + half_height) as f64);
+
+    Some((min_x.max(-half_size), max_x.min(half_size), min_y.max(-half_size), max_y.min(half_size)))
+}
+
+// Respawns the labels whenever the set of visible tiles changes
+#[allow(clippy::too_many_arguments)]
+fn update_coord_labels(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<MapConfig>,
+    show: Res<ShowTileCoords>,
+    windows: Res<Windows>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    labels: Query<Entity, With<CoordLabel>>,
+    mut shown: Local<Option<(i32, i32, i32, i32)>>,
+) {
+    let (window, (transform, projection)) = match (windows.get_primary(), camera.get_single()) {
+        (Some(window), Ok(camera)) => (window, camera),
+        _ => return,
+    };
+
+    let visible = if show.0 {
+        visible_tiles(&config, window, transform, projection)
+    } else {
+        None
+    };
+
+    if *shown == visible && !config.is_changed() {
+        return;
+    }
+    *shown = visible;
+
+    for entity in labels.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let (min_x, max_x, min_y, max_y) = match visible {
+        Some(range) => range,
+        None => return,
+    };
+
+    let font = asset_server.load(FONT_PATH);
+    let label_scale = (config.world_units_per_tile() * 0.25) as f32 / LABEL_FONT_SIZE;
+    let mut count = 0;
+
+    for y in min_y..(max_y + 1) {
+        for x in min_x..(max_x + 1) {
+            if count >= MAX_LABELS {
+                return;
+            }
+            count += 1;
+
+            let (world_x, world_y) = config.tile_to_world(x, y);
+
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(format!("{},{}", x, y), TextStyle {
+                        font: font.clone(),
+                        font_size: LABEL_FONT_SIZE,
+                        color: Color::WHITE,
+                    }).with_alignment(TextAlignment::CENTER),
+                    transform: Transform {
+                        translation: Vec3::new(world_x as f32, world_y as f32, 2.0),
+                        scale: Vec3::splat(label_scale),
+                        ..default()
+                    },
+                    ..default()
+                },
+                CoordLabel,
+            ));
+        }
+    }
+}
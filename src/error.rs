@@ -0,0 +1,84 @@
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::profile::Stage;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("image error: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error("parse error on line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("ron error: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+
+    #[error("ron error: {0}")]
+    RonWrite(#[from] ron::Error),
+
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid config: {0}")]
+    Config(String),
+
+    #[error("generation ran over its {budget:?} budget during {stage:?}")]
+    BudgetExceeded { budget: Duration, stage: Stage },
+
+    #[error("generation was cancelled during {stage:?}")]
+    Cancelled { stage: Stage },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biome_table::{read_biome_table, BiomeTable};
+    use crate::config::MapConfig;
+    use crate::export::{parse_seeds, verify_manifest, MANIFEST_NAME};
+    use crate::keybindings::KeyBindings;
+    use crate::land_mask::read_land_mask;
+    use crate::presets::read_preset;
+    use crate::test_support::*;
+
+    #[test]
+    fn missing_files_are_io_errors() {
+        let dir = TempDir::new("error_io");
+        let missing = dir.join("missing.csv");
+        let missing = missing.to_str().unwrap();
+
+        assert!(matches!(read_biome_table(missing), Err(Error::Io(_))));
+        assert!(matches!(read_preset(&dir.join("missing.ron")), Err(Error::Io(_))));
+        assert!(matches!(verify_manifest(dir.as_str()), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn malformed_lines_are_parse_errors_on_their_line() {
+        assert!(matches!(BiomeTable::from_csv("# header\nPolarDesert,,0,,\nSwamp,0,,,\n"), Err(Error::Parse { line: 3, .. })));
+        assert!(matches!(BiomeTable::from_csv("PolarDesert,,zero,,\n"), Err(Error::Parse { line: 1, .. })));
+        assert!(matches!(KeyBindings::from_csv("action,key\nToggleLabels\n"), Err(Error::Parse { line: 2, .. })));
+    }
+
+    #[test]
+    fn invalid_files_are_errors_of_their_format() {
+        let dir = TempDir::new("error_formats");
+        std::fs::write(dir.join("broken.ron"), "(map_size: 120, world_age").unwrap();
+        std::fs::write(dir.join("mask.png"), "not an image").unwrap();
+        std::fs::write(dir.join(MANIFEST_NAME), "{\"files\": [").unwrap();
+
+        assert!(matches!(read_preset(&dir.join("broken.ron")), Err(Error::Ron(_))));
+        assert!(matches!(read_land_mask(dir.join("mask.png").to_str().unwrap()), Err(Error::Image(_))));
+        assert!(matches!(verify_manifest(dir.as_str()), Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn invalid_settings_are_config_errors() {
+        assert!(matches!(MapConfig { map_size: 0, ..Default::default() }.validate(), Err(Error::Config(_))));
+        assert!(matches!(parse_seeds("1,x"), Err(Error::Config(_))));
+    }
+}
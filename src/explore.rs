@@ -0,0 +1,300 @@
+use bevy::prelude::*;
+use rand::thread_rng;
+
+use crate::config::{MapConfig, FONT_PATH};
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::{MapGen, MapGrid, SpawnSite};
+
+const PLAYER_COLOR: Color = Color::rgb(1.0, 0.85, 0.1);
+
+// Above the path markers and coastline, below the landmass labels
+const PLAYER_Z: f32 = 0.9;
+
+// Seconds to cross one tile of movement cost 1, scaled by the cost of the tile entered
+const SECONDS_PER_COST: f32 = 0.15;
+
+// What a new player's random start asks of its tile: at most SPAWN_COAST_DISTANCE tiles from the sea,
+// no steeper than SPAWN_MAX_SLOPE [m per tile] and above freezing
+const SPAWN_COAST_DISTANCE: u32 = 8;
+const SPAWN_MAX_SLOPE: f64 = 100.0;
+
+// Held pan directions, checked in order so only one step is taken at a time
+const MOVE_ACTIONS: [(Action, (i32, i32)); 4] = [
+    (Action::PanNorth, (0, 1)),
+    (Action::PanSouth, (0, -1)),
+    (Action::PanWest, (-1, 0)),
+    (Action::PanEast, (1, 0)),
+];
+
+pub struct ExplorePlugin;
+
+// Tab switches WASD between moving the view and moving the player
+#[derive(Resource, Default)]
+pub struct ExploreMode(pub bool);
+
+// Grid tile the player stands on and how long the current step has been held
+#[derive(Component)]
+struct Player {
+    coords: (i32, i32),
+    progress: f32,
+}
+
+#[derive(Component)]
+struct PlayerBiomeText;
+
+// Movement cost of every grid tile, recomputed whenever the map is
+#[derive(Resource, Default)]
+struct PlayerCosts(Vec<f32>);
+
+impl Plugin for ExplorePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ExploreMode::default())
+            .insert_resource(PlayerCosts::default())
+            .add_startup_system(spawn_player_biome_text)
+            .add_system(toggle_explore_mode)
+            .add_system(place_player.after(toggle_explore_mode))
+            .add_system(move_player.after(place_player))
+            .add_system(follow_player.after(move_player));
+    }
+}
+
+fn spawn_player_biome_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font: asset_server.load(FONT_PATH),
+            font_size: 20.0,
+            color: Color::WHITE,
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(35.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        PlayerBiomeText,
+    ));
+}
+
+// Passable tile nearest the map center, where a player stranded by a new map goes
+fn start_tile(grid: &MapGrid, costs: &[f32]) -> Option<(i32, i32)> {
+    (0..costs.len())
+        .filter(|&index| costs[index].is_finite())
+        .map(|index| grid.coords(index))
+        .min_by_key(|&(x, y)| x * x + y * y)
+}
+
+// Where a new player starts: a random passable tile MapGen::pick_spawn weighs by biome among those
+// near the coast, on gentle slopes and above freezing, or start_tile if the map has none
+fn spawn_tile(mapgen: &MapGen, grid: &MapGrid, costs: &[f32]) -> Option<(i32, i32)> {
+    let suitable = |site: &SpawnSite| {
+        grid.index(site.x, site.y).is_some_and(|index| costs[index].is_finite())
+            && site.coast_distance.is_some_and(|distance| distance <= SPAWN_COAST_DISTANCE)
+            && site.slope <= SPAWN_MAX_SLOPE
+            && site.sample.temperature > 0.0
+    };
+
+    mapgen.pick_spawn(&mut thread_rng(), suitable).or_else(|| start_tile(grid, costs))
+}
+
+// Leaving explore mode puts the camera back over the map center
+fn toggle_explore_mode(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut explore: ResMut<ExploreMode>,
+    players: Query<Entity, With<Player>>,
+    mut camera: Query<&mut Transform, With<Camera>>,
+) {
+    if !bindings.just_pressed(&keyboard, Action::ToggleExplore) {
+        return;
+    }
+
+    explore.0 = !explore.0;
+    if explore.0 {
+        return;
+    }
+
+    for entity in players.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    for mut transform in camera.iter_mut() {
+        transform.translation.x = 0.0;
+        transform.translation.y = 0.0;
+    }
+}
+
+// Spawns the player on entering explore mode and keeps it on a passable tile of each new map
+fn place_player(
+    mut commands: Commands,
+    explore: Res<ExploreMode>,
+    mapgen: Option<Res<MapGen>>,
+    grid: Option<Res<MapGrid>>,
+    mut costs: ResMut<PlayerCosts>,
+    mut players: Query<&mut Player>,
+) {
+    let (mapgen, grid) = match (mapgen, grid) {
+        (Some(mapgen), Some(grid)) => (mapgen, grid),
+        _ => return,
+    };
+
+    if grid.is_changed() || costs.0.len() != grid.samples.len() {
+        costs.0 = mapgen.cost_grid(&grid);
+    }
+
+    if !explore.0 {
+        return;
+    }
+
+    match players.get_single_mut() {
+        Ok(mut player) => {
+            if grid.is_changed() {
+                let standing = grid.index(player.coords.0, player.coords.1).map(|index| costs.0[index]);
+                if !standing.is_some_and(|cost| cost.is_finite()) {
+                    if let Some(start) = start_tile(&grid, &costs.0) {
+                        player.coords = start;
+                    }
+                }
+                player.progress = 0.0;
+            }
+        }
+        Err(_) => {
+            if let Some(start) = spawn_tile(&mapgen, &grid, &costs.0) {
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: PLAYER_COLOR,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Player { coords: start, progress: 0.0 },
+                ));
+            }
+        }
+    }
+}
+
+// Holding a direction steps to the neighbouring tile once it has been held for that tile's movement
+// cost. Impassable tiles, like the ocean, and the map edge block the step
+fn move_player(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    grid: Option<Res<MapGrid>>,
+    costs: Res<PlayerCosts>,
+    mut players: Query<&mut Player>,
+) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
+
+    for mut player in players.iter_mut() {
+        let direction = MOVE_ACTIONS.iter()
+            .find(|(action, _)| bindings.pressed(&keyboard, *action))
+            .map(|(_, direction)| *direction);
+
+        let (dx, dy) = match direction {
+            Some(direction) => direction,
+            None => {
+                player.progress = 0.0;
+                continue;
+            }
+        };
+
+        let next = (player.coords.0 + dx * grid.stride, player.coords.1 + dy * grid.stride);
+        let cost = match grid.index(next.0, next.1).map(|index| costs.0[index]) {
+            Some(cost) if cost.is_finite() => cost,
+            _ => {
+                player.progress = 0.0;
+                continue;
+            }
+        };
+
+        player.progress += time.delta_seconds();
+        if player.progress >= cost * SECONDS_PER_COST * grid.stride as f32 {
+            player.coords = next;
+            player.progress = 0.0;
+        }
+    }
+}
+
+// Places the player sprite on its tile, centers the camera on it and names the biome underfoot
+fn follow_player(
+    config: Res<MapConfig>,
+    grid: Option<Res<MapGrid>>,
+    mut players: Query<(&Player, &mut Transform, &mut Sprite), Without<Camera>>,
+    mut camera: Query<&mut Transform, With<Camera>>,
+    mut text: Query<&mut Text, With<PlayerBiomeText>>,
+) {
+    let (player, biome) = match (players.get_single_mut(), grid) {
+        (Ok((player, mut transform, mut sprite)), Some(grid)) => {
+            let (x, y) = config.tile_to_world(player.coords.0, player.coords.1);
+            transform.translation = Vec3::new(x as f32, y as f32, PLAYER_Z);
+            sprite.custom_size = Some(Vec2::splat((config.world_units_per_tile() * grid.stride as f64) as f32));
+
+            let biome = grid.index(player.coords.0, player.coords.1).map(|index| grid.samples[index].biome);
+            (Some(transform.translation), biome)
+        }
+        _ => (None, None),
+    };
+
+    if let Some(position) = player {
+        for mut transform in camera.iter_mut() {
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+        }
+    }
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = biome.map_or(String::new(), |biome| format!("Standing on {:?}", biome));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::tilemap::{Biome, TileSample};
+
+    // A row of steppe, the player in the middle and ocean to the east. Holding east for seconds leaves
+    // the player where it stood, holding west steps onto the steppe
+    #[test]
+    fn player_cannot_step_onto_the_ocean() {
+        let sample = |biome| TileSample { height: 0.0, temperature: 0.0, percipitation: 0.0, falloff: 0.0, biome };
+        let mut samples = vec![sample(Biome::TemperateSteppe); 9];
+        samples[5] = sample(Biome::Ocean);
+        let grid = MapGrid { half_size: 1, stride: 1, tiles: Vec::new(), samples };
+        let costs = grid.samples.iter().map(|sample| MapConfig::default().movement_cost(sample.biome)).collect();
+
+        let mut app = App::new();
+        app.insert_resource(grid)
+            .insert_resource(PlayerCosts(costs))
+            .insert_resource(KeyBindings::default())
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(Time::default())
+            .add_system(move_player);
+        let player = app.world.spawn(Player { coords: (0, 0), progress: 0.0 }).id();
+
+        // Five seconds of quarter second frames, plenty for a step onto steppe
+        let mut now = Instant::now();
+        let mut hold = |app: &mut App, key: KeyCode| {
+            let mut keyboard = app.world.resource_mut::<Input<KeyCode>>();
+            keyboard.release_all();
+            keyboard.press(key);
+            for _ in 0..20 {
+                now += Duration::from_millis(250);
+                app.world.resource_mut::<Time>().update_with_instant(now);
+                app.update();
+            }
+            app.world.get::<Player>(player).unwrap().coords
+        };
+
+        assert_eq!(hold(&mut app, KeyCode::D), (0, 0));
+        assert_eq!(hold(&mut app, KeyCode::A), (-1, 0));
+    }
+}
@@ -0,0 +1,447 @@
+use bevy::prelude::*;
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::biome_table::load_biome_table;
+use crate::config::MapConfig;
+use crate::error::{Error, Result};
+use crate::tilemap::{Biome, GridFields, MapGen, MapGrid, Tile};
+
+// Tile colors are already sRGB encoded, as Color::rgb stores them, and gamma 1 writes those channels
+// unchanged. Other gammas raise red, green and blue to 1 / gamma, leaving alpha linear
+pub fn color_bytes(color: Color, gamma: f32) -> [u8; 4] {
+    let [r, g, b, a] = color.as_rgba_f32();
+    let encode = |channel: f32| channel.clamp(0.0, 1.0).powf(1.0 / gamma);
+
+    [encode(r), encode(g), encode(b), a.clamp(0.0, 1.0)].map(|channel| (channel * 255.0).round() as u8)
+}
+
+// A tile_pixels square per tile in its tint color, with north (the highest grid y) at the top
+pub fn grid_image(grid: &MapGrid, gamma: f32, tile_pixels: u32) -> RgbaImage {
+    tiles_image(grid, &grid.tiles, gamma, tile_pixels)
+}
+
+// The image exports write: grid_image, or with export_subsamples above 1 each tile recolored from its
+// sub-tile samples
+pub fn export_image(map_gen: &MapGen, grid: &MapGrid, config: &MapConfig) -> RgbaImage {
+    if config.export_subsamples <= 1 {
+        return grid_image(grid, config.export_gamma, config.export_tile_pixels);
+    }
+
+    let tiles = map_gen.subsampled_tiles(grid, config.export_subsamples, config.export_subsample_mode);
+    tiles_image(grid, &tiles, config.export_gamma, config.export_tile_pixels)
+}
+
+// grid_image with tiles in place of the grid's own, one per grid tile in the same order
+pub fn tiles_image(grid: &MapGrid, tiles: &[Tile], gamma: f32, tile_pixels: u32) -> RgbaImage {
+    let width = grid.width() as u32 * tile_pixels;
+    let mut image = RgbaImage::new(width, width);
+
+    for (index, tile) in tiles.iter().enumerate() {
+        let (x, y) = grid.coords(index);
+        let block_x = (x / grid.stride + grid.half_size) as u32 * tile_pixels;
+        let block_y = (grid.half_size - y / grid.stride) as u32 * tile_pixels;
+        let pixel = Rgba(color_bytes(tile.color, gamma));
+
+        for dy in 0..tile_pixels {
+            for dx in 0..tile_pixels {
+                image.put_pixel(block_x + dx, block_y + dy, pixel);
+            }
+        }
+    }
+
+    image
+}
+
+// Seeds from a comma separated list whose entries are single seeds or inclusive ranges, as in "1,5,10-20"
+pub fn parse_seeds(spec: &str) -> Result<Vec<u32>> {
+    let mut seeds = Vec::new();
+
+    for entry in spec.split(',').map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+        let number = |text: &str| text.trim().parse::<u32>()
+            .map_err(|_| Error::Config(format!("invalid seed '{}'", text)));
+
+        match entry.split_once('-') {
+            Some((first, last)) => seeds.extend(number(first)?..=number(last)?),
+            None => seeds.push(number(entry)?),
+        }
+    }
+
+    Ok(seeds)
+}
+
+// Writes the default view of each seed to seed_<seed>.png in dir, one line of progress per map, and
+// the manifest of them all
+pub fn export_seeds(seeds: &[u32], dir: &str) -> Result<()> {
+    let config = MapConfig {
+        biome_table: Some(load_biome_table()),
+        ..Default::default()
+    };
+    std::fs::create_dir_all(dir)?;
+    let mut files = Vec::new();
+
+    for (done, &seed) in seeds.iter().enumerate() {
+        let map_gen = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config);
+        let grid = map_gen.generate_grid();
+        let name = format!("seed_{}.png", seed);
+        let path = std::path::Path::new(dir).join(&name);
+
+        export_image(&map_gen, &grid, &config).save(&path)?;
+        files.push(ManifestFile::read(dir, &name, seed)?);
+        println!("[{}/{}] {}", done + 1, seeds.len(), path.display());
+    }
+
+    if config.export_manifest {
+        write_manifest(dir, &config, files)?;
+    }
+    Ok(())
+}
+
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+// One exported file, named relative to the manifest, with the seed it shows, its size [bytes] and the
+// hex SHA-1 of its contents as sha1sum prints it
+#[derive(Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub seed: u32,
+    pub bytes: u64,
+    pub sha1: String,
+}
+
+// What a set of exports is and how to make it again, written as manifest.json beside the files:
+//   format, version      "rust_game.manifest" and 1, bumped on any change to the fields below
+//   config               the full MapConfig the files were generated with, as presets spell it, the
+//                        biome table aside
+//   files                every other file the export wrote
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub config: MapConfig,
+    pub files: Vec<ManifestFile>,
+}
+
+impl ManifestFile {
+    pub fn read(dir: &str, name: &str, seed: u32) -> Result<Self> {
+        let contents = std::fs::read(std::path::Path::new(dir).join(name))?;
+
+        Ok(ManifestFile {
+            path: name.to_string(),
+            seed,
+            bytes: contents.len() as u64,
+            sha1: sha1_smol::Sha1::from(&contents).digest().to_string(),
+        })
+    }
+}
+
+pub fn write_manifest(dir: &str, config: &MapConfig, files: Vec<ManifestFile>) -> Result<()> {
+    let manifest = Manifest {
+        format: "rust_game.manifest".to_string(),
+        version: 1,
+        config: config.clone(),
+        files,
+    };
+
+    std::fs::write(std::path::Path::new(dir).join(MANIFEST_NAME), serde_json::to_string_pretty(&manifest)? + "\n")?;
+    Ok(())
+}
+
+// Files in dir's manifest that are missing or whose size or checksum no longer match, each with what
+// is wrong with it
+pub fn verify_manifest(dir: &str) -> Result<Vec<String>> {
+    let manifest: Manifest = serde_json::from_str(&std::fs::read_to_string(std::path::Path::new(dir).join(MANIFEST_NAME))?)?;
+    let mut problems = Vec::new();
+
+    for listed in &manifest.files {
+        match ManifestFile::read(dir, &listed.path, listed.seed) {
+            Ok(actual) if actual.bytes != listed.bytes || actual.sha1 != listed.sha1 => problems.push(format!(
+                "{} is {} bytes with SHA-1 {}, not {} bytes with {}", listed.path, actual.bytes, actual.sha1, listed.bytes, listed.sha1)),
+            Ok(_) => {}
+            Err(error) => problems.push(format!("{} could not be read: {}", listed.path, error)),
+        }
+    }
+
+    Ok(problems)
+}
+
+// The default view of seed as map.png, fields.csv and tiles.json, its config as config.ron, which
+// loads as a preset, and the manifest of the four
+pub fn export_bundle(seed: u32, dir: &str) -> Result<()> {
+    // The bundle is of the seed asked for, so its config.ron mustn't start the app on today's seed
+    let config = MapConfig {
+        biome_table: Some(load_biome_table()),
+        daily_seed: false,
+        ..Default::default()
+    };
+    let map_gen = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config);
+    let grid = map_gen.generate_grid();
+    let path = |name: &str| std::path::Path::new(dir).join(name);
+    std::fs::create_dir_all(dir)?;
+
+    export_image(&map_gen, &grid, &config).save(path("map.png"))?;
+    std::fs::write(path("fields.csv"), fields_csv(&grid.fields()))?;
+    std::fs::write(path("tiles.json"), tiles_json(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config, &grid))?;
+    std::fs::write(path("config.ron"), ron::ser::to_string_pretty(&config, ron::ser::PrettyConfig::default())?)?;
+
+    if config.export_manifest {
+        let files = ["map.png", "fields.csv", "tiles.json", "config.ron"].into_iter()
+            .map(|name| ManifestFile::read(dir, name, seed))
+            .collect::<Result<Vec<_>>>()?;
+        write_manifest(dir, &config, files)?;
+    }
+    Ok(())
+}
+
+// One row per tile with its grid cell, heights [m], temperatures [C], percipitation [mm] and biome id,
+// in the grid's row order, so the columns load straight into numeric tools
+pub fn fields_csv(fields: &GridFields) -> String {
+    let mut csv = String::from("column,row,height,temperature,percipitation,biome\n");
+
+    for index in 0..fields.biome.len() {
+        csv.push_str(&format!("{},{},{},{},{},{}\n",
+            index % fields.width, index / fields.width,
+            fields.height[index], fields.temperature[index], fields.percipitation[index], fields.biome[index]));
+    }
+
+    csv
+}
+
+pub fn export_fields(seed: u32, path: &str) -> Result<()> {
+    let config = MapConfig {
+        biome_table: Some(load_biome_table()),
+        ..Default::default()
+    };
+    let grid = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config).generate_grid();
+
+    std::fs::write(path, fields_csv(&grid.fields()))?;
+    Ok(())
+}
+
+// How often each pair of biomes, indexed as in Biome::ALL, meet across a horizontal or vertical tile
+// edge. Symmetric, each edge counted once in both of its cells, so the diagonal holds edges between
+// two tiles of the same biome
+pub fn adjacency_matrix(fields: &GridFields) -> Vec<Vec<u32>> {
+    let mut matrix = vec![vec![0; Biome::ALL.len()]; Biome::ALL.len()];
+    let width = fields.width.max(1);
+
+    let mut count = |a: u8, b: u8| {
+        matrix[a as usize][b as usize] += 1;
+        if a != b {
+            matrix[b as usize][a as usize] += 1;
+        }
+    };
+
+    for (index, &biome) in fields.biome.iter().enumerate() {
+        if index % width + 1 < width {
+            count(biome, fields.biome[index + 1]);
+        }
+        if let Some(&above) = fields.biome.get(index + width) {
+            count(biome, above);
+        }
+    }
+
+    matrix
+}
+
+// The matrix as a table with a row and a column per biome that borders anything, headed by names
+pub fn adjacency_csv(matrix: &[Vec<u32>]) -> String {
+    let present: Vec<usize> = (0..matrix.len()).filter(|&row| matrix[row].iter().any(|&edges| edges > 0)).collect();
+    let names: Vec<String> = present.iter().map(|&index| format!("{:?}", Biome::ALL[index])).collect();
+
+    let mut csv = format!("biome,{}\n", names.join(","));
+    for (&row, name) in present.iter().zip(&names) {
+        let counts: Vec<String> = present.iter().map(|&column| matrix[row][column].to_string()).collect();
+        csv.push_str(&format!("{},{}\n", name, counts.join(",")));
+    }
+
+    csv
+}
+
+pub fn export_adjacency(seed: u32, path: &str) -> Result<()> {
+    let config = MapConfig {
+        biome_table: Some(load_biome_table()),
+        ..Default::default()
+    };
+    let grid = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config).generate_grid();
+
+    std::fs::write(path, adjacency_csv(&adjacency_matrix(&grid.fields())))?;
+    Ok(())
+}
+
+// Tile metadata for loading maps into other engines, as one JSON object:
+//   format, version      "rust_game.tiles" and 1, bumped on any change to the fields below
+//   seed, zoom, x_shift, y_shift, noise_offset
+//                        the view the grid was generated at, as MapGen::new takes it
+//   config               map_size, tile_scale, world_age, biome_model and wind (direction, strength);
+//                        every other setting was at its default
+//   width, height        tiles per row and number of rows
+//   biomes               biome names, indexed by the ids in tiles.biome
+//   tiles                biome ids, heights [m] and "#rrggbb" colors, one entry per tile row by row from
+//                        the south edge, each row west to east
+pub fn tiles_json(seed: u32, zoom: f64, x_shift: f64, y_shift: f64, noise_offset: (f64, f64), config: &MapConfig, grid: &MapGrid) -> String {
+    let fields = grid.fields();
+    let list = |items: Vec<String>| items.join(",");
+
+    let biomes = list(Biome::ALL.iter().map(|biome| format!("\"{:?}\"", biome)).collect());
+    let ids = list(fields.biome.iter().map(|id| id.to_string()).collect());
+    let heights = list(fields.height.iter().map(|height| height.to_string()).collect());
+    let colors = list(grid.tiles.iter().map(|tile| {
+        let [r, g, b, _] = color_bytes(tile.color, 1.0);
+        format!("\"#{:02x}{:02x}{:02x}\"", r, g, b)
+    }).collect());
+
+    format!(
+        "{{\"format\":\"rust_game.tiles\",\"version\":1,\
+        \"seed\":{},\"zoom\":{},\"x_shift\":{},\"y_shift\":{},\"noise_offset\":[{},{}],\
+        \"config\":{{\"map_size\":{},\"tile_scale\":{},\"world_age\":{},\"biome_model\":\"{:?}\",\"wind\":{{\"direction\":{},\"strength\":{}}}}},\
+        \"width\":{},\"height\":{},\"biomes\":[{}],\
+        \"tiles\":{{\"biome\":[{}],\"height\":[{}],\"color\":[{}]}}}}\n",
+        seed, zoom, x_shift, y_shift, noise_offset.0, noise_offset.1,
+        config.map_size, config.tile_scale, config.world_age, config.biome_model, config.wind.direction, config.wind.strength,
+        fields.width, fields.biome.len() / fields.width.max(1), biomes,
+        ids, heights, colors,
+    )
+}
+
+// The default view of seed, as tiles_json describes
+pub fn export_tiles(seed: u32, path: &str) -> Result<()> {
+    let config = MapConfig {
+        biome_table: Some(load_biome_table()),
+        ..Default::default()
+    };
+    let grid = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config).generate_grid();
+
+    std::fs::write(path, tiles_json(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config, &grid))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::read_preset;
+    use crate::test_support::*;
+
+    // A known color exports its channels unchanged at gamma 1 and raised to 1 / 2.2 at gamma 2.2,
+    // with alpha left linear
+    #[test]
+    fn gamma_encodes_known_color_bytes() {
+        let color = Color::rgba(0.5, 0.25, 1.0, 0.5);
+
+        assert_eq!(color_bytes(color, 1.0), [128, 64, 255, 128]);
+        assert_eq!(color_bytes(color, 2.2), [186, 136, 255, 128]);
+    }
+
+    // At 2 px per tile the reference map's M x M tiles export as a 2M x 2M image, each tile a 2 x 2 block
+    // of its own color with north at the top
+    #[test]
+    fn two_pixel_tiles_export_as_blocks() {
+        let grid = reference_map_gen(&reference_config()).generate_grid();
+        let image = grid_image(&grid, 1.0, 2);
+        let tiles = grid.width() as u32;
+        assert_eq!(image.dimensions(), (2 * tiles, 2 * tiles));
+
+        for (index, tile) in grid.tiles.iter().enumerate() {
+            let (x, y) = grid.coords(index);
+            let (column, row) = ((x + grid.half_size) as u32, (grid.half_size - y) as u32);
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                assert_eq!(image.get_pixel(2 * column + dx, 2 * row + dy).0, color_bytes(tile.color, 1.0), "tile ({}, {})", x, y);
+            }
+        }
+    }
+
+    // Two seeds export as two different PNGs, named by seed, each the default map's tiles across at the
+    // default export size, which keeps every tile
+    #[test]
+    fn batch_export_writes_a_png_per_seed() {
+        let dir = TempDir::new("batch");
+        let seeds = [REFERENCE_SEED, REFERENCE_SEED + 1];
+        export_seeds(&seeds, dir.as_str()).unwrap();
+
+        let config = MapConfig::default();
+        let width = config.tiles_per_axis() * config.export_tile_pixels;
+        let images: Vec<RgbaImage> = seeds.iter()
+            .map(|seed| image::open(dir.join(&format!("seed_{}.png", seed))).unwrap().to_rgba8())
+            .collect();
+        for image in &images {
+            assert_eq!(image.dimensions(), (width, width));
+        }
+        assert!(images[0] != images[1]);
+    }
+
+    // Reading the reference map's tile JSON back gives the grid's own widths, biome ids, biome names and
+    // heights, to the bit
+    #[test]
+    fn tiles_json_reads_back_into_the_fields() {
+        let config = reference_config();
+        let grid = reference_map_gen(&config).generate_grid();
+        let json: serde_json::Value = serde_json::from_str(&tiles_json(REFERENCE_SEED, 1.0, 0.0, 0.0, (0.0, 0.0), &config, &grid)).unwrap();
+
+        let list = |key: &str| json["tiles"][key].as_array().unwrap().clone();
+        let read = GridFields {
+            width: json["width"].as_u64().unwrap() as usize,
+            height: list("height").iter().map(|height| height.as_f64().unwrap() as f32).collect(),
+            temperature: Vec::new(),
+            percipitation: Vec::new(),
+            biome: list("biome").iter().map(|id| id.as_u64().unwrap() as u8).collect(),
+        };
+
+        let fields = grid.fields();
+        assert_eq!(read.width, fields.width);
+        assert_eq!(json["height"].as_u64().unwrap() as usize, fields.biome.len() / fields.width);
+        assert_eq!(read.biome, fields.biome);
+        assert_eq!(read.height, fields.height);
+
+        let names = json["biomes"].as_array().unwrap();
+        for (&id, sample) in read.biome.iter().zip(&grid.samples) {
+            assert_eq!(names[id as usize], format!("{:?}", sample.biome));
+        }
+    }
+
+    // A hand drawn 3x2 grid, whose bottom row comes first,
+    //   Beach  Beach  Marsh
+    //   Ocean  Ocean  Beach
+    // has the adjacency counted by hand
+    #[test]
+    fn adjacency_matrix_matches_hand_counted_grid() {
+        let id = |biome: Biome| Biome::ALL.iter().position(|&other| other == biome).unwrap();
+        let (ocean, beach, marsh) = (id(Biome::Ocean), id(Biome::Beach), id(Biome::Marsh));
+        let fields = GridFields {
+            width: 3,
+            height: vec![0.0; 6],
+            temperature: vec![0.0; 6],
+            percipitation: vec![0.0; 6],
+            biome: [ocean, ocean, beach, beach, beach, marsh].map(|biome| biome as u8).to_vec(),
+        };
+
+        let mut expected = vec![vec![0; Biome::ALL.len()]; Biome::ALL.len()];
+        for (a, b, edges) in [(ocean, ocean, 1), (beach, beach, 1), (ocean, beach, 3), (beach, marsh, 2)] {
+            expected[a][b] = edges;
+            expected[b][a] = edges;
+        }
+
+        assert_eq!(adjacency_matrix(&fields), expected);
+    }
+
+    // An exported bundle lists every file it wrote with matching checksums, and its config loads back as
+    // a preset. The manifest then catches a file changed afterwards
+    #[test]
+    fn bundle_manifest_covers_its_files() {
+        let dir = TempDir::new("manifest");
+        export_bundle(REFERENCE_SEED, dir.as_str()).unwrap();
+        assert_eq!(verify_manifest(dir.as_str()).unwrap(), Vec::<String>::new());
+
+        let manifest: Manifest = serde_json::from_str(&std::fs::read_to_string(dir.join(MANIFEST_NAME)).unwrap()).unwrap();
+        for entry in std::fs::read_dir(dir.path()).unwrap() {
+            let name = entry.unwrap().file_name().to_string_lossy().into_owned();
+            assert!(name == MANIFEST_NAME || manifest.files.iter().any(|file| file.path == name), "{} is not in the manifest", name);
+        }
+        assert!(manifest.files.iter().all(|file| file.seed == REFERENCE_SEED));
+        assert_eq!(manifest.config.map_size, MapConfig::default().map_size);
+
+        assert!(!read_preset(&dir.join("config.ron")).unwrap().config.daily_seed);
+
+        std::fs::write(dir.join("fields.csv"), "column,row\n").unwrap();
+        assert!(!verify_manifest(dir.as_str()).unwrap().is_empty(), "the manifest still matched after fields.csv was overwritten");
+    }
+}
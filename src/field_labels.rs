@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use crate::config::{MapConfig, FONT_PATH};
+use crate::coord_labels::visible_tiles;
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::{MapGrid, TileSample};
+
+const MAX_LABELS: usize = 2500;
+const LABEL_FONT_SIZE: f32 = 32.0;
+
+// Grid range (min_x, max_x, min_y, max_y), as visible_tiles gives it
+type TileRange = (i32, i32, i32, i32);
+
+pub struct FieldLabelPlugin;
+
+// Field written on each visible tile, None for no labels
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum LabelField {
+    Height,
+    Temperature,
+    Percipitation,
+}
+
+#[derive(Resource)]
+struct ShownField(Option<LabelField>);
+
+#[derive(Component)]
+struct FieldLabel;
+
+impl Plugin for FieldLabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShownField(None))
+            .add_system(cycle_field_labels)
+            .add_system(update_field_labels);
+    }
+}
+
+impl LabelField {
+    fn value(self, sample: &TileSample) -> String {
+        match self {
+            LabelField::Height => format!("{:.0}", sample.height),
+            LabelField::Temperature => format!("{:.1}", sample.temperature),
+            LabelField::Percipitation => format!("{:.0}", sample.percipitation),
+        }
+    }
+}
+
+// Steps from no labels through height [m], temperature [C] and percipitation [mm]
+fn cycle_field_labels(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut shown: ResMut<ShownField>) {
+    if !bindings.just_pressed(&keyboard, Action::CycleFieldLabels) {
+        return;
+    }
+
+    shown.0 = match shown.0 {
+        None => Some(LabelField::Height),
+        Some(LabelField::Height) => Some(LabelField::Temperature),
+        Some(LabelField::Temperature) => Some(LabelField::Percipitation),
+        Some(LabelField::Percipitation) => None,
+    };
+    info!("Tile field labels: {}", shown.0.map_or("off".to_string(), |field| format!("{:?}", field)));
+}
+
+// Respawns the labels from the grid's samples whenever the visible tiles, the field or the grid
+// change. Tiles the grid skips at a coarse stride get no label, and nor does a map without a grid
+#[allow(clippy::too_many_arguments)]
+fn update_field_labels(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<MapConfig>,
+    shown: Res<ShownField>,
+    grid: Option<Res<MapGrid>>,
+    windows: Res<Windows>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    labels: Query<Entity, With<FieldLabel>>,
+    mut labelled: Local<Option<(LabelField, TileRange)>>,
+) {
+    let (window, (transform, projection)) = match (windows.get_primary(), camera.get_single()) {
+        (Some(window), Ok(camera)) => (window, camera),
+        _ => return,
+    };
+
+    let visible = match (shown.0, &grid) {
+        (Some(field), Some(_)) => visible_tiles(&config, window, transform, projection).map(|range| (field, range)),
+        _ => None,
+    };
+
+    let grid_changed = grid.as_ref().is_some_and(|grid| grid.is_changed());
+    if *labelled == visible && !grid_changed && !config.is_changed() {
+        return;
+    }
+    *labelled = visible;
+
+    for entity in labels.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let (grid, (field, (min_x, max_x, min_y, max_y))) = match (grid, visible) {
+        (Some(grid), Some(visible)) => (grid, visible),
+        _ => return,
+    };
+
+    let font = asset_server.load(FONT_PATH);
+    let label_scale = (config.world_units_per_tile() * 0.2) as f32 / LABEL_FONT_SIZE;
+    let mut count = 0;
+
+    for y in min_y..(max_y + 1) {
+        for x in min_x..(max_x + 1) {
+            let index = match grid.index(x, y) {
+                Some(index) if count < MAX_LABELS => index,
+                Some(_) => return,
+                None => continue,
+            };
+            count += 1;
+
+            let (world_x, world_y) = config.tile_to_world(x, y);
+
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(field.value(&grid.samples[index]), TextStyle {
+                        font: font.clone(),
+                        font_size: LABEL_FONT_SIZE,
+                        color: Color::YELLOW,
+                    }).with_alignment(TextAlignment::CENTER),
+                    transform: Transform {
+                        translation: Vec3::new(world_x as f32, world_y as f32, 2.0),
+                        scale: Vec3::splat(label_scale),
+                        ..default()
+                    },
+                    ..default()
+                },
+                FieldLabel,
+            ));
+        }
+    }
+}
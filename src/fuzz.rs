@@ -0,0 +1,84 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::biome_table::{BiomeTable, DEFAULT_BIOME_CSV};
+use crate::config::{BiomeModel, ColoringMode, EdgePolicy, MapConfig, PercipitationScale, Wind};
+use crate::tilemap::{MapGen, Tile, ATLAS_TILES};
+
+// Fixed so every run checks the same configs and points
+const FUZZ_SEED: u64 = 20220707;
+const FUZZ_RUNS: usize = 200;
+
+// Random in-range configs and world positions fed through get_tile_at_world. Every tile must have
+// finite color channels within 0..1 and an index inside the atlas. Returns one line per violation
+fn fuzz_tiles(runs: usize) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(FUZZ_SEED);
+    let mut problems = Vec::new();
+
+    for run in 0..runs {
+        let config = MapConfig {
+            map_size: rng.gen_range(8..400),
+            tile_scale: rng.gen_range(0.05..2.0),
+            world_age: rng.gen_range(0.0..=1.0),
+            biome_filter_passes: rng.gen_range(0..3),
+            water_dist_min: if rng.gen_bool(0.5) { f64::NEG_INFINITY } else { rng.gen_range(-1.0..1.0) },
+            biome_table: if rng.gen_bool(0.5) { BiomeTable::from_csv(DEFAULT_BIOME_CSV).ok() } else { None },
+            biome_model: if rng.gen_bool(0.5) { BiomeModel::Simple } else { BiomeModel::Holdridge },
+            edge_policy: [EdgePolicy::Ocean, EdgePolicy::Land, EdgePolicy::Mirror][rng.gen_range(0..3)],
+            coloring_mode: if rng.gen_bool(0.5) { ColoringMode::Continuous } else { ColoringMode::Biome },
+            percipitation_scale: if rng.gen_bool(0.5) { PercipitationScale::Log } else { PercipitationScale::Linear },
+            percipitation_cap_hardness: if rng.gen_bool(0.5) { f64::INFINITY } else { rng.gen_range(0.1..10.0) },
+            volcano_density: rng.gen_range(0.0..=1.0),
+            volcano_min_height: rng.gen_range(0.0..5000.0),
+            treeline_height: rng.gen_range(0.0..5000.0),
+            treeline_raggedness: rng.gen_range(0.0..500.0),
+            wind: Wind { direction: rng.gen_range(0.0..360.0), strength: rng.gen_range(0.0..=1.0) },
+            ..Default::default()
+        };
+
+        if let Err(error) = config.validate() {
+            problems.push(format!("run {}: generated an invalid config: {}", run, error));
+            continue;
+        }
+
+        let mapgen = MapGen::new(
+            rng.gen(),
+            rng.gen_range(0.2..3.0),
+            rng.gen_range(-0.9..0.9),
+            rng.gen_range(-0.9..0.9),
+            (rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0)),
+            &config,
+        );
+
+        let reach = config.map_axis_len() * 1.5;
+        for _ in 0..64 {
+            let (x, y) = (rng.gen_range(-reach..reach), rng.gen_range(-reach..reach));
+            let (tile, sample) = mapgen.get_tile_at_world(x, y);
+
+            if let Some(problem) = tile_problem(&tile) {
+                problems.push(format!("run {}: {:?} tile at ({:.1}, {:.1}) {}", run, sample.biome, x, y, problem));
+            }
+        }
+    }
+
+    problems
+}
+
+fn tile_problem(tile: &Tile) -> Option<String> {
+    if tile.index >= ATLAS_TILES {
+        return Some(format!("has atlas index {} of {}", tile.index, ATLAS_TILES));
+    }
+
+    let channels = tile.color.as_rgba_f32();
+    if channels.iter().any(|channel| !channel.is_finite() || !(0.0..=1.0).contains(channel)) {
+        return Some(format!("has color {:?}", channels));
+    }
+
+    None
+}
+
+#[test]
+fn random_configs_give_only_valid_tiles() {
+    let problems = fuzz_tiles(FUZZ_RUNS);
+    assert!(problems.is_empty(), "{} problems, the first: {}", problems.len(), problems[0]);
+}
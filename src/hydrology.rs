@@ -0,0 +1,341 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::config::MapConfig;
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::MapGrid;
+
+const RIVER_COLOR: Color = Color::rgb(0.15, 0.45, 0.9);
+const LAKE_COLOR: Color = Color::rgb(0.1, 0.35, 0.75);
+
+// River width as a share of a tile's width
+const RIVER_WIDTH: f32 = 0.4;
+
+// Grid cells between river sources on each axis
+const RIVER_SOURCE_SPACING: usize = 6;
+
+// Above the tiles, below the coastline
+const LAKE_Z: f32 = 0.6;
+const RIVER_Z: f32 = 0.65;
+
+pub struct HydrologyPlugin;
+
+#[derive(Resource)]
+struct ShowHydrology(bool);
+
+#[derive(Component)]
+struct WaterMarker;
+
+impl Plugin for HydrologyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowHydrology(false))
+            .add_system(toggle_hydrology)
+            .add_system(update_hydrology);
+    }
+}
+
+// Where a river stops
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RiverEnd {
+    Ocean,
+    // An endorheic lake, which keeps the water
+    Lake(u32),
+    // Flat ground with nowhere lower to go
+    Sink,
+    // Flowed off the grid
+    Edge,
+}
+
+// Grid indices a river passes, from its source
+#[derive(Clone, PartialEq, Debug)]
+pub struct River {
+    pub path: Vec<usize>,
+    pub end: RiverEnd,
+}
+
+// Open priority flood cell, ordered so the BinaryHeap pops the lowest water level first
+struct FloodCell {
+    level: f64,
+    index: usize,
+}
+
+impl PartialEq for FloodCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.level == other.level
+    }
+}
+
+impl Eq for FloodCell {}
+
+impl PartialOrd for FloodCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FloodCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.level.total_cmp(&self.level)
+    }
+}
+
+// Lakes of a row-major height grid and how water drains out of them. The grid is flooded from its
+// edges and the ocean up; land left under the water level is lake, filled to the level at which it
+// spills over its lowest rim
+pub struct Hydrology {
+    width: usize,
+    heights: Vec<f64>,
+    // Water level each cell fills to
+    levels: Vec<f64>,
+    // Cell each cell drains toward on the way out of the grid, None for the edges and ocean
+    drains: Vec<Option<usize>>,
+    lakes: Vec<Option<u32>>,
+    lake_count: u32,
+}
+
+impl Hydrology {
+    pub fn new(heights: &[f64], width: usize) -> Self {
+        let width = width.max(1);
+        let rows = heights.len() / width;
+        let mut levels = vec![f64::INFINITY; heights.len()];
+        let mut drains = vec![None; heights.len()];
+        let mut open = BinaryHeap::new();
+
+        for (index, &height) in heights.iter().enumerate() {
+            let (x, y) = (index % width, index / width);
+            if height <= 0.0 || x == 0 || y == 0 || x + 1 == width || y + 1 == rows {
+                levels[index] = height;
+                open.push(FloodCell { level: height, index });
+            }
+        }
+
+        let mut hydrology = Hydrology { width, heights: heights.to_vec(), levels: Vec::new(), drains: Vec::new(), lakes: Vec::new(), lake_count: 0 };
+
+        while let Some(FloodCell { level, index }) = open.pop() {
+            if level > levels[index] {
+                continue;
+            }
+
+            for next in hydrology.neighbours(index) {
+                if levels[next] == f64::INFINITY {
+                    levels[next] = heights[next].max(level);
+                    drains[next] = Some(index);
+                    open.push(FloodCell { level: levels[next], index: next });
+                }
+            }
+        }
+
+        hydrology.levels = levels;
+        hydrology.drains = drains;
+        hydrology.label_lakes();
+        hydrology
+    }
+
+    fn neighbours(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = ((index % self.width) as i64, (index / self.width) as i64);
+        let rows = (self.heights.len() / self.width) as i64;
+
+        [(1, 0), (-1, 0), (0, 1), (0, -1)].into_iter()
+            .map(move |(dx, dy)| (x + dx, y + dy))
+            .filter(move |&(x, y)| x >= 0 && y >= 0 && x < self.width as i64 && y < rows)
+            .map(move |(x, y)| y as usize * self.width + x as usize)
+    }
+
+    fn is_lake_cell(&self, index: usize) -> bool {
+        self.heights[index] > 0.0 && self.levels[index] > self.heights[index]
+    }
+
+    // Numbers the 4-connected lake cells, one id per lake
+    fn label_lakes(&mut self) {
+        self.lakes = vec![None; self.heights.len()];
+
+        for start in 0..self.heights.len() {
+            if self.lakes[start].is_some() || !self.is_lake_cell(start) {
+                continue;
+            }
+
+            let lake = self.lake_count;
+            self.lake_count += 1;
+            self.lakes[start] = Some(lake);
+            let mut stack = vec![start];
+
+            while let Some(index) = stack.pop() {
+                let next: Vec<usize> = self.neighbours(index)
+                    .filter(|&next| self.lakes[next].is_none() && self.is_lake_cell(next))
+                    .collect();
+                for next in next {
+                    self.lakes[next] = Some(lake);
+                    stack.push(next);
+                }
+            }
+        }
+    }
+
+    pub fn lake(&self, index: usize) -> Option<u32> {
+        self.lakes[index]
+    }
+
+    #[cfg(test)]
+    pub fn lake_count(&self) -> u32 {
+        self.lake_count
+    }
+
+    // Follows the steepest descent from source. A river reaching a lake ends there, or with overflow
+    // runs across it and out over its spill point, carrying on downhill once below the lake's surface
+    pub fn trace_river(&self, source: usize, overflow: bool) -> River {
+        let mut path = vec![source];
+        let mut current = source;
+
+        // Every step either descends or leaves a lake for good, so no river is longer than the grid
+        while path.len() <= self.heights.len() {
+            if self.heights[current] <= 0.0 {
+                return River { path, end: RiverEnd::Ocean };
+            }
+
+            if let Some(lake) = self.lakes[current] {
+                if !overflow {
+                    return River { path, end: RiverEnd::Lake(lake) };
+                }
+
+                let surface = self.levels[current];
+                while let Some(next) = self.drains[current] {
+                    current = next;
+                    path.push(current);
+                    if self.lakes[current] != Some(lake) && self.heights[current] < surface {
+                        break;
+                    }
+                }
+                if self.drains[current].is_none() && self.heights[current] > 0.0 {
+                    return River { path, end: RiverEnd::Edge };
+                }
+                continue;
+            }
+
+            let lowest = self.neighbours(current)
+                .filter(|&next| self.heights[next] < self.heights[current])
+                .min_by(|&a, &b| self.heights[a].total_cmp(&self.heights[b]));
+
+            match lowest {
+                Some(next) => {
+                    current = next;
+                    path.push(current);
+                }
+                None if self.drains[current].is_none() => return River { path, end: RiverEnd::Edge },
+                None => return River { path, end: RiverEnd::Sink },
+            }
+        }
+
+        River { path, end: RiverEnd::Sink }
+    }
+}
+
+fn toggle_hydrology(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut show: ResMut<ShowHydrology>) {
+    if bindings.just_pressed(&keyboard, Action::ToggleRivers) {
+        show.0 = !show.0;
+    }
+}
+
+// Redraws the lakes, and rivers from a lattice of sources at least river_source_height high, whenever
+// the map or the toggle changes
+fn update_hydrology(
+    mut commands: Commands,
+    config: Res<MapConfig>,
+    show: Res<ShowHydrology>,
+    grid: Option<Res<MapGrid>>,
+    markers: Query<Entity, With<WaterMarker>>,
+) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
+
+    if !grid.is_changed() && !show.is_changed() {
+        return;
+    }
+
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    if !show.0 {
+        return;
+    }
+
+    let heights: Vec<f64> = grid.samples.iter().map(|sample| sample.height).collect();
+    let width = grid.width() as usize;
+    let hydrology = Hydrology::new(&heights, width);
+    let tile = (config.world_units_per_tile() * grid.stride as f64) as f32;
+
+    let marker = |commands: &mut Commands, index: usize, color: Color, size: f32, z: f32| {
+        let (x, y) = grid.coords(index);
+        let (world_x, world_y) = config.tile_to_world(x, y);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(world_x as f32, world_y as f32, z),
+                ..default()
+            },
+            WaterMarker,
+        ));
+    };
+
+    for index in (0..heights.len()).filter(|&index| hydrology.lake(index).is_some()) {
+        marker(&mut commands, index, LAKE_COLOR, tile, LAKE_Z);
+    }
+
+    for y in (RIVER_SOURCE_SPACING / 2..heights.len() / width).step_by(RIVER_SOURCE_SPACING) {
+        for x in (RIVER_SOURCE_SPACING / 2..width).step_by(RIVER_SOURCE_SPACING) {
+            let source = y * width + x;
+            if heights[source] < config.river_source_height {
+                continue;
+            }
+
+            let river = hydrology.trace_river(source, config.lake_overflow);
+            for &index in river.path.iter().filter(|&&index| heights[index] > 0.0 && hydrology.lake(index).is_none()) {
+                marker(&mut commands, index, RIVER_COLOR, tile * RIVER_WIDTH, RIVER_Z);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A slope down to an ocean in the east with a pit partway down. The river from the west edge falls
+    // into the pit, which spills east over its lowest rim
+    const BASIN_WIDTH: usize = 7;
+    const BASIN_HEIGHTS: [f64; 35] = [
+        90.0, 80.0, 70.0, 60.0, 50.0, 40.0, -10.0,
+        90.0, 80.0, 70.0, 60.0, 50.0, 40.0, -10.0,
+        90.0, 80.0, 70.0, 10.0, 50.0, 40.0, -10.0,
+        90.0, 80.0, 70.0, 60.0, 50.0, 40.0, -10.0,
+        90.0, 80.0, 70.0, 60.0, 50.0, 40.0, -10.0,
+    ];
+
+    // The river ends in the lake, or with overflow crosses it on to the ocean
+    #[test]
+    fn river_fills_the_pit_then_overflows_to_the_ocean() {
+        let hydrology = Hydrology::new(&BASIN_HEIGHTS, BASIN_WIDTH);
+        let source = 2 * BASIN_WIDTH;
+        let pit = 2 * BASIN_WIDTH + 3;
+        let rim = pit + 1;
+
+        assert_eq!(hydrology.lake_count(), 1);
+        assert_eq!(hydrology.lake(pit), Some(0));
+
+        let river = hydrology.trace_river(source, false);
+        assert_eq!(river.end, RiverEnd::Lake(0));
+        assert_eq!(river.path.last(), Some(&pit));
+        assert!(!river.path.contains(&rim));
+
+        let river = hydrology.trace_river(source, true);
+        assert_eq!(river.end, RiverEnd::Ocean);
+        assert!(river.path.contains(&pit) && river.path.contains(&rim), "the river ran {:?}", river.path);
+    }
+}
@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+
+use crate::camera::cursor_world_position;
+use crate::config::{MapConfig, FONT_PATH};
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::MapGrid;
+
+// Side [px] of each square of the neighbour grid
+const CELL_PIXELS: f32 = 14.0;
+
+// Gap [px] between the cursor and the panel's top-left corner
+const CURSOR_OFFSET: f32 = 16.0;
+
+// Shown for neighbours past the map edge
+const EMPTY_CELL_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+pub struct InspectorPlugin;
+
+#[derive(Resource)]
+struct ShowInspector(bool);
+
+#[derive(Component)]
+struct InspectorPanel;
+
+// Neighbour offset in grid steps, (0, 0) for the hovered tile
+#[derive(Component)]
+struct InspectorCell(i32, i32);
+
+#[derive(Component)]
+struct InspectorText;
+
+// Biome region of each grid tile, see GridFields::regions. Flood filling is too slow to redo every
+// frame, so it waits until the panel is shown after the grid or config changed
+#[derive(Default)]
+struct Regions {
+    ids: Vec<u32>,
+    count: u32,
+    stale: bool,
+}
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowInspector(false))
+            .add_startup_system(spawn_inspector)
+            .add_system(update_inspector);
+    }
+}
+
+// A 3x3 grid of the hovered tile and its neighbours in their biome colors, north up, with the
+// hovered tile's climate written beneath
+fn spawn_inspector(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Px(3.0 * CELL_PIXELS), Val::Px(3.0 * CELL_PIXELS)),
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..default()
+        },
+        InspectorPanel,
+    )).with_children(|panel| {
+        for row in 0..3 {
+            for column in 0..3 {
+                panel.spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: Val::Px(column as f32 * CELL_PIXELS),
+                                top: Val::Px(row as f32 * CELL_PIXELS),
+                                ..default()
+                            },
+                            size: Size::new(Val::Px(CELL_PIXELS), Val::Px(CELL_PIXELS)),
+                            ..default()
+                        },
+                        background_color: EMPTY_CELL_COLOR.into(),
+                        ..default()
+                    },
+                    InspectorCell(column - 1, 1 - row),
+                ));
+            }
+        }
+
+        panel.spawn((
+            TextBundle::from_section("", TextStyle {
+                font: asset_server.load(FONT_PATH),
+                font_size: 16.0,
+                color: Color::WHITE,
+            }).with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(3.0 * CELL_PIXELS + 4.0),
+                    left: Val::Px(0.0),
+                    ..default()
+                },
+                ..default()
+            }),
+            InspectorText,
+        ));
+    });
+}
+
+// Y toggles the panel, which follows the cursor and reads the generated grid, so it shows the
+// tiles as drawn after erosion and smoothing, along with the biome region the hovered tile is in
+#[allow(clippy::too_many_arguments)]
+fn update_inspector(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut show: ResMut<ShowInspector>,
+    windows: Res<Windows>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    config: Res<MapConfig>,
+    grid: Option<Res<MapGrid>>,
+    mut panel: Query<(&mut Style, &mut Visibility), With<InspectorPanel>>,
+    mut cells: Query<(&InspectorCell, &mut BackgroundColor)>,
+    mut text: Query<&mut Text, With<InspectorText>>,
+    mut regions: Local<Regions>,
+) {
+    if bindings.just_pressed(&keyboard, Action::ToggleInspector) {
+        show.0 = !show.0;
+    }
+
+    if config.is_changed() || grid.as_ref().is_some_and(|grid| grid.is_changed()) {
+        regions.stale = true;
+    }
+
+    let (mut style, mut visibility) = match panel.get_single_mut() {
+        Ok(panel) => panel,
+        Err(_) => return,
+    };
+
+    let hovered = match (windows.get_primary(), camera.get_single(), grid) {
+        (Some(window), Ok((transform, projection)), Some(grid)) if show.0 => {
+            window.cursor_position().zip(cursor_world_position(window, transform, projection))
+                .map(|(screen, world)| (window.height(), screen, world, grid))
+        }
+        _ => None,
+    };
+
+    let (window_height, screen, world, grid) = match hovered {
+        Some(hovered) => hovered,
+        None => {
+            visibility.is_visible = false;
+            return;
+        }
+    };
+
+    // Cursor positions count up from the bottom of the window, UI positions down from the top
+    visibility.is_visible = true;
+    style.position.left = Val::Px(screen.x + CURSOR_OFFSET);
+    style.position.top = Val::Px(window_height - screen.y + CURSOR_OFFSET);
+
+    if regions.stale || regions.ids.len() != grid.samples.len() {
+        regions.ids = grid.fields().regions(config.region_connectivity);
+        regions.count = regions.ids.iter().max().map_or(0, |max| max + 1);
+        regions.stale = false;
+    }
+
+    let (x, y) = config.world_to_tile(world.x as f64, world.y as f64);
+    let (x, y) = grid.snap(x, y);
+
+    for (cell, mut color) in cells.iter_mut() {
+        let neighbour = grid.index(x + cell.0 * grid.stride, y + cell.1 * grid.stride);
+        color.0 = neighbour.map_or(EMPTY_CELL_COLOR, |index| grid.tiles[index].color);
+    }
+
+    let description = match grid.index(x, y) {
+        Some(index) => {
+            let sample = &grid.samples[index];
+            let elevation = sample.ocean_depth().map(|depth| format!("{:.0} m deep", depth))
+                .or_else(|| sample.land_height().map(|height| format!("{:.0} m high", height)))
+                .unwrap_or_default();
+            format!(
+                "{:?}, region {} of {}\n{}, {:.1} C, {:.0} mm",
+                sample.biome, regions.ids[index], regions.count, elevation, sample.temperature, sample.percipitation,
+            )
+        }
+        None => "Off the map".to_string(),
+    };
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = description.clone();
+    }
+}
@@ -0,0 +1,498 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::error::{Error, Result};
+
+pub const KEY_BINDINGS_PATH: &str = "keys.csv";
+
+pub struct KeyBindingsPlugin;
+
+// Everything a key does. The Shift variant of an action is an action of its own, as ScrollNoiseNorth
+// is Shift + PanNorth's key, and the plain action only answers with Shift released. Explore mode moves
+// the player with the pan keys
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    RandomSeed,
+    ReseedKeepingView,
+    DailySeed,
+    NextSeed,
+    PreviousSeed,
+    PanNorth,
+    PanSouth,
+    PanWest,
+    PanEast,
+    ScrollNoiseNorth,
+    ScrollNoiseSouth,
+    ScrollNoiseWest,
+    ScrollNoiseEast,
+    ZoomIn,
+    ZoomOut,
+    CenterOnCursor,
+    FitMapToWindow,
+    ToggleLabels,
+    ToggleClimateZones,
+    ToggleDayNight,
+    ToggleStats,
+    CycleZoomScaling,
+    CycleBiomeModel,
+    CycleColoringMode,
+    CycleQuality,
+    CycleDebugView,
+    CycleColorSpace,
+    CycleSeedFormat,
+    CycleTileFiltering,
+    ToggleMsaa,
+    CyclePreset,
+    IsolateNextBiome,
+    InspectCursor,
+    ToggleCenterBiomeReport,
+    ExportTiles,
+    ToggleHeightView,
+    LowerHeightExaggeration,
+    RaiseHeightExaggeration,
+    LowerBrightness,
+    RaiseBrightness,
+    LowerContrast,
+    RaiseContrast,
+    LowerSaturation,
+    RaiseSaturation,
+    ResetColorAdjustments,
+    FreezeNoon,
+    FreezeDusk,
+    FreezeMidnight,
+    FreezeDawn,
+    PauseClock,
+    SlowDownClock,
+    SpeedUpClock,
+    ToggleBrush,
+    ToggleEraser,
+    CycleBrushBiome,
+    ShrinkBrush,
+    GrowBrush,
+    ToggleCoastline,
+    ToggleRivers,
+    ToggleCoordLabels,
+    CycleFieldLabels,
+    ToggleInspector,
+    ToggleParameters,
+    ToggleWindRose,
+    ToggleExplore,
+    AddBookmark,
+    Bookmark1,
+    Bookmark2,
+    Bookmark3,
+    Bookmark4,
+    Bookmark5,
+    Bookmark6,
+    Bookmark7,
+    Bookmark8,
+    Bookmark9,
+    DeleteBookmark1,
+    DeleteBookmark2,
+    DeleteBookmark3,
+    DeleteBookmark4,
+    DeleteBookmark5,
+    DeleteBookmark6,
+    DeleteBookmark7,
+    DeleteBookmark8,
+    DeleteBookmark9,
+    ToggleLegend,
+}
+
+impl Action {
+    pub const ALL: [Action; 85] = [
+        Action::RandomSeed,
+        Action::ReseedKeepingView,
+        Action::DailySeed,
+        Action::NextSeed,
+        Action::PreviousSeed,
+        Action::PanNorth,
+        Action::PanSouth,
+        Action::PanWest,
+        Action::PanEast,
+        Action::ScrollNoiseNorth,
+        Action::ScrollNoiseSouth,
+        Action::ScrollNoiseWest,
+        Action::ScrollNoiseEast,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::CenterOnCursor,
+        Action::FitMapToWindow,
+        Action::ToggleLabels,
+        Action::ToggleClimateZones,
+        Action::ToggleDayNight,
+        Action::ToggleStats,
+        Action::CycleZoomScaling,
+        Action::CycleBiomeModel,
+        Action::CycleColoringMode,
+        Action::CycleQuality,
+        Action::CycleDebugView,
+        Action::CycleColorSpace,
+        Action::CycleSeedFormat,
+        Action::CycleTileFiltering,
+        Action::ToggleMsaa,
+        Action::CyclePreset,
+        Action::IsolateNextBiome,
+        Action::InspectCursor,
+        Action::ToggleCenterBiomeReport,
+        Action::ExportTiles,
+        Action::ToggleHeightView,
+        Action::LowerHeightExaggeration,
+        Action::RaiseHeightExaggeration,
+        Action::LowerBrightness,
+        Action::RaiseBrightness,
+        Action::LowerContrast,
+        Action::RaiseContrast,
+        Action::LowerSaturation,
+        Action::RaiseSaturation,
+        Action::ResetColorAdjustments,
+        Action::FreezeNoon,
+        Action::FreezeDusk,
+        Action::FreezeMidnight,
+        Action::FreezeDawn,
+        Action::PauseClock,
+        Action::SlowDownClock,
+        Action::SpeedUpClock,
+        Action::ToggleBrush,
+        Action::ToggleEraser,
+        Action::CycleBrushBiome,
+        Action::ShrinkBrush,
+        Action::GrowBrush,
+        Action::ToggleCoastline,
+        Action::ToggleRivers,
+        Action::ToggleCoordLabels,
+        Action::CycleFieldLabels,
+        Action::ToggleInspector,
+        Action::ToggleParameters,
+        Action::ToggleWindRose,
+        Action::ToggleExplore,
+        Action::AddBookmark,
+        Action::Bookmark1,
+        Action::Bookmark2,
+        Action::Bookmark3,
+        Action::Bookmark4,
+        Action::Bookmark5,
+        Action::Bookmark6,
+        Action::Bookmark7,
+        Action::Bookmark8,
+        Action::Bookmark9,
+        Action::DeleteBookmark1,
+        Action::DeleteBookmark2,
+        Action::DeleteBookmark3,
+        Action::DeleteBookmark4,
+        Action::DeleteBookmark5,
+        Action::DeleteBookmark6,
+        Action::DeleteBookmark7,
+        Action::DeleteBookmark8,
+        Action::DeleteBookmark9,
+        Action::ToggleLegend,
+    ];
+
+    // The key each action has unless keys.csv binds another. A Shift variant defaults to its plain
+    // action's key
+    fn default_key(self) -> KeyCode {
+        match self {
+            Action::RandomSeed => KeyCode::Space,
+            Action::ReseedKeepingView => KeyCode::Space,
+            Action::DailySeed => KeyCode::T,
+            Action::NextSeed => KeyCode::PageUp,
+            Action::PreviousSeed => KeyCode::PageDown,
+            Action::PanNorth => KeyCode::W,
+            Action::PanSouth => KeyCode::S,
+            Action::PanWest => KeyCode::A,
+            Action::PanEast => KeyCode::D,
+            Action::ScrollNoiseNorth => KeyCode::W,
+            Action::ScrollNoiseSouth => KeyCode::S,
+            Action::ScrollNoiseWest => KeyCode::A,
+            Action::ScrollNoiseEast => KeyCode::D,
+            Action::ZoomIn => KeyCode::Up,
+            Action::ZoomOut => KeyCode::Down,
+            Action::CenterOnCursor => KeyCode::F,
+            Action::FitMapToWindow => KeyCode::Key0,
+            Action::ToggleLabels => KeyCode::L,
+            Action::ToggleClimateZones => KeyCode::Z,
+            Action::ToggleDayNight => KeyCode::N,
+            Action::ToggleStats => KeyCode::Left,
+            Action::CycleZoomScaling => KeyCode::X,
+            Action::CycleBiomeModel => KeyCode::U,
+            Action::CycleColoringMode => KeyCode::Delete,
+            Action::CycleQuality => KeyCode::Home,
+            Action::CycleDebugView => KeyCode::V,
+            Action::CycleColorSpace => KeyCode::C,
+            Action::CycleSeedFormat => KeyCode::O,
+            Action::CycleTileFiltering => KeyCode::J,
+            Action::ToggleMsaa => KeyCode::J,
+            Action::CyclePreset => KeyCode::Right,
+            Action::IsolateNextBiome => KeyCode::I,
+            Action::InspectCursor => KeyCode::R,
+            Action::ToggleCenterBiomeReport => KeyCode::R,
+            Action::ExportTiles => KeyCode::Insert,
+            Action::ToggleHeightView => KeyCode::H,
+            Action::LowerHeightExaggeration => KeyCode::Minus,
+            Action::RaiseHeightExaggeration => KeyCode::Equals,
+            Action::LowerBrightness => KeyCode::F5,
+            Action::RaiseBrightness => KeyCode::F6,
+            Action::LowerContrast => KeyCode::F7,
+            Action::RaiseContrast => KeyCode::F8,
+            Action::LowerSaturation => KeyCode::F9,
+            Action::RaiseSaturation => KeyCode::F10,
+            Action::ResetColorAdjustments => KeyCode::F12,
+            Action::FreezeNoon => KeyCode::F1,
+            Action::FreezeDusk => KeyCode::F2,
+            Action::FreezeMidnight => KeyCode::F3,
+            Action::FreezeDawn => KeyCode::F4,
+            Action::PauseClock => KeyCode::K,
+            Action::SlowDownClock => KeyCode::Comma,
+            Action::SpeedUpClock => KeyCode::Period,
+            Action::ToggleBrush => KeyCode::P,
+            Action::ToggleEraser => KeyCode::E,
+            Action::CycleBrushBiome => KeyCode::B,
+            Action::ShrinkBrush => KeyCode::LBracket,
+            Action::GrowBrush => KeyCode::RBracket,
+            Action::ToggleCoastline => KeyCode::Q,
+            Action::ToggleRivers => KeyCode::Q,
+            Action::ToggleCoordLabels => KeyCode::G,
+            Action::CycleFieldLabels => KeyCode::G,
+            Action::ToggleInspector => KeyCode::Y,
+            Action::ToggleParameters => KeyCode::End,
+            Action::ToggleWindRose => KeyCode::F11,
+            Action::ToggleExplore => KeyCode::Tab,
+            Action::AddBookmark => KeyCode::M,
+            Action::Bookmark1 => KeyCode::Key1,
+            Action::Bookmark2 => KeyCode::Key2,
+            Action::Bookmark3 => KeyCode::Key3,
+            Action::Bookmark4 => KeyCode::Key4,
+            Action::Bookmark5 => KeyCode::Key5,
+            Action::Bookmark6 => KeyCode::Key6,
+            Action::Bookmark7 => KeyCode::Key7,
+            Action::Bookmark8 => KeyCode::Key8,
+            Action::Bookmark9 => KeyCode::Key9,
+            Action::DeleteBookmark1 => KeyCode::Key1,
+            Action::DeleteBookmark2 => KeyCode::Key2,
+            Action::DeleteBookmark3 => KeyCode::Key3,
+            Action::DeleteBookmark4 => KeyCode::Key4,
+            Action::DeleteBookmark5 => KeyCode::Key5,
+            Action::DeleteBookmark6 => KeyCode::Key6,
+            Action::DeleteBookmark7 => KeyCode::Key7,
+            Action::DeleteBookmark8 => KeyCode::Key8,
+            Action::DeleteBookmark9 => KeyCode::Key9,
+            Action::ToggleLegend => KeyCode::Semicolon,
+        }
+    }
+
+    // Actions done with Shift + their key, wherever it is bound
+    fn needs_shift(self) -> bool {
+        matches!(self,
+            Action::ReseedKeepingView
+            | Action::ScrollNoiseNorth | Action::ScrollNoiseSouth | Action::ScrollNoiseWest | Action::ScrollNoiseEast
+            | Action::ToggleMsaa
+            | Action::ToggleCenterBiomeReport
+            | Action::ToggleRivers
+            | Action::CycleFieldLabels
+            | Action::DeleteBookmark1 | Action::DeleteBookmark2 | Action::DeleteBookmark3
+            | Action::DeleteBookmark4 | Action::DeleteBookmark5 | Action::DeleteBookmark6
+            | Action::DeleteBookmark7 | Action::DeleteBookmark8 | Action::DeleteBookmark9
+        )
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| format!("{:?}", action) == name)
+    }
+}
+
+// Keys a binding may name, written as KeyCode spells them
+const BINDABLE_KEYS: [KeyCode; 69] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H,
+    KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P,
+    KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X,
+    KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+    KeyCode::Space, KeyCode::Tab, KeyCode::Return, KeyCode::Back, KeyCode::Delete, KeyCode::Insert,
+    KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::Minus, KeyCode::Equals, KeyCode::Comma, KeyCode::Period, KeyCode::LBracket, KeyCode::RBracket,
+    KeyCode::Semicolon,
+];
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.iter().copied().find(|key| format!("{:?}", key) == name)
+}
+
+// The key bound to each action
+#[derive(Resource, Clone, Debug)]
+pub struct KeyBindings(HashMap<Action, KeyCode>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings(Action::ALL.iter().map(|&action| (action, action.default_key())).collect())
+    }
+}
+
+impl Plugin for KeyBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(KeyBindings::default())
+            .add_startup_system(setup_key_bindings);
+    }
+}
+
+impl KeyBindings {
+    // "action,key" lines, as in "ToggleLabels,L". Actions left out keep their default key, and a Shift
+    // variant bound to a new key still needs Shift with it
+    pub fn from_csv(csv: &str) -> Result<Self> {
+        let mut bindings = KeyBindings::default();
+
+        for (line_number, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("action,") {
+                continue;
+            }
+
+            let parse_error = |message: String| Error::Parse {
+                line: line_number + 1,
+                message,
+            };
+
+            let (action, key) = line.split_once(',')
+                .ok_or_else(|| parse_error(format!("expected 'action,key', found '{}'", line)))?;
+            let action = Action::from_name(action.trim())
+                .ok_or_else(|| parse_error(format!("unknown action '{}'", action.trim())))?;
+            let key = key_from_name(key.trim())
+                .ok_or_else(|| parse_error(format!("unknown key '{}'", key.trim())))?;
+
+            bindings.0.insert(action, key);
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.0.get(&action).copied().unwrap_or_else(|| action.default_key())
+    }
+
+    // Whether the action's key was just pressed, with Shift held exactly when the action needs it
+    pub fn just_pressed(&self, keyboard: &Input<KeyCode>, action: Action) -> bool {
+        keyboard.just_pressed(self.key(action)) && shift_pressed(keyboard) == action.needs_shift()
+    }
+
+    // Whether the action's key is held, with Shift as for just_pressed, for actions that repeat while it is
+    pub fn pressed(&self, keyboard: &Input<KeyCode>, action: Action) -> bool {
+        keyboard.pressed(self.key(action)) && shift_pressed(keyboard) == action.needs_shift()
+    }
+
+    // Keys bound to more than one action, each with the actions sharing it. An action needing Shift
+    // only clashes with others that need it too
+    pub fn conflicts(&self) -> Vec<(KeyCode, Vec<Action>)> {
+        let mut conflicts = Vec::new();
+
+        for key in BINDABLE_KEYS {
+            for shift in [false, true] {
+                let actions: Vec<Action> = Action::ALL.into_iter()
+                    .filter(|&action| self.key(action) == key && action.needs_shift() == shift)
+                    .collect();
+                if actions.len() > 1 {
+                    conflicts.push((key, actions));
+                }
+            }
+        }
+
+        conflicts
+    }
+}
+
+fn shift_pressed(keyboard: &Input<KeyCode>) -> bool {
+    keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift])
+}
+
+pub fn read_key_bindings(path: &str) -> Result<KeyBindings> {
+    KeyBindings::from_csv(&std::fs::read_to_string(path)?)
+}
+
+// Loads keys.csv over the defaults, keeping the defaults when it is missing or broken
+fn setup_key_bindings(mut bindings: ResMut<KeyBindings>) {
+    match read_key_bindings(KEY_BINDINGS_PATH) {
+        Ok(loaded) => *bindings = loaded,
+        Err(Error::Io(_)) => {}
+        Err(error) => warn!("Failed to load {}: {}, using the default keys", KEY_BINDINGS_PATH, error),
+    }
+
+    for (key, actions) in bindings.conflicts() {
+        warn!("{:?} is bound to several actions: {:?}", key, actions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: KeyCode) -> Input<KeyCode> {
+        let mut keyboard = Input::default();
+        keyboard.press(key);
+        keyboard
+    }
+
+    // The action's key, with Shift held for the actions that need it
+    fn press_action(bindings: &KeyBindings, action: Action) -> Input<KeyCode> {
+        let mut keyboard = press(bindings.key(action));
+        if action.needs_shift() {
+            keyboard.press(KeyCode::LShift);
+        }
+        keyboard
+    }
+
+    #[test]
+    fn default_keys_do_not_conflict() {
+        assert_eq!(KeyBindings::default().conflicts(), Vec::new());
+    }
+
+    #[test]
+    fn binding_a_taken_key_is_a_conflict() {
+        let bindings = KeyBindings::from_csv("action,key\nToggleLabels,Q\n").unwrap();
+
+        assert_eq!(bindings.conflicts(), vec![(KeyCode::Q, vec![Action::ToggleLabels, Action::ToggleCoastline])]);
+    }
+
+    // Shift picks between an action and its Shift variant on their shared key, and a Shift variant moved
+    // to a plain action's key takes it with Shift only
+    #[test]
+    fn shift_picks_the_variant_on_any_key() {
+        let shifted = |key| {
+            let mut keyboard = press(key);
+            keyboard.press(KeyCode::LShift);
+            keyboard
+        };
+        let defaults = KeyBindings::default();
+
+        assert!(defaults.just_pressed(&press(KeyCode::Q), Action::ToggleCoastline));
+        assert!(!defaults.just_pressed(&press(KeyCode::Q), Action::ToggleRivers));
+        assert!(defaults.just_pressed(&shifted(KeyCode::Q), Action::ToggleRivers));
+        assert!(!defaults.just_pressed(&shifted(KeyCode::Q), Action::ToggleCoastline));
+
+        let moved = KeyBindings::from_csv("action,key\nToggleRivers,K\n").unwrap();
+        assert_eq!(moved.conflicts(), Vec::new());
+        assert!(moved.just_pressed(&shifted(KeyCode::K), Action::ToggleRivers));
+        assert!(!moved.just_pressed(&press(KeyCode::K), Action::ToggleRivers));
+        assert!(moved.just_pressed(&press(KeyCode::K), Action::PauseClock));
+    }
+
+    // Swapping ToggleLabels and ToggleCoastline between L and Q makes each answer the other's key only,
+    // while every other action keeps its key
+    #[test]
+    fn remapped_action_answers_its_new_key_only() {
+        let bindings = KeyBindings::from_csv("action,key\nToggleLabels,Q\nToggleCoastline,L\n").unwrap();
+        let defaults = KeyBindings::default();
+        let swapped = [Action::ToggleLabels, Action::ToggleCoastline];
+
+        assert_eq!(bindings.conflicts(), Vec::new());
+        assert!(bindings.just_pressed(&press(KeyCode::Q), Action::ToggleLabels));
+        assert!(!bindings.just_pressed(&press(KeyCode::L), Action::ToggleLabels));
+        assert!(bindings.just_pressed(&press(KeyCode::L), Action::ToggleCoastline));
+        assert!(!bindings.just_pressed(&press(KeyCode::Q), Action::ToggleCoastline));
+        for action in Action::ALL.into_iter().filter(|action| !swapped.contains(action)) {
+            assert!(bindings.just_pressed(&press_action(&defaults, action), action), "{:?} lost its key", action);
+            assert!(!bindings.just_pressed(&press(KeyCode::Q), action), "{:?} answers Q", action);
+        }
+    }
+}
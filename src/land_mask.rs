@@ -0,0 +1,124 @@
+use image::GrayImage;
+use std::sync::Arc;
+
+use crate::error::Result;
+
+// Luminance at and above which a mask pixel is land
+const LAND_LUMINANCE: u8 = 128;
+
+// Closest [m] the mask lets heights come to sea level, so masked land stays above it and masked ocean
+// below it
+const MASK_SHORE: f64 = 5.0;
+
+// Share of the relief kept when the mask lifts ocean into land or sinks land into ocean, so drowned
+// mountains become shallow banks and raised sea floor low hills instead of flat plates
+const MASK_FLIPPED_RELIEF: f64 = 0.1;
+
+// Black and white image of where land and ocean must be. It is stretched over the map at zoom 1, its
+// top to the north, and leaves anything beyond its edges unconstrained
+#[derive(Clone)]
+pub struct LandMask {
+    width: u32,
+    height: u32,
+    // Shared, since every MapGen clones the config
+    land: Arc<Vec<bool>>,
+}
+
+impl LandMask {
+    pub fn from_image(image: &GrayImage) -> Self {
+        Self {
+            width: image.width(),
+            height: image.height(),
+            land: Arc::new(image.pixels().map(|pixel| pixel.0[0] >= LAND_LUMINANCE).collect()),
+        }
+    }
+
+    // Whether the mask wants land at falloff coordinates, each -1 to 1 across the map, None outside it
+    pub fn land_at(&self, x_dis: f64, y_dis: f64) -> Option<bool> {
+        if self.width == 0 || self.height == 0 || x_dis.abs() > 1.0 || y_dis.abs() > 1.0 {
+            return None;
+        }
+
+        let column = (((x_dis + 1.0) / 2.0 * self.width as f64) as u32).min(self.width - 1);
+        let row = (((1.0 - y_dis) / 2.0 * self.height as f64) as u32).min(self.height - 1);
+        Some(self.land[(row * self.width + column) as usize])
+    }
+
+    // Mirrors heights on the wrong side of the shore back across it, flattened, keeping the detail of
+    // the noise within each region
+    pub fn constrain(&self, height: f64, x_dis: f64, y_dis: f64) -> f64 {
+        match self.land_at(x_dis, y_dis) {
+            Some(true) if height < MASK_SHORE => MASK_SHORE + (MASK_SHORE - height) * MASK_FLIPPED_RELIEF,
+            Some(false) if height > -MASK_SHORE => -MASK_SHORE - (height + MASK_SHORE) * MASK_FLIPPED_RELIEF,
+            _ => height,
+        }
+    }
+
+    // Hard limit for heights that moved after constrain, like eroded ones
+    pub fn clamp(&self, height: f64, x_dis: f64, y_dis: f64) -> f64 {
+        match self.land_at(x_dis, y_dis) {
+            Some(true) => height.max(MASK_SHORE),
+            Some(false) => height.min(-MASK_SHORE),
+            None => height,
+        }
+    }
+}
+
+pub fn read_land_mask(path: &str) -> Result<LandMask> {
+    Ok(LandMask::from_image(&image::open(path)?.to_luma8()))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+    use crate::config::MapConfig;
+    use crate::test_support::*;
+    use crate::tilemap::{Biome, MapGen};
+
+    // Mask side [px]
+    const MASK_SIZE: u32 = 16;
+
+    // Land in a corner the falloff leaves ocean and in a ring around the middle, ocean in the middle of
+    // the continent, so the mask has to overrule the terrain both ways
+    fn mask_wants_land(x: u32, y: u32) -> bool {
+        let center = MASK_SIZE as f64 / 2.0;
+        let radius = ((x as f64 + 0.5 - center).powi(2) + (y as f64 + 0.5 - center).powi(2)).sqrt();
+        (x < 5 && y < 5) || (2.5..6.0).contains(&radius)
+    }
+
+    // Every tile the mask covers classifies as it says, while the masked land keeps at least 100 m of relief
+    #[test]
+    fn masked_tiles_follow_the_mask() {
+        let mask = GrayImage::from_fn(MASK_SIZE, MASK_SIZE, |x, y| Luma([if mask_wants_land(x, y) { 255 } else { 0 }]));
+        let config = MapConfig { land_mask: Some(LandMask::from_image(&mask)), ..reference_config() };
+
+        for seed in [REFERENCE_SEED, 1] {
+            for (zoom, x_shift, y_shift) in [(1.0, 0.0, 0.0), (1.5, 0.1, -0.1)] {
+                let mapgen = MapGen::new(seed, zoom, x_shift, y_shift, (0.0, 0.0), &config);
+                let grid = mapgen.generate_grid();
+                let (mut land, mut ocean) = (Vec::new(), 0);
+
+                for (index, sample) in grid.samples.iter().enumerate() {
+                    let (x, y) = grid.coords(index);
+                    match mapgen.masked_land(x, y) {
+                        Some(true) => {
+                            assert!(sample.height > 0.0 && sample.biome != Biome::Ocean, "seed {} masked land tile ({}, {}) is {:?}", seed, x, y, sample.biome);
+                            land.push(sample.height);
+                        }
+                        Some(false) => {
+                            assert!(sample.height <= 0.0 && sample.biome == Biome::Ocean, "seed {} masked ocean tile ({}, {}) is {:?}", seed, x, y, sample.biome);
+                            ocean += 1;
+                        }
+                        None => {}
+                    }
+                }
+
+                assert!(!land.is_empty() && ocean > 0, "seed {} at zoom {} has {} masked land and {} masked ocean tiles", seed, zoom, land.len(), ocean);
+                let relief = land.iter().cloned().fold(f64::NEG_INFINITY, f64::max) - land.iter().cloned().fold(f64::INFINITY, f64::min);
+                assert!(relief >= 100.0, "seed {} at zoom {}: masked land spans only {:.0} m", seed, zoom, relief);
+            }
+        }
+    }
+}
@@ -1,12 +1,240 @@
 use bevy::prelude::*;
 
+mod animation;
+mod biome_table;
+mod bookmarks;
+mod brush;
+mod camera;
+mod coastline;
+mod config;
+mod coord_labels;
+mod daily;
+mod error;
+mod explore;
+mod export;
+mod hydrology;
+mod field_labels;
+#[cfg(test)]
+mod fuzz;
+mod inspector;
+mod keybindings;
+mod land_mask;
+mod names;
+mod outline;
+mod overlay;
+mod parallax;
+mod parameters;
+mod pathfinding;
+mod presets;
+mod profile;
+mod similarity;
+mod stats;
+#[cfg(test)]
+mod test_support;
+mod tile_cache;
 mod tilemap;
+mod wind;
+mod window;
 
+use animation::AnimationPlugin;
+use bookmarks::BookmarkPlugin;
+use brush::BrushPlugin;
+use camera::CameraPlugin;
+use coastline::CoastlinePlugin;
+use coord_labels::CoordLabelPlugin;
+use explore::ExplorePlugin;
+use field_labels::FieldLabelPlugin;
+use hydrology::HydrologyPlugin;
+use inspector::InspectorPlugin;
+use keybindings::KeyBindingsPlugin;
+use outline::OutlinePlugin;
+use parallax::ParallaxPlugin;
+use parameters::ParameterPlugin;
+use pathfinding::PathPlugin;
+use presets::PresetPlugin;
+use stats::StatsPlugin;
 use tilemap::TileMapPlugin;
+use wind::WindPlugin;
+use window::WindowGeometryPlugin;
+
+// Seeds --search-similar compares by default and the number of best matches it prints
+const SEARCH_CANDIDATES: usize = 2000;
+const SEARCH_MATCHES: usize = 10;
 
 fn main() {
+    // --search-similar IMAGE [COUNT] lists the seeds among the first COUNT whose maps look most like the image,
+    // --export-seeds SEEDS DIR writes a PNG per seed, SEEDS being a list like 1,5,10-20, --export-fields SEED
+    // PATH writes each tile's fields as CSV, --export-tiles SEED PATH writes the tile metadata JSON other
+    // engines import, --export-adjacency SEED PATH writes a CSV of how often each pair of biomes borders each
+    // other, --export-bundle SEED DIR writes the map's PNG, fields CSV, tiles JSON and config RON with a
+    // manifest of their checksums, --verify-bundle DIR lists the bundle's files that no longer match it.
+    // Otherwise the app launches with the config from --config PRESET, at the view --zoom, --x-shift and
+    // --y-shift give
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    for (index, arg) in args.iter().enumerate() {
+        match arg.as_str() {
+            "--search-similar" => {
+                let target = match args.get(index + 1).map(|path| similarity::load_target(path)) {
+                    Some(Ok(target)) => target,
+                    Some(Err(error)) => {
+                        eprintln!("Failed to load the target image: {}", error);
+                        std::process::exit(1);
+                    }
+                    None => {
+                        eprintln!("--search-similar needs an image path");
+                        std::process::exit(1);
+                    }
+                };
+                let candidates = args.get(index + 2).and_then(|count| count.parse().ok()).unwrap_or(SEARCH_CANDIDATES);
+
+                for (seed, distance) in similarity::search_similar(&target, candidates, SEARCH_MATCHES) {
+                    println!("{:>10} {:.0}", seed, distance);
+                }
+                return;
+            }
+            "--export-seeds" => {
+                let (spec, dir) = match (args.get(index + 1), args.get(index + 2)) {
+                    (Some(spec), Some(dir)) => (spec, dir),
+                    _ => {
+                        eprintln!("--export-seeds needs a seed list and an output directory");
+                        std::process::exit(1);
+                    }
+                };
+
+                if let Err(error) = export::parse_seeds(spec).and_then(|seeds| export::export_seeds(&seeds, dir)) {
+                    eprintln!("Failed to export seeds: {}", error);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            "--export-bundle" => {
+                let (seed, dir) = match (args.get(index + 1).and_then(|seed| seed.parse().ok()), args.get(index + 2)) {
+                    (Some(seed), Some(dir)) => (seed, dir),
+                    _ => {
+                        eprintln!("--export-bundle needs a seed and an output directory");
+                        std::process::exit(1);
+                    }
+                };
+
+                if let Err(error) = export::export_bundle(seed, dir) {
+                    eprintln!("Failed to export bundle: {}", error);
+                    std::process::exit(1);
+                }
+                println!("Wrote {}", dir);
+                return;
+            }
+            "--verify-bundle" => {
+                let dir = match args.get(index + 1) {
+                    Some(dir) => dir,
+                    None => {
+                        eprintln!("--verify-bundle needs a bundle directory");
+                        std::process::exit(1);
+                    }
+                };
+
+                match export::verify_manifest(dir) {
+                    Ok(problems) if problems.is_empty() => println!("Every file in {} matches its manifest", dir),
+                    Ok(problems) => {
+                        for problem in problems {
+                            eprintln!("{}", problem);
+                        }
+                        std::process::exit(1);
+                    }
+                    Err(error) => {
+                        eprintln!("Failed to read the manifest: {}", error);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
+            "--export-tiles" => {
+                let (seed, path) = match (args.get(index + 1).and_then(|seed| seed.parse().ok()), args.get(index + 2)) {
+                    (Some(seed), Some(path)) => (seed, path),
+                    _ => {
+                        eprintln!("--export-tiles needs a seed and an output path");
+                        std::process::exit(1);
+                    }
+                };
+
+                if let Err(error) = export::export_tiles(seed, path) {
+                    eprintln!("Failed to export tiles: {}", error);
+                    std::process::exit(1);
+                }
+                println!("Wrote {}", path);
+                return;
+            }
+            "--export-fields" => {
+                let (seed, path) = match (args.get(index + 1).and_then(|seed| seed.parse().ok()), args.get(index + 2)) {
+                    (Some(seed), Some(path)) => (seed, path),
+                    _ => {
+                        eprintln!("--export-fields needs a seed and an output path");
+                        std::process::exit(1);
+                    }
+                };
+
+                if let Err(error) = export::export_fields(seed, path) {
+                    eprintln!("Failed to export fields: {}", error);
+                    std::process::exit(1);
+                }
+                println!("Wrote {}", path);
+                return;
+            }
+            "--export-adjacency" => {
+                let (seed, path) = match (args.get(index + 1).and_then(|seed| seed.parse().ok()), args.get(index + 2)) {
+                    (Some(seed), Some(path)) => (seed, path),
+                    _ => {
+                        eprintln!("--export-adjacency needs a seed and an output path");
+                        std::process::exit(1);
+                    }
+                };
+
+                if let Err(error) = export::export_adjacency(seed, path) {
+                    eprintln!("Failed to export adjacency: {}", error);
+                    std::process::exit(1);
+                }
+                println!("Wrote {}", path);
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    let config = match presets::launch_config(&args) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("Failed to load the launch config: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let (initial_window, window_error) = window::initial_window(&config);
+
     App::new()
-        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
-        .add_plugin(TileMapPlugin)
+        .add_plugins(DefaultPlugins
+            .set(ImagePlugin::default_nearest())
+            .set(WindowPlugin {
+                window: initial_window,
+                ..default()
+            }))
+        .add_plugin(KeyBindingsPlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(TileMapPlugin { config })
+        .add_plugin(CoordLabelPlugin)
+        .add_plugin(FieldLabelPlugin)
+        .add_plugin(BrushPlugin)
+        .add_plugin(AnimationPlugin)
+        .add_plugin(BookmarkPlugin)
+        .add_plugin(PathPlugin)
+        .add_plugin(CoastlinePlugin)
+        .add_plugin(OutlinePlugin)
+        .add_plugin(HydrologyPlugin)
+        .add_plugin(ParallaxPlugin)
+        .add_plugin(InspectorPlugin)
+        .add_plugin(ExplorePlugin)
+        .add_plugin(WindPlugin)
+        .add_plugin(ParameterPlugin)
+        .add_plugin(WindowGeometryPlugin { load_error: window_error })
+        .add_plugin(PresetPlugin)
+        .add_plugin(StatsPlugin)
         .run();
 }
\ No newline at end of file
@@ -0,0 +1,43 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::tilemap::{tile_hash, NAME_SALT};
+
+const ONSETS: [&str; 18] = [
+    "b", "d", "g", "k", "l", "m", "n", "r", "s", "t", "v", "z", "th", "sh", "kr", "dr", "br", "st",
+];
+const VOWELS: [&str; 8] = ["a", "e", "i", "o", "u", "ae", "ia", "ou"];
+const CODAS: [&str; 8] = ["", "", "n", "r", "s", "l", "th", "nd"];
+
+// Builds a pronounceable name from syllables, seeded by the world seed and a grid position
+pub fn landmass_name(seed: u32, x: i32, y: i32) -> String {
+    let mut rng = StdRng::seed_from_u64(tile_hash(seed, x as i64, y as i64, NAME_SALT));
+
+    let mut name = String::new();
+    let syllables = rng.gen_range(2..4);
+
+    for _ in 0..syllables {
+        name.push_str(ONSETS[rng.gen_range(0..ONSETS.len())]);
+        name.push_str(VOWELS[rng.gen_range(0..VOWELS.len())]);
+    }
+    name.push_str(CODAS[rng.gen_range(0..CODAS.len())]);
+
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Seeds and positions whose bits overlapped when they were packed into one key get their own names
+    #[test]
+    fn overlapping_keys_get_different_names() {
+        assert_ne!(landmass_name(1, 0, 0), landmass_name(0, 65536, 0));
+        assert_ne!(landmass_name(0, 1, 0), landmass_name(0, 0, 65536));
+        assert_ne!(landmass_name(0, -1, 0), landmass_name(0, 0, -1));
+    }
+}
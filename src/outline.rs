@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+
+use crate::config::{DisplayConfig, MapConfig, FONT_PATH};
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::{Biome, MapGrid};
+
+// Line thickness as a share of a tile's width
+const OUTLINE_WIDTH: f32 = 0.12;
+
+// Above the tiles and lakes, just below the coastline
+const OUTLINE_Z: f32 = 0.68;
+
+const LEGEND_FONT_SIZE: f32 = 16.0;
+const LEGEND_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const LEGEND_HOVERED: Color = Color::rgba(0.3, 0.3, 0.3, 0.8);
+
+pub struct OutlinePlugin;
+
+#[derive(Resource)]
+struct ShowLegend(bool);
+
+#[derive(Component)]
+struct OutlineSegment;
+
+#[derive(Component)]
+struct Legend;
+
+// A legend row, clicking it toggles the biome's outline
+#[derive(Component)]
+struct LegendEntry(Biome);
+
+impl Plugin for OutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowLegend(false))
+            .add_system(toggle_legend)
+            .add_system(update_legend)
+            .add_system(select_outlined_biomes)
+            .add_system(update_outlines);
+    }
+}
+
+// Semicolon shows the legend of the biomes on the map
+fn toggle_legend(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut show: ResMut<ShowLegend>) {
+    if bindings.just_pressed(&keyboard, Action::ToggleLegend) {
+        show.0 = !show.0;
+    }
+}
+
+fn legend_label(biome: Biome, outlined: bool) -> String {
+    format!("[{}] {}", if outlined { "x" } else { " " }, biome.name())
+}
+
+// Rebuilds the legend, one row per biome on the map in Biome::ALL order, whenever the map, the toggle
+// or the selection changes
+fn update_legend(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    display: Res<DisplayConfig>,
+    show: Res<ShowLegend>,
+    grid: Option<Res<MapGrid>>,
+    legends: Query<Entity, With<Legend>>,
+) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
+
+    if !grid.is_changed() && !show.is_changed() && !display.is_changed() {
+        return;
+    }
+
+    for entity in legends.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if !show.0 {
+        return;
+    }
+
+    let font = asset_server.load(FONT_PATH);
+    let present: Vec<Biome> = Biome::ALL.into_iter()
+        .filter(|&biome| grid.samples.iter().any(|sample| sample.biome == biome))
+        .collect();
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(60.0),
+                    left: Val::Px(10.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            background_color: LEGEND_BACKGROUND.into(),
+            ..default()
+        },
+        Legend,
+    )).with_children(|legend| {
+        for biome in present {
+            let outlined = display.outlined_biomes.contains(&biome);
+            legend.spawn((
+                ButtonBundle {
+                    background_color: Color::NONE.into(),
+                    ..default()
+                },
+                LegendEntry(biome),
+            )).with_children(|entry| {
+                entry.spawn(TextBundle::from_section(legend_label(biome, outlined), TextStyle {
+                    font: font.clone(),
+                    font_size: LEGEND_FONT_SIZE,
+                    color: if outlined { display.outline_color } else { Color::WHITE },
+                }));
+            });
+        }
+    });
+}
+
+fn select_outlined_biomes(
+    mut display: ResMut<DisplayConfig>,
+    mut entries: Query<(&Interaction, &LegendEntry, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, entry, mut background) in entries.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                match display.outlined_biomes.iter().position(|&biome| biome == entry.0) {
+                    Some(position) => {
+                        display.outlined_biomes.remove(position);
+                    }
+                    None => display.outlined_biomes.push(entry.0),
+                }
+            }
+            Interaction::Hovered => *background = LEGEND_HOVERED.into(),
+            Interaction::None => *background = Color::NONE.into(),
+        }
+    }
+}
+
+// Redraws the outlines around the selected biomes whenever the map or the selection changes
+fn update_outlines(
+    mut commands: Commands,
+    config: Res<MapConfig>,
+    display: Res<DisplayConfig>,
+    grid: Option<Res<MapGrid>>,
+    segments: Query<Entity, With<OutlineSegment>>,
+) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
+
+    if !grid.is_changed() && !display.is_changed() {
+        return;
+    }
+
+    for entity in segments.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let length = (config.world_units_per_tile() * grid.stride as f64) as f32;
+    let thickness = length * OUTLINE_WIDTH;
+
+    for (from, to) in grid.biome_outline_edges(&display.outlined_biomes) {
+        let (from_x, from_y) = config.tile_to_world(from.0, from.1);
+        let (to_x, to_y) = config.tile_to_world(to.0, to.1);
+
+        let size = if from.1 == to.1 {
+            Vec2::new(thickness, length)
+        } else {
+            Vec2::new(length, thickness)
+        };
+
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: display.outline_color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_xyz(((from_x + to_x) / 2.0) as f32, ((from_y + to_y) / 2.0) as f32, OUTLINE_Z),
+                ..default()
+            },
+            OutlineSegment,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::TileSample;
+
+    // Biome rows of a synthetic grid, top row first, with wetlands scattered between unselected biomes
+    const GRID: [&str; 5] = [
+        "OOOSS",
+        "OMSSF",
+        "MMSWF",
+        "SSWWF",
+        "SFFFF",
+    ];
+    const WETLANDS: [Biome; 2] = [Biome::Marsh, Biome::Mangrove];
+
+    fn grid_biome(letter: char) -> Biome {
+        match letter {
+            'O' => Biome::Ocean,
+            'M' => Biome::Mangrove,
+            'W' => Biome::Marsh,
+            'S' => Biome::TemperateSteppe,
+            _ => Biome::BorealMoistForest,
+        }
+    }
+
+    // Outlines run exactly along the sides where a selected biome meets another biome
+    #[test]
+    fn outlines_trace_the_selected_biomes() {
+        let samples = GRID.iter().rev()
+            .flat_map(|row| row.chars())
+            .map(|letter| TileSample { height: 0.0, temperature: 0.0, percipitation: 0.0, falloff: 0.0, biome: grid_biome(letter) })
+            .collect();
+        let grid = MapGrid { half_size: GRID.len() as i32 / 2, stride: 1, tiles: Vec::new(), samples };
+        let biome = |(x, y): (i32, i32)| grid.samples[grid.index(x, y).unwrap()].biome;
+        let edges = grid.biome_outline_edges(&WETLANDS);
+
+        for &(from, to) in &edges {
+            assert!(WETLANDS.contains(&biome(from)) || WETLANDS.contains(&biome(to)), "outlined {:?} {:?}, neither selected", from, to);
+            assert_ne!(biome(from), biome(to), "outlined {:?} {:?} inside one region", from, to);
+        }
+
+        for index in 0..grid.samples.len() {
+            let from = grid.coords(index);
+            for to in [(from.0 + 1, from.1), (from.0, from.1 + 1)] {
+                if grid.index(to.0, to.1).is_some() && biome(from) != biome(to)
+                    && (WETLANDS.contains(&biome(from)) || WETLANDS.contains(&biome(to))) {
+                    assert!(edges.contains(&(from, to)), "{:?} {:?} is not outlined", from, to);
+                }
+            }
+        }
+
+        assert!(grid.biome_outline_edges(&[]).is_empty());
+    }
+}
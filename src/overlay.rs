@@ -0,0 +1,374 @@
+use bevy::prelude::*;
+
+use crate::config::{ContinuousColoring, Haze, OceanColoring, Shadows};
+
+// Semi-transparent tints for each climate zone, from polar to tropical
+pub const CLIMATE_ZONE_COLORS: [Color; 6] = [
+    Color::rgba(1.0, 1.0, 1.0, 0.5),
+    Color::rgba(0.6, 0.8, 1.0, 0.4),
+    Color::rgba(0.2, 0.5, 0.3, 0.4),
+    Color::rgba(0.4, 0.9, 0.3, 0.4),
+    Color::rgba(1.0, 0.8, 0.2, 0.4),
+    Color::rgba(1.0, 0.3, 0.1, 0.4),
+];
+
+// Moonlit tint the map fades to at midnight
+const NIGHT_TINT: Color = Color::rgb(0.25, 0.3, 0.6);
+
+// Flat color of the tiles hidden by biome isolation
+pub const ISOLATION_GRAY: Color = Color::rgb(0.45, 0.45, 0.45);
+
+// Heights [m] mapped to the brightest land and darkest water shades of the height view
+const HEIGHT_VIEW_PEAK: f64 = 8000.0;
+const HEIGHT_VIEW_DEPTH: f64 = 1000.0;
+
+// Movement cost shown reddest in the movement cost view
+const COST_VIEW_MAX: f32 = 8.0;
+
+// Full ranges the generator produces: height [m], temperature [C] and percipitation [mm]
+pub const HEIGHT_RANGE: (f64, f64) = (-1000.0, 8000.0);
+pub const TEMPERATURE_RANGE: (f64, f64) = (-33.0, 55.0);
+pub const PERCIPITATION_RANGE: (f64, f64) = (0.0, 16000.0);
+
+// Radial falloff factor from the map corners to the center
+const FALLOFF_RANGE: (f64, f64) = (-1.0, 0.7);
+
+// Lays the overlay over the base color by the overlay's alpha, keeping the base alpha
+pub fn blend(base: Color, overlay: Color) -> Color {
+    let alpha = overlay.a();
+
+    Color::rgba(
+        base.r() + (overlay.r() - base.r()) * alpha,
+        base.g() + (overlay.g() - base.g()) * alpha,
+        base.b() + (overlay.b() - base.b()) * alpha,
+        base.a(),
+    )
+}
+
+const BAYER_4X4: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+// Ordered dither offsetting each channel by up to amplitude 8-bit steps, averaging out over 4x4 tiles
+pub fn dither(color: Color, coords: (i32, i32), amplitude: f32) -> Color {
+    if amplitude <= 0.0 {
+        return color;
+    }
+
+    let threshold = BAYER_4X4[coords.1.rem_euclid(4) as usize][coords.0.rem_euclid(4) as usize];
+    let offset = ((threshold + 0.5) / 16.0 - 0.5) * 2.0 * amplitude / 255.0;
+
+    Color::rgba(
+        (color.r() + offset).clamp(0.0, 1.0),
+        (color.g() + offset).clamp(0.0, 1.0),
+        (color.b() + offset).clamp(0.0, 1.0),
+        color.a(),
+    )
+}
+
+// Light over one day of period seconds, white at noon (time 0) and darkest blue at midnight
+pub fn day_tint(time: f32, period: f32) -> Color {
+    let phase = (time / period).rem_euclid(1.0);
+    let light = 0.5 + 0.5 * (std::f32::consts::TAU * phase).cos();
+
+    Color::rgb(
+        NIGHT_TINT.r() + (1.0 - NIGHT_TINT.r()) * light,
+        NIGHT_TINT.g() + (1.0 - NIGHT_TINT.g()) * light,
+        NIGHT_TINT.b() + (1.0 - NIGHT_TINT.b()) * light,
+    )
+}
+
+pub fn modulate(color: Color, tint: Color) -> Color {
+    Color::rgba(color.r() * tint.r(), color.g() * tint.g(), color.b() * tint.b(), color.a())
+}
+
+// Grayscale land and blue water shaded by height, with height differences scaled by exaggeration
+pub fn height_color(height: f64, exaggeration: f32) -> Color {
+    if height <= 0.0 {
+        let depth = (-height / HEIGHT_VIEW_DEPTH * exaggeration as f64).clamp(0.0, 1.0) as f32;
+        return Color::rgb(0.1, 0.2 - 0.15 * depth, 0.6 - 0.4 * depth);
+    }
+
+    let relief = (height / HEIGHT_VIEW_PEAK * exaggeration as f64).clamp(0.0, 1.0) as f32;
+    let shade = 0.15 + 0.85 * relief;
+
+    Color::rgb(shade, shade, shade)
+}
+
+// The color faded toward the haze color by how far height lies between the haze's start and the highest
+// terrain the generator makes, nothing below the start
+pub fn haze(color: Color, height: f64, haze: &Haze) -> Color {
+    let thickness = normalize(height, (haze.start_height, HEIGHT_RANGE.1.max(haze.start_height + 1.0)));
+    let mut overlay = haze.color;
+    overlay.set_a(thickness * haze.strength.clamp(0.0, 1.0));
+
+    blend(color, overlay)
+}
+
+// Which tiles of a row-major height grid, rows running south to north, lie in the shadow of taller
+// terrain. A ray marches from each tile toward the sun one cell at a time, climbing by the sun's
+// elevation over cell_length [m] a cell, until terrain blocks it or it leaves the grid or clears the
+// highest peak. Water counts at its surface, so the sea floor casts no shadow
+pub fn cast_shadows(heights: &[f64], width: usize, cell_length: f64, sun: &Shadows) -> Vec<bool> {
+    let width = width.max(1);
+    let rows = (heights.len() / width) as f64;
+    let surface = |index: usize| heights[index].max(0.0);
+    let highest = (0..heights.len()).map(surface).fold(0.0, f64::max);
+
+    let (step_x, step_y) = (sun.azimuth.to_radians().sin(), sun.azimuth.to_radians().cos());
+    let rise = sun.elevation.to_radians().tan() * cell_length;
+
+    (0..heights.len()).map(|index| {
+        let (x, y) = ((index % width) as f64, (index / width) as f64);
+        let start = surface(index);
+
+        (1..).map(|step| step as f64)
+            .map(|distance| (x + step_x * distance, y + step_y * distance, start + rise * distance))
+            .take_while(|&(x, y, ray)| ray < highest && x.round() >= 0.0 && y.round() >= 0.0
+                && x.round() < width as f64 && y.round() < rows)
+            .any(|(x, y, ray)| surface(y.round() as usize * width + x.round() as usize) > ray)
+    }).collect()
+}
+
+// Where value lies in range, clamped to 0..1
+fn normalize(value: f64, range: (f64, f64)) -> f32 {
+    ((value - range.0) / (range.1 - range.0)).clamp(0.0, 1.0) as f32
+}
+
+// Temperature in red, height in green and percipitation in blue, so all three fields read at once
+pub fn combined_color(height: f64, temperature: f64, percipitation: f64) -> Color {
+    Color::rgb(
+        normalize(temperature, TEMPERATURE_RANGE),
+        normalize(height, HEIGHT_RANGE),
+        normalize(percipitation, PERCIPITATION_RANGE),
+    )
+}
+
+// Land color of a climate under the continuous coloring mode. Percipitation is spread by its square
+// root, as most land sits at the dry end of the range
+pub fn continuous_color(coloring: &ContinuousColoring, height: f64, temperature: f64, percipitation: f64) -> Color {
+    let warmth = normalize(temperature, TEMPERATURE_RANGE);
+    let wetness = normalize(percipitation.max(0.0).sqrt(), (PERCIPITATION_RANGE.0.sqrt(), PERCIPITATION_RANGE.1.sqrt()));
+
+    let mix = |a: Color, b: Color, share: f32| Color::rgb(
+        a.r() + (b.r() - a.r()) * share,
+        a.g() + (b.g() - a.g()) * share,
+        a.b() + (b.b() - a.b()) * share,
+    );
+    let cold = mix(coloring.cold_dry, coloring.cold_wet, wetness);
+    let hot = mix(coloring.hot_dry, coloring.hot_wet, wetness);
+    let climate = mix(cold, hot, warmth);
+
+    let peak = normalize(height, (coloring.peak_start_height, HEIGHT_RANGE.1.max(coloring.peak_start_height + 1.0)));
+    mix(climate, coloring.peak_color, peak)
+}
+
+// Ocean color of a sea surface temperature
+pub fn ocean_color(coloring: &OceanColoring, temperature: f64) -> Color {
+    let warmth = normalize(temperature, (coloring.cold_temperature, coloring.warm_temperature));
+    let (cold, warm) = (coloring.cold_color, coloring.warm_color);
+
+    Color::rgb(
+        cold.r() + (warm.r() - cold.r()) * warmth,
+        cold.g() + (warm.g() - cold.g()) * warmth,
+        cold.b() + (warm.b() - cold.b()) * warmth,
+    )
+}
+
+// Green for the cheapest tiles through red at COST_VIEW_MAX, black for impassable ones
+pub fn cost_color(cost: f32) -> Color {
+    if !cost.is_finite() {
+        return Color::BLACK;
+    }
+
+    let expense = ((cost - 1.0) / (COST_VIEW_MAX - 1.0)).clamp(0.0, 1.0);
+
+    Color::rgb(expense, 1.0 - expense, 0.0)
+}
+
+// Gray from black where the falloff drowns the land to white where it leaves the most height
+pub fn falloff_color(falloff: f64) -> Color {
+    let shade = normalize(falloff, FALLOFF_RANGE);
+
+    Color::rgb(shade, shade, shade)
+}
+
+// Saturation scales the HSL saturation, contrast spreads the channels away from middle gray and
+// brightness scales them, all leaving the color unchanged at 1
+pub fn adjust(color: Color, brightness: f32, contrast: f32, saturation: f32) -> Color {
+    if (brightness, contrast, saturation) == (1.0, 1.0, 1.0) {
+        return color;
+    }
+
+    let [hue, hsl_saturation, lightness, alpha] = color.as_hsla_f32();
+    let color = Color::hsla(hue, (hsl_saturation * saturation).clamp(0.0, 1.0), lightness, alpha);
+    let channel = |value: f32| (((value - 0.5) * contrast + 0.5) * brightness).clamp(0.0, 1.0);
+
+    Color::rgba(channel(color.r()), channel(color.g()), channel(color.b()), alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MapConfig;
+    use crate::test_support::*;
+    use crate::tilemap::Biome;
+
+    // Smallest summed channel difference that counts as telling two colors apart
+    const COLOR_DIFFERENCE: f32 = 0.2;
+
+    fn difference(a: Color, b: Color) -> f32 {
+        (a.r() - b.r()).abs() + (a.g() - b.g()).abs() + (a.b() - b.b()).abs()
+    }
+
+    // Each channel moves by at most the amplitude in 8-bit steps, and over any 4x4 block of tiles the
+    // offsets cancel, so the dithered colors average to the original
+    #[test]
+    fn dither_stays_within_amplitude_and_averages_out() {
+        let color = Color::rgba(0.3, 0.55, 0.8, 0.7);
+
+        for amplitude in [0.0, 1.0, 4.0] {
+            for (left, bottom) in [(0, 0), (-7, 13)] {
+                let mut sum = [0.0; 3];
+                for y in bottom..bottom + 4 {
+                    for x in left..left + 4 {
+                        let dithered = dither(color, (x, y), amplitude);
+                        let channels = [(dithered.r(), color.r()), (dithered.g(), color.g()), (dithered.b(), color.b())];
+                        for (channel, (value, original)) in channels.into_iter().enumerate() {
+                            assert!((value - original).abs() <= amplitude / 255.0 + 1e-6, "amplitude {} at ({}, {})", amplitude, x, y);
+                            sum[channel] += value - original;
+                        }
+                        assert_eq!(dithered.a(), color.a());
+                    }
+                }
+                assert!(sum.iter().all(|offset| offset.abs() < 1e-5), "amplitude {} averaged {:?} off", amplitude, sum);
+            }
+        }
+    }
+
+    // Temperature, height and percipitation each set their own channel to where they lie in the generator's
+    // range, clamped past either end
+    #[test]
+    fn combined_channels_are_the_normalized_fields() {
+        let channels = |height, temperature, percipitation| {
+            let color = combined_color(height, temperature, percipitation);
+            [color.r(), color.g(), color.b()]
+        };
+        let assert_channels = |actual: [f32; 3], expected: [f32; 3]| {
+            assert!(actual.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-6), "{:?} is not {:?}", actual, expected);
+        };
+
+        assert_channels(channels(3500.0, 11.0, 4000.0), [0.5, 0.5, 0.25]);
+        assert_channels(channels(3500.0, 55.0, 4000.0), [1.0, 0.5, 0.25]);
+        assert_channels(channels(-1000.0, 11.0, 4000.0), [0.5, 0.0, 0.25]);
+        assert_channels(channels(3500.0, 11.0, 12000.0), [0.5, 0.5, 0.75]);
+        assert_channels(channels(9000.0, -40.0, 20000.0), [0.0, 1.0, 1.0]);
+    }
+
+    // Adjustments of 1 leave a color as it was, and a saturation of 0 turns it to the gray of its own
+    // lightness, alpha kept either way
+    #[test]
+    fn identity_adjustments_keep_colors_and_zero_saturation_grays() {
+        for color in [Color::rgba(0.3, 0.55, 0.8, 0.7), Color::rgb(0.9, 0.2, 0.1), Color::rgb(0.4, 0.4, 0.4)] {
+            assert_eq!(adjust(color, 1.0, 1.0, 1.0), color);
+
+            let gray = adjust(color, 1.0, 1.0, 0.0);
+            let lightness = color.as_hsla_f32()[2];
+            for channel in [gray.r(), gray.g(), gray.b()] {
+                assert!((channel - lightness).abs() < 1e-5, "{:?} turned {:?}", color, gray);
+            }
+            assert_eq!(gray.a(), color.a());
+        }
+    }
+
+    // A peak fades further toward the haze color than a hill, and ground below the haze's start not at all
+    #[test]
+    fn high_tiles_are_hazier() {
+        let settings = Haze { color: Color::rgb(0.7, 0.8, 1.0), start_height: 1000.0, strength: 0.8 };
+        let ground = Color::rgb(0.2, 0.5, 0.1);
+        let haziness = |height| difference(haze(ground, height, &settings), settings.color);
+
+        assert_eq!(haze(ground, 500.0, &settings), ground);
+        assert!(haziness(6000.0) < haziness(2000.0), "the peak is no hazier than the hill");
+        assert!(haziness(2000.0) < difference(ground, settings.color));
+    }
+
+    // Lowland a few hundred meters apart, and shallow sea, shades further apart at a triple exaggeration
+    // than at none
+    #[test]
+    fn exaggeration_raises_height_view_contrast() {
+        for (low, high) in [(200.0, 800.0), (-50.0, -150.0)] {
+            let contrast = |exaggeration| difference(height_color(low, exaggeration), height_color(high, exaggeration));
+            assert!(contrast(3.0) > contrast(1.0), "{} and {} m", low, high);
+        }
+    }
+
+    // Over a lattice of the generator's climate ranges, one step in temperature [C], percipitation [mm]
+    // or height [m] small enough to count as neighbouring tiles changes no channel by more than 0.05
+    #[test]
+    fn continuous_coloring_has_no_jumps() {
+        let coloring = MapConfig::default().continuous_coloring;
+        let color = |height: f64, temperature: f64, percipitation: f64| {
+            continuous_color(&coloring, height, temperature, percipitation).as_rgba_f32()
+        };
+        let lattice = |range: (f64, f64)| (0..=40).map(move |step| range.0 + (range.1 - range.0) * step as f64 / 40.0);
+
+        for height in lattice((0.0, HEIGHT_RANGE.1)) {
+            for temperature in lattice(TEMPERATURE_RANGE) {
+                for percipitation in lattice(PERCIPITATION_RANGE) {
+                    let here = color(height, temperature, percipitation);
+                    for neighbour in [
+                        color(height, temperature + 0.5, percipitation),
+                        color(height, temperature, percipitation + 50.0),
+                        color(height + 20.0, temperature, percipitation),
+                    ] {
+                        assert!(here.iter().zip(neighbour).all(|(a, b)| (a - b).abs() <= 0.05),
+                            "the coloring jumps at {} m, {} C, {} mm", height, temperature, percipitation);
+                    }
+                }
+            }
+        }
+    }
+
+    // Tropical and arctic but unfrozen seas come out clearly different colors, both from the coloring
+    // alone and on a generated map, where the ocean has no depth shading to tell them apart
+    #[test]
+    fn warm_and_cold_oceans_look_different() {
+        let config = reference_config();
+        let (tropical, arctic) = (ocean_color(&config.ocean_coloring, 28.0), ocean_color(&config.ocean_coloring, 3.0));
+        assert!(difference(tropical, arctic) >= COLOR_DIFFERENCE, "{:?} and {:?} look alike", tropical, arctic);
+
+        let grid = reference_map_gen(&config).generate_grid();
+        let ocean: Vec<(f64, Color)> = grid.samples.iter().zip(&grid.tiles)
+            .filter(|(sample, _)| sample.biome == Biome::Ocean && sample.temperature > 0.0)
+            .map(|(sample, tile)| (sample.temperature, tile.color))
+            .collect();
+        let (warm, warm_color) = *ocean.iter().max_by(|a, b| a.0.total_cmp(&b.0)).expect("the reference map has no unfrozen ocean");
+        let (cold, cold_color) = *ocean.iter().min_by(|a, b| a.0.total_cmp(&b.0)).unwrap();
+
+        assert!(warm - cold >= 10.0, "the reference ocean only spans {} to {} C", cold, warm);
+        assert!(difference(warm_color, cold_color) >= COLOR_DIFFERENCE, "{:?} and {:?} look alike", warm_color, cold_color);
+    }
+
+    // A 9 x 9 plain [m] with a tower in the middle, lit from the east at 30 degrees over 100 m tiles.
+    // The sun's rays climb 58 m a tile, so the tower shadows the whole row west of it and no more
+    #[test]
+    fn tower_shadows_the_row_behind_it() {
+        let width = 9;
+        let mut heights = vec![100.0; width * width];
+        let tower = (width / 2) * width + width / 2;
+        heights[tower] = 1000.0;
+        let sun = Shadows { azimuth: 90.0, elevation: 30.0, tile_length: 100.0, darkness: 0.5 };
+
+        for (index, shadowed) in cast_shadows(&heights, width, sun.tile_length, &sun).into_iter().enumerate() {
+            let behind = index / width == tower / width && index < tower;
+            assert_eq!(shadowed, behind, "tile ({}, {})", index % width, index / width);
+        }
+
+        let overhead = Shadows { elevation: 89.0, ..sun };
+        assert!(!cast_shadows(&heights, width, overhead.tile_length, &overhead).contains(&true));
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::config::{DisplayConfig, MapConfig};
+use crate::tilemap::{XShift, YShift, Zoom};
+
+// Behind the tiles at 0, each further layer another step back
+const PARALLAX_Z: f32 = -10.0;
+const PARALLAX_LAYER_STEP: f32 = 1.0;
+
+// Half the side of the square the layers cover, in map half widths, so they fill the view zoomed out
+const PARALLAX_COVER: f64 = 3.0;
+
+// Most sprites along each axis of a layer. A repeat too small to cover the area with them is stretched
+const MAX_PARALLAX_SPRITES: usize = 48;
+
+// Side [px] of the generated starfield texture and the share of its pixels lit
+const STARFIELD_SIZE: u32 = 256;
+const STAR_DENSITY: f64 = 0.004;
+const STARFIELD_SEED: u64 = 7;
+
+pub struct ParallaxPlugin;
+
+#[derive(Component)]
+struct ParallaxSprite;
+
+impl Plugin for ParallaxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_parallax);
+    }
+}
+
+// World units the terrain has moved under the view, on one axis, after panning to shift. Solves the
+// recentering in tilemap::recentered_shift for the world distance
+pub fn panned_distance(shift: f64, map_axis_len: f64, zoom: f64) -> f64 {
+    shift * map_axis_len * zoom * zoom
+}
+
+// Where a layer's repeating pattern starts within one repeat, on one axis. The layer moves opposite the
+// pan like the terrain, by speed of the distance
+pub fn layer_offset(speed: f32, repeat: f32, panned: f64) -> f32 {
+    (-(speed as f64) * panned).rem_euclid(repeat as f64) as f32
+}
+
+// White points on a clear background, tinted per layer
+fn starfield() -> Image {
+    let mut rng = StdRng::seed_from_u64(STARFIELD_SEED);
+    let mut data = vec![0; (STARFIELD_SIZE * STARFIELD_SIZE * 4) as usize];
+
+    for pixel in data.chunks_mut(4) {
+        if rng.gen_bool(STAR_DENSITY) {
+            let brightness = rng.gen_range(120..=255);
+            pixel.copy_from_slice(&[brightness, brightness, brightness, 255]);
+        }
+    }
+
+    Image::new(
+        Extent3d { width: STARFIELD_SIZE, height: STARFIELD_SIZE, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+// Respawns the layers whenever the view or the layer settings change. The camera never moves for them,
+// so cursor picking is unaffected, and only the sprites shift with the pan
+#[allow(clippy::too_many_arguments)]
+fn update_parallax(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut starfield_texture: Local<Option<Handle<Image>>>,
+    config: Res<MapConfig>,
+    display: Res<DisplayConfig>,
+    zoom: Res<Zoom>,
+    x_shift: Res<XShift>,
+    y_shift: Res<YShift>,
+    sprites: Query<Entity, With<ParallaxSprite>>,
+) {
+    if !display.is_changed() && !config.is_changed() && !zoom.is_changed() && !x_shift.is_changed() && !y_shift.is_changed() {
+        return;
+    }
+
+    for entity in sprites.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let cover = (config.map_axis_len() * PARALLAX_COVER) as f32;
+    let panned_x = panned_distance(x_shift.0, config.map_axis_len(), zoom.0);
+    let panned_y = panned_distance(y_shift.0, config.map_axis_len(), zoom.0);
+
+    for (depth, layer) in display.parallax.iter().enumerate() {
+        let texture = match &layer.texture {
+            Some(path) => asset_server.load(path.as_str()),
+            None => starfield_texture.get_or_insert_with(|| images.add(starfield())).clone(),
+        };
+
+        let count = ((2.0 * cover / layer.repeat.max(1.0)).ceil() as usize + 1).min(MAX_PARALLAX_SPRITES);
+        let size = layer.repeat.max(2.0 * cover / (count - 1).max(1) as f32);
+        let start = (-cover - size + layer_offset(layer.speed, size, panned_x), -cover - size + layer_offset(layer.speed, size, panned_y));
+        let z = PARALLAX_Z - depth as f32 * PARALLAX_LAYER_STEP;
+
+        for row in 0..=count {
+            for column in 0..=count {
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: layer.tint,
+                            custom_size: Some(Vec2::splat(size)),
+                            ..default()
+                        },
+                        texture: texture.clone(),
+                        transform: Transform::from_xyz(start.0 + column as f32 * size, start.1 + row as f32 * size, z),
+                        ..default()
+                    },
+                    ParallaxSprite,
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPEAT: f32 = 512.0;
+
+    // Small enough a pan step not to wrap the repeat
+    const PAN_STEP: f64 = 0.01;
+
+    // Offsets stay within one repeat at any shift, and a pan moves each layer, a fixed one first, by its
+    // speed's share of the distance the terrain moved, opposite the pan
+    #[test]
+    fn layers_follow_the_pan_at_their_speed() {
+        let map_axis_len = MapConfig::default().map_axis_len();
+
+        for speed in [0.0, 0.1, 0.5] {
+            for step in -90..=90 {
+                let shift = step as f64 * PAN_STEP;
+                let offset = layer_offset(speed, REPEAT, panned_distance(shift, map_axis_len, 1.0));
+                assert!((0.0..REPEAT).contains(&offset), "speed {} at shift {} put the layer at {}", speed, shift, offset);
+
+                let next = layer_offset(speed, REPEAT, panned_distance(shift + PAN_STEP, map_axis_len, 1.0));
+                let moved = (next - offset + REPEAT / 2.0).rem_euclid(REPEAT) - REPEAT / 2.0;
+                let expected = -speed * panned_distance(PAN_STEP, map_axis_len, 1.0) as f32;
+                assert!((moved - expected).abs() <= 1e-2, "speed {} moved the layer {} from shift {}, not {}", speed, moved, shift, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+
+use crate::config::{DisplayConfig, MapConfig, ParameterField, FONT_PATH};
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::{NoiseOffset, Seed, XShift, YShift, Zoom};
+
+pub struct ParameterPlugin;
+
+#[derive(Resource)]
+struct ShowParameters(bool);
+
+#[derive(Component)]
+struct ParameterText;
+
+impl Plugin for ParameterPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowParameters(false))
+            .add_startup_system(spawn_parameter_text)
+            .add_system(update_parameter_text);
+    }
+}
+
+fn spawn_parameter_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font: asset_server.load(FONT_PATH),
+            font_size: 16.0,
+            color: Color::WHITE,
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(60.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        ParameterText,
+    ));
+}
+
+fn field_line(field: ParameterField, seed: &Seed, zoom: f64, shift: (f64, f64), noise_offset: &NoiseOffset, config: &MapConfig, display: &DisplayConfig) -> String {
+    match field {
+        ParameterField::Seed => format!("Seed {}", display.seed_format.format(seed)),
+        ParameterField::Zoom => format!("Zoom {:.3}", zoom),
+        ParameterField::Shift => format!("Shift {:.3}, {:.3}", shift.0, shift.1),
+        ParameterField::NoiseOffset => format!("Noise offset {:.2}, {:.2}", noise_offset.0, noise_offset.1),
+        ParameterField::MapSize => format!("Map size {}", config.map_size),
+        ParameterField::WorldAge => format!("World age {:.2}", config.world_age),
+        ParameterField::BiomeModel => format!("Biome model {:?}", config.biome_model),
+        ParameterField::Quality => format!("Quality {:?}", config.quality),
+        ParameterField::Wind => if config.wind.strength == 0.0 {
+            "Wind calm".to_string()
+        } else {
+            format!("Wind from {:.0} at {:.1}", config.wind.direction, config.wind.strength)
+        },
+    }
+}
+
+// End toggles the overlay, a line per configured field so screenshots record what made the map.
+// Rewritten whenever the toggle, the view or a config changes
+#[allow(clippy::too_many_arguments)]
+fn update_parameter_text(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut show: ResMut<ShowParameters>,
+    seed: Res<Seed>,
+    zoom: Res<Zoom>,
+    x_shift: Res<XShift>,
+    y_shift: Res<YShift>,
+    noise_offset: Res<NoiseOffset>,
+    config: Res<MapConfig>,
+    display: Res<DisplayConfig>,
+    mut text: Query<&mut Text, With<ParameterText>>,
+) {
+    if bindings.just_pressed(&keyboard, Action::ToggleParameters) {
+        show.0 = !show.0;
+    }
+
+    let changed = show.is_changed() || seed.is_changed() || zoom.is_changed() || x_shift.is_changed()
+        || y_shift.is_changed() || noise_offset.is_changed() || config.is_changed() || display.is_changed();
+    if !changed {
+        return;
+    }
+
+    let value = if show.0 {
+        display.parameter_fields.iter()
+            .map(|field| field_line(*field, &seed, zoom.0, (x_shift.0, y_shift.0), &noise_offset, &config, &display))
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        String::new()
+    };
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
@@ -0,0 +1,280 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::camera::cursor_world_position;
+use crate::config::MapConfig;
+use crate::tilemap::{MapGen, MapGrid};
+
+const PATH_COLOR: Color = Color::rgb(1.0, 0.1, 0.8);
+
+// Markers sit above the tiles and below the landmass labels
+const PATH_MARKER_Z: f32 = 0.8;
+
+pub struct PathPlugin;
+
+// First end of the path being picked, waiting for the second click
+#[derive(Resource, Default)]
+struct PathStart(Option<(i32, i32)>);
+
+#[derive(Component)]
+struct PathMarker;
+
+impl Plugin for PathPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PathStart::default())
+            .add_system(pick_path)
+            .add_system(clear_path_on_regeneration);
+    }
+}
+
+// Open A* node, ordered so the BinaryHeap pops the lowest estimate first
+struct OpenTile {
+    estimate: f32,
+    index: usize,
+}
+
+impl PartialEq for OpenTile {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for OpenTile {}
+
+impl PartialOrd for OpenTile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenTile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+// A* over the grid's 4-connected tiles, where entering a tile costs its movement cost for every tile
+// crossed. None if either end is off the grid or impassable, or no passable route connects them
+pub fn find_path(grid: &MapGrid, costs: &[f32], start: (i32, i32), goal: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+    let start_index = grid.index(start.0, start.1)?;
+    let goal_index = grid.index(goal.0, goal.1)?;
+    if !costs[start_index].is_finite() || !costs[goal_index].is_finite() {
+        return None;
+    }
+
+    // Manhattan distance at the cheapest cost anywhere never overestimates, keeping the path optimal
+    let cheapest = costs.iter().copied().filter(|cost| cost.is_finite()).fold(f32::INFINITY, f32::min);
+    let heuristic = |(x, y): (i32, i32)| ((x - goal.0).abs() + (y - goal.1).abs()) as f32 * cheapest;
+
+    let mut spent = vec![f32::INFINITY; costs.len()];
+    let mut came_from = vec![usize::MAX; costs.len()];
+    let mut open = BinaryHeap::new();
+
+    spent[start_index] = 0.0;
+    open.push(OpenTile { estimate: heuristic(start), index: start_index });
+
+    while let Some(OpenTile { estimate, index }) = open.pop() {
+        let coords = grid.coords(index);
+
+        if index == goal_index {
+            let mut path = vec![coords];
+            let mut current = index;
+            while current != start_index {
+                current = came_from[current];
+                path.push(grid.coords(current));
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        // Skip entries left behind when a cheaper route to the tile was found
+        if estimate > spent[index] + heuristic(coords) {
+            continue;
+        }
+
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let next = (coords.0 + dx * grid.stride, coords.1 + dy * grid.stride);
+            let next_index = match grid.index(next.0, next.1) {
+                Some(next_index) if costs[next_index].is_finite() => next_index,
+                _ => continue,
+            };
+
+            let through = spent[index] + costs[next_index] * grid.stride as f32;
+            if through < spent[next_index] {
+                spent[next_index] = through;
+                came_from[next_index] = index;
+                open.push(OpenTile { estimate: through + heuristic(next), index: next_index });
+            }
+        }
+    }
+
+    None
+}
+
+fn spawn_marker(commands: &mut Commands, grid: &MapGrid, config: &MapConfig, coords: (i32, i32)) {
+    let size = (config.world_units_per_tile() * grid.stride as f64 / 2.0) as f32;
+    let (x, y) = config.tile_to_world(coords.0, coords.1);
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: PATH_COLOR,
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+            transform: Transform::from_xyz(x as f32, y as f32, PATH_MARKER_Z),
+            ..default()
+        },
+        PathMarker,
+    ));
+}
+
+// Right clicks pick the start and then the goal, drawing the cheapest path between them
+#[allow(clippy::too_many_arguments)]
+fn pick_path(
+    mut commands: Commands,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    config: Res<MapConfig>,
+    mapgen: Option<Res<MapGen>>,
+    grid: Option<Res<MapGrid>>,
+    mut start: ResMut<PathStart>,
+    markers: Query<Entity, With<PathMarker>>,
+) {
+    if !mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+
+    let cursor = match (windows.get_primary(), camera.get_single()) {
+        (Some(window), Ok((transform, projection))) => cursor_world_position(window, transform, projection),
+        _ => None,
+    };
+
+    let (cursor, mapgen, grid) = match (cursor, mapgen, grid) {
+        (Some(cursor), Some(mapgen), Some(grid)) => (cursor, mapgen, grid),
+        _ => return,
+    };
+
+    let (x, y) = config.world_to_tile(cursor.x as f64, cursor.y as f64);
+    let clicked = grid.snap(x, y);
+
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let from = match start.0.take() {
+        Some(from) => from,
+        None => {
+            start.0 = Some(clicked);
+            spawn_marker(&mut commands, &grid, &config, clicked);
+            return;
+        }
+    };
+
+    match mapgen.find_path(&grid, from, clicked) {
+        Some(path) => {
+            for coords in path {
+                spawn_marker(&mut commands, &grid, &config, coords);
+            }
+        }
+        None => info!("No path from {:?} to {:?}", from, clicked),
+    }
+}
+
+// A new map invalidates the drawn path and any half picked one
+fn clear_path_on_regeneration(
+    mut commands: Commands,
+    grid: Option<Res<MapGrid>>,
+    mut start: ResMut<PathStart>,
+    markers: Query<Entity, With<PathMarker>>,
+) {
+    if !grid.is_some_and(|grid| grid.is_changed()) {
+        return;
+    }
+
+    start.0 = None;
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 5x5 grid of unit stride whose movement costs are given as rows, top row first, '#' impassable
+    fn cost_grid(rows: [&str; 5]) -> (MapGrid, Vec<f32>) {
+        let costs = rows.iter().rev()
+            .flat_map(|row| row.chars())
+            .map(|cost| cost.to_digit(10).map_or(f32::INFINITY, |cost| cost as f32))
+            .collect();
+        (MapGrid { half_size: 2, stride: 1, tiles: Vec::new(), samples: Vec::new() }, costs)
+    }
+
+    // Movement cost of a path, the costs of every tile entered after the first
+    fn path_cost(grid: &MapGrid, costs: &[f32], path: &[(i32, i32)]) -> f32 {
+        path.windows(2)
+            .map(|step| {
+                let ((from_x, from_y), (to_x, to_y)) = (step[0], step[1]);
+                assert_eq!((from_x - to_x).abs() + (from_y - to_y).abs(), 1, "{:?} is no step", step);
+                costs[grid.index(to_x, to_y).unwrap()]
+            })
+            .sum()
+    }
+
+    // Crossing the middle row costs 20, going around the expensive ring by either side costs 8
+    #[test]
+    fn path_goes_around_expensive_tiles() {
+        let (grid, costs) = cost_grid([
+            "11111",
+            "19991",
+            "19191",
+            "19991",
+            "11111",
+        ]);
+        let path = find_path(&grid, &costs, (-2, 0), (2, 0)).unwrap();
+
+        assert_eq!((path[0], path[path.len() - 1]), ((-2, 0), (2, 0)));
+        assert_eq!(path.len(), 9);
+        assert_eq!(path_cost(&grid, &costs, &path), 8.0);
+    }
+
+    // The cheapest route takes the one gap in an impassable wall, however far it leads off the straight line
+    #[test]
+    fn path_takes_the_gap_in_a_wall() {
+        let (grid, costs) = cost_grid([
+            "11111",
+            "1###1",
+            "1#1#1",
+            "1#1#1",
+            "11111",
+        ]);
+        let path = find_path(&grid, &costs, (0, 0), (0, -2)).unwrap();
+
+        assert_eq!(path, vec![(0, 0), (0, -1), (0, -2)]);
+        assert_eq!(path_cost(&grid, &costs, &path), 2.0);
+
+        let path = find_path(&grid, &costs, (0, 2), (0, 0)).unwrap();
+        assert_eq!(path.len(), 11);
+        assert_eq!(path_cost(&grid, &costs, &path), 10.0);
+    }
+
+    // Walled-in, impassable or off-grid ends have no path
+    #[test]
+    fn unreachable_goals_have_no_path() {
+        let (grid, costs) = cost_grid([
+            "11111",
+            "1###1",
+            "1#1#1",
+            "1###1",
+            "11111",
+        ]);
+
+        assert!(find_path(&grid, &costs, (-2, -2), (0, 0)).is_none());
+        assert!(find_path(&grid, &costs, (-2, -2), (0, 1)).is_none());
+        assert!(find_path(&grid, &costs, (-2, -2), (3, 0)).is_none());
+        assert_eq!(find_path(&grid, &costs, (-2, -2), (-2, -2)), Some(vec![(-2, -2)]));
+    }
+}
@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+
+use crate::config::MapConfig;
+use crate::error::{Error, Result};
+use crate::keybindings::{Action, KeyBindings};
+use crate::land_mask::read_land_mask;
+
+pub const PRESETS_DIR: &str = "presets";
+
+pub struct PresetPlugin;
+
+// A map config read from a .ron file, named after the file
+#[derive(Clone)]
+pub struct Preset {
+    pub name: String,
+    pub config: MapConfig,
+}
+
+// Presets found at startup, in name order
+#[derive(Resource, Default)]
+pub struct Presets(pub Vec<Preset>);
+
+impl Plugin for PresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Presets::default())
+            .add_startup_system(setup_presets)
+            .add_system(cycle_presets);
+    }
+}
+
+// A MapConfig in RON, as in "(map_size: 400, world_age: 0.5, biome_model: Simple)", with its quality
+// applied and checked the way the launch config is
+pub fn read_preset(path: &std::path::Path) -> Result<Preset> {
+    let mut config: MapConfig = ron::from_str(&std::fs::read_to_string(path)?)?;
+    let quality = config.quality;
+    quality.apply(&mut config);
+    config.validate()?;
+    if let Some(path) = &config.land_mask_path {
+        config.land_mask = Some(read_land_mask(path)?);
+    }
+
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned())
+        .ok_or_else(|| Error::Config(format!("preset {} has no name", path.display())))?;
+    Ok(Preset { name, config })
+}
+
+// The config the app launches with: the preset at --config PATH or the built-in one, its view
+// overridden by --zoom, --x-shift and --y-shift, and checked the way presets are
+pub fn launch_config(args: &[String]) -> Result<MapConfig> {
+    let mut config = MapConfig::default();
+    let mut view = Vec::new();
+
+    for (index, arg) in args.iter().enumerate() {
+        let value = || args.get(index + 1).ok_or_else(|| Error::Config(format!("{} needs a value", arg)));
+        let number = || value()?.parse::<f64>().map_err(|_| Error::Config(format!("{} needs a number", arg)));
+
+        match arg.as_str() {
+            "--config" => config = read_preset(std::path::Path::new(value()?))?.config,
+            "--zoom" | "--x-shift" | "--y-shift" => view.push((arg.as_str(), number()?)),
+            _ => {}
+        }
+    }
+
+    // The view applies over the preset wherever the flags come
+    for (flag, value) in view {
+        match flag {
+            "--zoom" => config.initial_zoom = value,
+            "--x-shift" => config.initial_x_shift = value,
+            _ => config.initial_y_shift = value,
+        }
+    }
+
+    config.validate()?;
+    Ok(config)
+}
+
+// Every .ron file in dir that reads as a valid preset. Broken files are skipped with a warning, so one
+// bad download doesn't take the others with it
+pub fn load_presets(dir: &str) -> Result<Vec<Preset>> {
+    let mut presets = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "ron") {
+            continue;
+        }
+
+        match read_preset(&path) {
+            Ok(preset) => presets.push(preset),
+            Err(error) => warn!("Skipping preset {}: {}", path.display(), error),
+        }
+    }
+
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(presets)
+}
+
+fn setup_presets(mut presets: ResMut<Presets>) {
+    match load_presets(PRESETS_DIR) {
+        Ok(loaded) => {
+            let names: Vec<&str> = loaded.iter().map(|preset| preset.name.as_str()).collect();
+            if !names.is_empty() {
+                info!("Presets: {}", names.join(", "));
+            }
+            presets.0 = loaded;
+        }
+        Err(Error::Io(_)) => {}
+        Err(error) => warn!("Failed to load {}: {}", PRESETS_DIR, error),
+    }
+}
+
+// Steps through the presets and back to the built-in config after the last one. The biome table
+// loaded at startup carries over, as presets can't hold one
+fn cycle_presets(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    presets: Res<Presets>,
+    mut current: Local<Option<usize>>,
+    mut config: ResMut<MapConfig>,
+) {
+    if presets.0.is_empty() || !bindings.just_pressed(&keyboard, Action::CyclePreset) {
+        return;
+    }
+
+    *current = match *current {
+        None => Some(0),
+        Some(index) if index + 1 < presets.0.len() => Some(index + 1),
+        Some(_) => None,
+    };
+
+    let biome_table = config.biome_table.take();
+    *config = match *current {
+        Some(index) => {
+            info!("Preset: {}", presets.0[index].name);
+            presets.0[index].config.clone()
+        }
+        None => {
+            info!("Preset: built-in");
+            let mut config = MapConfig::default();
+            let quality = config.quality;
+            quality.apply(&mut config);
+            config
+        }
+    };
+    config.biome_table = biome_table;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    // Of two good presets, one that isn't RON, one with an invalid config and a file that isn't a
+    // preset at all, just the two good ones load, in name order
+    #[test]
+    fn only_valid_presets_load() {
+        let dir = TempDir::new("presets");
+        for (name, contents) in [
+            ("islands.ron", "(map_size: 120, world_age: 0.5, biome_model: Simple)"),
+            ("archipelago.ron", "(edge_policy: Land, wind: (direction: 90.0, strength: 0.5))"),
+            ("broken.ron", "(map_size: 120, world_age"),
+            ("empty_map.ron", "(map_size: 0)"),
+            ("notes.txt", "(map_size: 64)"),
+        ] {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+
+        let presets = load_presets(dir.as_str()).unwrap();
+        let names: Vec<&str> = presets.iter().map(|preset| preset.name.as_str()).collect();
+        assert_eq!(names, ["archipelago", "islands"]);
+
+        let islands = &presets[1].config;
+        assert_eq!(islands.map_size, 120);
+        assert_eq!(islands.tile_size, MapConfig::default().tile_size);
+    }
+
+    // A preset's view is taken as given, the view flags override it, and a view past the shift clamps
+    // is refused
+    #[test]
+    fn launch_config_takes_the_view_from_preset_and_flags() {
+        let dir = TempDir::new("launch");
+        let preset = dir.join("coast.ron");
+        std::fs::write(&preset, "(map_size: 120, initial_zoom: 2.5, initial_x_shift: 0.25)").unwrap();
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        let config = launch_config(&args(&["--y-shift", "-0.5", "--config", preset.to_str().unwrap()])).unwrap();
+        assert_eq!((config.map_size, config.initial_zoom, config.initial_x_shift, config.initial_y_shift), (120, 2.5, 0.25, -0.5));
+
+        let config = launch_config(&args(&["--config", preset.to_str().unwrap(), "--zoom", "4"])).unwrap();
+        assert_eq!((config.initial_zoom, config.initial_x_shift), (4.0, 0.25));
+
+        assert_eq!(launch_config(&[]).unwrap().initial_zoom, MapConfig::default().initial_zoom);
+        assert!(launch_config(&args(&["--x-shift", "2.0"])).is_err());
+        assert!(launch_config(&args(&["--zoom"])).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+
+// Parts of generate_grid timed separately, in the order they run for each map
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Stage {
+    // Height noise of every tile
+    Height,
+    Erosion,
+    // Temperature, percipitation and falloff noise
+    Climate,
+    // Picking the biome, including beaches, mangroves, volcanoes and the treeline
+    Classification,
+    // Picking the tile and its color for the biome
+    Color,
+    // Biome smoothing, with the tiles it recolors
+    Filter,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [Stage::Height, Stage::Erosion, Stage::Climate, Stage::Classification, Stage::Color, Stage::Filter];
+
+    pub fn column(self) -> &'static str {
+        match self {
+            Stage::Height => "height_ms",
+            Stage::Erosion => "erosion_ms",
+            Stage::Climate => "climate_ms",
+            Stage::Classification => "classification_ms",
+            Stage::Color => "color_ms",
+            Stage::Filter => "filter_ms",
+        }
+    }
+}
+
+// Time one map's generation spent in each stage, summed over its tiles
+#[derive(Clone, Copy, Default, Debug)]
+pub struct GenerationProfile {
+    pub tiles: usize,
+    pub total: Duration,
+    pub stages: [Duration; 6],
+}
+
+impl GenerationProfile {
+    pub fn stage(&self, stage: Stage) -> Duration {
+        self.stages[stage as usize]
+    }
+}
+
+// Charges the time since the last lap to a stage. A timer that is off does nothing, so the
+// generation paths share one body whether or not they are profiled. Profiled or not, it can also
+// hold a budget and a cancel flag that generation checks itself against
+pub struct StageTimer {
+    start: Option<Instant>,
+    last: Option<Instant>,
+    profile: GenerationProfile,
+    deadline: Option<(Instant, Duration)>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl StageTimer {
+    pub fn on() -> Self {
+        let now = Instant::now();
+        StageTimer { start: Some(now), last: Some(now), profile: GenerationProfile::default(), deadline: None, cancel: None }
+    }
+
+    pub fn off() -> Self {
+        StageTimer { start: None, last: None, profile: GenerationProfile::default(), deadline: None, cancel: None }
+    }
+
+    // Starts the budget now, None leaving it unlimited
+    pub fn with_budget(mut self, budget: Option<Duration>) -> Self {
+        self.deadline = budget.map(|budget| (Instant::now() + budget, budget));
+        self
+    }
+
+    // Generation stops at the next check once the flag is set
+    pub fn with_cancel(mut self, cancel: Option<Arc<AtomicBool>>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    // Err once the budget has run out or the generation was cancelled, naming the stage that was running
+    pub fn check_budget(&self, stage: Stage) -> Result<()> {
+        if self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            return Err(Error::Cancelled { stage });
+        }
+
+        match self.deadline {
+            Some((deadline, budget)) if Instant::now() > deadline => Err(Error::BudgetExceeded { budget, stage }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn lap(&mut self, stage: Stage) {
+        if let Some(last) = &mut self.last {
+            let now = Instant::now();
+            self.profile.stages[stage as usize] += now - *last;
+            *last = now;
+        }
+    }
+
+    pub fn finish(mut self, tiles: usize) -> GenerationProfile {
+        self.profile.tiles = tiles;
+        self.profile.total = self.start.map(|start| start.elapsed()).unwrap_or_default();
+        self.profile
+    }
+}
+
+pub fn profile_header() -> String {
+    let stages: Vec<&str> = Stage::ALL.iter().map(|stage| stage.column()).collect();
+    format!("seed,zoom,tiles,total_ms,{}\n", stages.join(","))
+}
+
+pub fn profile_row(seed: u32, zoom: f64, profile: &GenerationProfile) -> String {
+    let milliseconds = |duration: Duration| format!("{:.3}", duration.as_secs_f64() * 1000.0);
+    let stages: Vec<String> = Stage::ALL.iter().map(|&stage| milliseconds(profile.stage(stage))).collect();
+
+    format!("{},{},{},{},{}\n", seed, zoom, profile.tiles, milliseconds(profile.total), stages.join(","))
+}
+
+// Adds a row for the map to the CSV at path, starting the file with its header if it is new, so a
+// session's maps collect in one file
+pub fn append_profile(path: &str, seed: u32, zoom: f64, profile: &GenerationProfile) -> Result<()> {
+    let new = !std::path::Path::new(path).exists();
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+
+    if new {
+        file.write_all(profile_header().as_bytes())?;
+    }
+    file.write_all(profile_row(seed, zoom, profile).as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    // Two profiled generations write a header naming every stage and a complete row per map
+    #[test]
+    fn profile_file_has_a_header_and_a_row_per_map() {
+        let dir = TempDir::new("profile");
+        let path = dir.join("profile.csv");
+        let path = path.to_str().unwrap();
+        let mapgen = reference_map_gen(&reference_config());
+
+        let mut tiles = 0;
+        for _ in 0..2 {
+            let (grid, profile) = mapgen.generate_grid_profiled().unwrap();
+            tiles = grid.tiles.len();
+            append_profile(path, REFERENCE_SEED, 1.0, &profile).unwrap();
+        }
+
+        let csv = std::fs::read_to_string(path).unwrap();
+        let mut lines = csv.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        for column in ["seed", "zoom", "tiles", "total_ms"].into_iter().chain(Stage::ALL.iter().map(|stage| stage.column())) {
+            assert!(header.contains(&column), "the header lacks {}", column);
+        }
+
+        let rows: Vec<&str> = lines.collect();
+        assert_eq!(rows.len(), 2);
+        for row in rows {
+            let fields: Vec<&str> = row.split(',').collect();
+            assert_eq!(fields.len(), header.len(), "row '{}'", row);
+            assert!(fields.iter().all(|field| field.parse::<f64>().is_ok()), "row '{}'", row);
+            assert_eq!(fields[2], tiles.to_string());
+        }
+    }
+}
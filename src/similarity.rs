@@ -0,0 +1,96 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::biome_table::{BiomeTable, DEFAULT_BIOME_CSV};
+use crate::config::MapConfig;
+use crate::error::Result;
+use crate::export::grid_image;
+use crate::tilemap::{MapGen, MapGrid};
+
+// Small enough to generate thousands of candidates, large enough to keep the coastlines recognizable
+const SEARCH_MAP_SIZE: u32 = 64;
+
+// Fixed so a search over the same target always tries the same seeds
+const SEARCH_SEED: u64 = 20221215;
+
+// Sum of squared RGB differences over every pixel, 0 for identical images of the same size
+pub fn color_distance(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    a.pixels().zip(b.pixels())
+        .map(|(a, b)| (0..3).map(|channel| (a.0[channel] as f64 - b.0[channel] as f64).powi(2)).sum::<f64>())
+        .sum()
+}
+
+fn search_config() -> MapConfig {
+    MapConfig {
+        map_size: SEARCH_MAP_SIZE,
+        biome_table: BiomeTable::from_csv(DEFAULT_BIOME_CSV).ok(),
+        ..Default::default()
+    }
+}
+
+// The biome colors of a seed's default view, one pixel per tile
+pub fn seed_image(seed: u32) -> RgbaImage {
+    let config = search_config();
+    grid_image(&MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config).generate_grid(), 1.0, 1)
+}
+
+// seed_image generating into a grid reused across a worker's seeds
+fn seed_image_into(seed: u32, config: &MapConfig, grid: &mut MapGrid) -> RgbaImage {
+    MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), config).generate_grid_into(grid);
+    grid_image(grid, 1.0, 1)
+}
+
+// Loads an image and downsamples it to the size seed_image renders at
+pub fn load_target(path: &str) -> Result<RgbaImage> {
+    let target = image::open(path)?.to_rgba8();
+    let width = seed_image(0).width();
+
+    Ok(imageops::resize(&target, width, width, FilterType::Triangle))
+}
+
+// Compares the first `candidates` seeds of a fixed sequence against the target, split across the
+// available cores, and returns the best `keep` as (seed, distance), closest first
+pub fn search_similar(target: &RgbaImage, candidates: usize, keep: usize) -> Vec<(u32, f64)> {
+    let mut rng = StdRng::seed_from_u64(SEARCH_SEED);
+    let seeds: Vec<u32> = (0..candidates).map(|_| rng.gen()).collect();
+
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_len = seeds.len().div_ceil(threads).max(1);
+
+    let mut scored: Vec<(u32, f64)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = seeds.chunks(chunk_len)
+            .map(|chunk| scope.spawn(move || {
+                let config = search_config();
+                let mut grid = MapGrid::default();
+
+                chunk.iter()
+                    .map(|&seed| (seed, color_distance(&seed_image_into(seed, &config, &mut grid), target)))
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+
+        workers.into_iter().flat_map(|worker| worker.join().unwrap_or_default()).collect()
+    });
+
+    scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+    scored.truncate(keep);
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A candidate seed's own image finds that seed first, at distance 0, ahead of every other candidate
+    #[test]
+    fn seed_image_finds_its_own_seed() {
+        let candidates = 12;
+        let seed: u32 = StdRng::seed_from_u64(SEARCH_SEED).sample_iter(rand::distributions::Standard).nth(candidates / 2).unwrap();
+
+        let found = search_similar(&seed_image(seed), candidates, 3);
+        assert_eq!(found[0], (seed, 0.0));
+        assert!(found[1..].iter().all(|&(_, distance)| distance > 0.0), "{:?}", found);
+    }
+}
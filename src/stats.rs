@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+
+use crate::config::FONT_PATH;
+use crate::keybindings::{Action, KeyBindings};
+use crate::tilemap::MapGrid;
+
+pub struct StatsPlugin;
+
+#[derive(Resource)]
+struct ShowStats(bool);
+
+#[derive(Component)]
+struct StatsText;
+
+// Range and mean of one field over a map
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FieldStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GridStats {
+    pub height: FieldStats,
+    pub temperature: FieldStats,
+    pub percipitation: FieldStats,
+}
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowStats(false))
+            .add_startup_system(spawn_stats_text)
+            .add_system(update_stats_text);
+    }
+}
+
+impl FieldStats {
+    fn of(values: impl Iterator<Item = f64>) -> Option<Self> {
+        let mut count = 0;
+        let mut stats = FieldStats { min: f64::INFINITY, max: f64::NEG_INFINITY, mean: 0.0 };
+
+        for value in values {
+            count += 1;
+            stats.min = stats.min.min(value);
+            stats.max = stats.max.max(value);
+            stats.mean += value;
+        }
+
+        (count > 0).then(|| FieldStats { mean: stats.mean / count as f64, ..stats })
+    }
+}
+
+// Stats of the grid's samples in one pass each, None for an empty grid
+pub fn grid_stats(grid: &MapGrid) -> Option<GridStats> {
+    Some(GridStats {
+        height: FieldStats::of(grid.samples.iter().map(|sample| sample.height))?,
+        temperature: FieldStats::of(grid.samples.iter().map(|sample| sample.temperature))?,
+        percipitation: FieldStats::of(grid.samples.iter().map(|sample| sample.percipitation))?,
+    })
+}
+
+fn spawn_stats_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font: asset_server.load(FONT_PATH),
+            font_size: 16.0,
+            color: Color::WHITE,
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        StatsText,
+    ));
+}
+
+fn stats_line(name: &str, unit: &str, stats: &FieldStats) -> String {
+    format!("{:<13} {:>8.1} {:>8.1} {:>8.1} {}", name, stats.min, stats.mean, stats.max, unit)
+}
+
+// The stats key toggles a panel of each field's min, mean and max over the current map, rewritten
+// whenever the map is regenerated. Low memory mode keeps no grid to read them from
+fn update_stats_text(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut show: ResMut<ShowStats>,
+    grid: Option<Res<MapGrid>>,
+    mut had_grid: Local<bool>,
+    mut text: Query<&mut Text, With<StatsText>>,
+) {
+    if bindings.just_pressed(&keyboard, Action::ToggleStats) {
+        show.0 = !show.0;
+    }
+
+    // Switching to low memory mode removes the grid rather than changing it
+    let grid_changed = grid.as_ref().is_some_and(|grid| grid.is_changed()) || grid.is_some() != *had_grid;
+    *had_grid = grid.is_some();
+    if !show.is_changed() && !grid_changed {
+        return;
+    }
+
+    let value = match (show.0, grid.as_ref().and_then(|grid| grid_stats(grid))) {
+        (false, _) => String::new(),
+        (true, None) => "No grid to take stats of".to_string(),
+        (true, Some(stats)) => [
+            format!("{:<13} {:>8} {:>8} {:>8}", "", "min", "mean", "max"),
+            stats_line("Height", "m", &stats.height),
+            stats_line("Temperature", "C", &stats.temperature),
+            stats_line("Percipitation", "mm", &stats.percipitation),
+        ].join("\n"),
+    };
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    // The stats match a plain computation over the samples
+    #[test]
+    fn stats_match_the_samples() {
+        let grid = reference_map_gen(&reference_config()).generate_grid();
+        let stats = grid_stats(&grid).unwrap();
+
+        for (values, reported) in [
+            (grid.samples.iter().map(|sample| sample.height).collect::<Vec<f64>>(), stats.height),
+            (grid.samples.iter().map(|sample| sample.temperature).collect(), stats.temperature),
+            (grid.samples.iter().map(|sample| sample.percipitation).collect(), stats.percipitation),
+        ] {
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+
+            assert_eq!((reported.min, reported.max), (min, max));
+            assert!((reported.mean - mean).abs() <= 1e-9 * mean.abs().max(1.0), "mean {} reported as {}", mean, reported.mean);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::biome_table::{BiomeTable, DEFAULT_BIOME_CSV};
+use crate::config::MapConfig;
+use crate::tilemap::MapGen;
+
+// Small fixed-seed map most tests generate, the one the reference image pins down
+pub const REFERENCE_SEED: u32 = 829201;
+pub const REFERENCE_MAP_SIZE: u32 = 64;
+
+pub fn reference_config() -> MapConfig {
+    MapConfig {
+        map_size: REFERENCE_MAP_SIZE,
+        biome_table: BiomeTable::from_csv(DEFAULT_BIOME_CSV).ok(),
+        ..Default::default()
+    }
+}
+
+// The reference seed under config, unzoomed and centered
+pub fn reference_map_gen(config: &MapConfig) -> MapGen {
+    MapGen::new(REFERENCE_SEED, 1.0, 0.0, 0.0, (0.0, 0.0), config)
+}
+
+// A scratch directory under the system temp dir, removed again when dropped
+pub struct TempDir(std::path::PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("rust_game_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+
+    pub fn join(&self, name: &str) -> std::path::PathBuf {
+        self.0.join(name)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
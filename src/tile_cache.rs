@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+
+use bevy::utils::HashMap;
+
+use crate::tilemap::{Tile, TileSample};
+
+// World units the cache snaps lookups to, far finer than a tile so hover and path queries still
+// land on the points they asked for
+pub const TILE_CACHE_QUANTUM: f64 = 1.0 / 64.0;
+
+// Finished tiles by quantized world position, dropping the least recently used past capacity.
+// Belongs to one MapGen, so a new seed or config starts with an empty cache
+pub struct TileCache {
+    capacity: usize,
+    // Each tile with the stamp of its last use
+    entries: HashMap<(i64, i64), (Tile, TileSample, u64)>,
+    // Keys in order of use. A key can appear several times, only the entry matching its stamp in
+    // entries is current, so a lookup never has to search this queue
+    uses: VecDeque<((i64, i64), u64)>,
+    clock: u64,
+}
+
+impl TileCache {
+    pub fn new(capacity: usize) -> Self {
+        TileCache {
+            capacity,
+            entries: HashMap::default(),
+            uses: VecDeque::new(),
+            clock: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn tile_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn quantize(x: f64, y: f64) -> (i64, i64) {
+        ((x / TILE_CACHE_QUANTUM).round() as i64, (y / TILE_CACHE_QUANTUM).round() as i64)
+    }
+
+    pub fn position(key: (i64, i64)) -> (f64, f64) {
+        (key.0 as f64 * TILE_CACHE_QUANTUM, key.1 as f64 * TILE_CACHE_QUANTUM)
+    }
+
+    pub fn get(&mut self, key: (i64, i64)) -> Option<(Tile, TileSample)> {
+        let stamp = self.tick();
+        let entry = self.entries.get_mut(&key)?;
+        entry.2 = stamp;
+        let (tile, sample) = (entry.0, entry.1);
+
+        self.record_use(key, stamp);
+        Some((tile, sample))
+    }
+
+    pub fn insert(&mut self, key: (i64, i64), tile: Tile, sample: TileSample) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let stamp = self.tick();
+        self.entries.insert(key, (tile, sample, stamp));
+        self.record_use(key, stamp);
+
+        while self.entries.len() > self.capacity {
+            let (oldest, stamp) = match self.uses.pop_front() {
+                Some(used) => used,
+                None => break,
+            };
+            if self.entries.get(&oldest).is_some_and(|entry| entry.2 == stamp) {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    // Notes a use, dropping stale queue entries once they outnumber the live ones so the queue stays
+    // within a constant factor of capacity
+    fn record_use(&mut self, key: (i64, i64), stamp: u64) {
+        self.uses.push_back((key, stamp));
+
+        if self.uses.len() > 2 * self.capacity.max(1) {
+            let entries = &self.entries;
+            self.uses.retain(|(key, stamp)| entries.get(key).is_some_and(|entry| entry.2 == *stamp));
+        }
+    }
+}
@@ -1,42 +1,280 @@
+use bevy::ecs::system::SystemParam;
+use bevy::input::InputSystem;
 use bevy::prelude::*;
+use bevy::render::texture::ImageSampler;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
 use std::ops::Add;
 use std::ops::Mul;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use noise::{NoiseFn, Perlin};
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::animation::GenTime;
+use crate::biome_table::{load_biome_table, BiomeTable, SIMPLE_BIOME_CSV};
+use crate::brush::BiomeOverrides;
+use crate::camera::cursor_world_position;
+use crate::config::{BiomeModel, Clouds, ColoringMode, Connectivity, DebugView, EdgePolicy, DisplayConfig, MapConfig, SubsampleMode, TileFiltering, FONT_PATH};
+use crate::daily;
+use crate::error::Result;
+use crate::explore::ExploreMode;
+use crate::export;
+use crate::keybindings::{Action, KeyBindings};
+use crate::land_mask::read_land_mask;
+use crate::names::landmass_name;
+use crate::overlay::{
+    adjust, blend, cast_shadows, combined_color, continuous_color, cost_color, day_tint, dither, falloff_color, haze, height_color, modulate,
+    ocean_color, HEIGHT_RANGE,
+    ISOLATION_GRAY, PERCIPITATION_RANGE, TEMPERATURE_RANGE,
+};
+use crate::pathfinding::find_path;
+use crate::profile::{append_profile, GenerationProfile, Stage, StageTimer};
+use crate::tile_cache::TileCache;
+
+#[derive(Resource)]
+pub struct Seed(pub u32, pub SeedOrigin);
+
+// What a seed was made from, so it can be shown the way it was entered
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SeedOrigin {
+    Number,
+    // Hashed from this text
+    Text(String),
+}
+
+// Text typed so far while entering a seed, None when not entering one
+#[derive(Resource, Default)]
+pub struct SeedEntry(pub Option<String>);
+
+// Label of the seed entry, for text entries that must see the keyboard before it
+#[derive(SystemLabel)]
+pub struct SeedEntrySystem;
+
+#[derive(Resource)]
+pub struct Zoom(pub f64);
+
+#[derive(Resource)]
+pub struct XShift(pub f64);
 
 #[derive(Resource)]
-struct Seed(u32);
+pub struct YShift(pub f64);
+
+// Origin of the noise sampling in map axis lengths, moving the terrain under a fixed falloff
+#[derive(Resource, Default)]
+pub struct NoiseOffset(pub f64, pub f64);
+
+// Everything besides the seed that a map is generated from
+#[derive(SystemParam)]
+struct MapView<'w, 's> {
+    zoom: Res<'w, Zoom>,
+    x_shift: Res<'w, XShift>,
+    y_shift: Res<'w, YShift>,
+    noise_offset: Res<'w, NoiseOffset>,
+    config: Res<'w, MapConfig>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl MapView<'_, '_> {
+    fn is_changed(&self) -> bool {
+        self.zoom.is_changed() || self.x_shift.is_changed() || self.y_shift.is_changed()
+            || self.noise_offset.is_changed() || self.config.is_changed()
+    }
+}
 
 #[derive(Resource)]
-struct Zoom(f64);
+struct ShowLabels(bool);
 
 #[derive(Resource)]
-struct XShift(f64);
+struct ShowClimateZones(bool);
+
+// The only biome drawn in full color, every other tile is grayed out
+#[derive(Resource, Default)]
+struct IsolatedBiome(Option<Biome>);
+
+#[derive(Component)]
+struct LandmassLabel;
+
+#[derive(Component)]
+struct SeedEntryText;
+
+#[derive(Component)]
+struct SeedText;
+
+#[derive(Component)]
+struct IsolatedBiomeText;
+
+#[derive(Component)]
+struct DebugViewText;
+
+// Everything spawn_map creates, despawned again on regeneration
+#[derive(Component)]
+struct MapEntity;
+
+// Coarse map shown while the full one generates on another thread
+#[derive(Component)]
+struct MapPreview {
+    generating: Option<std::thread::JoinHandle<Result<MapGrid>>>,
+    // Set when the preview is despawned, so a thread whose map was replaced stops early
+    cancel: Arc<AtomicBool>,
+    atlas: Handle<TextureAtlas>,
+    seed: u32,
+}
+
+impl Drop for MapPreview {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+// Tile of a replaced map, fading out since the GenTime second it was replaced at
+#[derive(Component)]
+struct FadingOut(f32);
+
+// Time the seed last changed while a debounced regeneration waits for it to settle
+#[derive(Resource, Default)]
+struct PendingSeed(Option<f32>);
+
+impl PendingSeed {
+    // Whether the seed should regenerate the map at second now. A debounced seed only regenerates once
+    // it has stopped changing for debounce seconds, so a burst of changes regenerates just once
+    fn due(&mut self, seed_changed: bool, now: f32, debounce: f32) -> bool {
+        if debounce <= 0.0 {
+            return seed_changed;
+        }
+
+        if seed_changed {
+            self.0 = Some(now);
+        }
+        self.0.is_some_and(|changed| now - changed >= debounce)
+    }
+}
+
+// Set by schedule_map on frames the map regenerates, to whether the old tiles crossfade out
+#[derive(Resource, Default)]
+struct MapDue(Option<bool>);
+
+// GenTime second the current map started fading in, while a seed crossfade runs
+#[derive(Resource, Default)]
+struct MapFadeIn(Option<f32>);
+
+// Depth of fading out tiles, above the new map and below the landmass labels
+const FADING_OUT_Z: f32 = 0.5;
 
+// Generated tile and climate data of a spawned tile, before any overrides or overlays
+#[derive(Component)]
+struct MapTile {
+    coords: (i32, i32),
+    // Cell of the map under the tile, which painted biomes are keyed by, see MapGen::map_cell
+    cell: (i32, i32),
+    tile: Tile,
+    sample: TileSample,
+    movement_cost: f32,
+}
+
+// The map, launched with config and at its initial view
+pub struct TileMapPlugin {
+    pub config: MapConfig,
+}
+
+// Noise the cloud layer is cut from, in tile units
 #[derive(Resource)]
-struct YShift(f64);
+struct CloudNoise(NoiseMap);
 
-pub struct TileMapPlugin;
+// Grid tiles in a cast shadow, by MapGrid index, empty with shadows off
+#[derive(Resource, Default)]
+struct CastShadows(Vec<bool>);
 
 // Adiabatic Lapse Rates for dry and wet air [C/m]
 const DRY_ADB_LAPSE_RATE: f64 = 9.8/1000.0;
 const WET_ADB_LAPSE_RATE: f64 = 5.0/1000.0;
 
+// Tiles upwind searched for a mountain casting a rain shadow, and the rise [m] above the tile at
+// which such a mountain takes all moisture out of the air at full wind strength
+const RAIN_SHADOW_REACH: i32 = 6;
+const RAIN_SHADOW_BARRIER: f64 = 2000.0;
+
+// Seed of the cloud noise, fixed so the sky doesn't change with the map
+const CLOUD_SEED: u32 = 0xc10d;
+// Range of the cloud noise over which a cloud's edge thickens from clear to full cover
+const CLOUD_EDGE: f64 = 0.1;
+
+// Percipitation [mm] the soft cap's roll-off spans at a percipitation_cap_hardness of 1
+const CAP_ROLL_OFF: f64 = 1000.0;
+
 // Grass biome colors
 const YELLOW_COLOR: Color = Color::rgb(240.0/255.0, 240.0/255.0, 127.0/255.0);
 const BLUE_COLOR: Color   = Color::rgb(0.0/255.0, 255.0/255.0, 213.0/255.0);
 const GREEN_COLOR: Color  = Color::rgb(0.0/255.0, 255.0/255.0, 17.0/255.0);
 
-struct MapGen {
-    map_size: u32,
-    tile_size: f64, 
-    tile_scale: f64,
-    height_noise: NoiseMap,
+// Average color of the desert atlas tiles, for blending computed colors toward them
+const DESERT_SAND_COLOR: Color = Color::rgb(0.86, 0.76, 0.52);
+
+// Samples across the desert blend zone looking for its boundary
+const DESERT_BLEND_STEPS: usize = 8;
+
+// Side of the square noise space cells [world units at zoom 1] that each hold at most one volcano, and
+// the volcano's radius and that of its lava crater as shares of the side
+const VOLCANO_CELL: f64 = 150.0;
+const VOLCANO_RADIUS: f64 = 0.15;
+const CRATER_RADIUS: f64 = 0.04;
+
+const LAVA_COLOR: Color = Color::rgb(0.95, 0.35, 0.05);
+
+// Only a budget aborts generation, so the paths without one can't fail
+const UNBUDGETED: &str = "generation without a budget never runs out of it";
+
+// Furthest the view may be shifted from the map center, in map axis lengths
+pub const MAX_SHIFT: f64 = 0.9;
+
+const TILEMAP_TEXTURE_PATH: &str = "textures/tilemap.png";
+
+// Tiles in the single row of the tilemap texture
+pub const ATLAS_TILES: usize = 6;
+
+// Landmasses smaller than this many tiles are left unnamed
+const MIN_LANDMASS_TILES: usize = 40;
+
+// Points per axis the land fraction of a candidate seed is estimated from, far fewer than a full map
+const LAND_FRACTION_SAMPLES: i32 = 32;
+
+// Points per axis the visible region of a candidate seed is judged from at most, see MapGen::view_biomes
+const VIEW_SAMPLES: i32 = 32;
+
+// Radius [tiles] of the biome report around the cursor
+const BIOME_REPORT_RADIUS: f64 = 10.0;
+
+// Change per key press of the brightness, contrast and saturation adjustments, and their largest value
+const COLOR_ADJUSTMENT_STEP: f32 = 0.1;
+const MAX_COLOR_ADJUSTMENT: f32 = 3.0;
+
+// The generator of the spawned map, kept for queries after spawning
+#[derive(Resource)]
+pub struct MapGen {
+    // Height fields of every blended seed with their weights, just the map seed at weight 1 by default
+    height_noises: Vec<(NoiseMap, f64)>,
     temperature_noise: NoiseMap,
     humidity_noise: NoiseMap,
+    // Tiles classified from the noise so far. Recoloring works from cached samples and never adds to it
+    climate_samples: AtomicUsize,
+    // Raggedness of the treeline, one fine octave
+    treeline_noise: NoiseMap,
+    // Seed the volcano hotspots hash, with the side of their cells in noise space
+    seed: u32,
+    volcano_cell: f64,
+    // Tiles get_tile_at_world already sampled, None when tile_cache_size is 0
+    tile_cache: Option<Mutex<TileCache>>,
     zoom: f64,
-    x_shift: f64,
-    y_shift: f64,
+    noise_offset: (f64, f64),
+    // Noise space point of the view center. Coordinates inside MapGen are relative to it, so they
+    // stay small enough to keep their detail at any zoom
+    noise_base: (f64, f64),
+    // Stops budgeted generation once set, None for a generator nothing cancels
+    cancel: Option<Arc<AtomicBool>>,
+    config: MapConfig,
 }
 
 struct NoiseMap {
@@ -47,7 +285,8 @@ struct NoiseMap {
     lacunarity: f64,
 }
 
-enum Biome {
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize, Serialize)]
+pub enum Biome {
     Ocean,
     
     PolarDesert,
@@ -86,73 +325,345 @@ enum Biome {
     TropicalMoistForest,
     TropicalWetForest,
     TropicalRainForest,
+
+    Marsh,
+
+    Beach,
+
+    Alpine,
+
+    SaltFlat,
+
+    Mangrove,
+
+    Volcanic,
+
+    IceSheet,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClimateZone {
+    Polar,
+    Subpolar,
+    Boreal,
+    Temperate,
+    Subtropical,
+    Tropical,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub index: usize,
+    pub color: Color,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct TileSample {
+    pub height: f64,
+    pub temperature: f64,
+    pub percipitation: f64,
+    // Share of the height noise left after the radial falloff, see MapGen::falloff
+    pub falloff: f64,
+    pub biome: Biome,
+}
+
+// A land tile MapGen::pick_spawn may choose, with what its predicate judges it by
+#[derive(Clone, Copy)]
+pub struct SpawnSite {
+    pub x: i32,
+    pub y: i32,
+    pub sample: TileSample,
+    // Tiles along the axes to the nearest ocean tile, None if the grid has none
+    pub coast_distance: Option<u32>,
+    // Steepest height change [m per tile] to a neighbour
+    pub slope: f64,
+}
+
+// Generated tiles row by row, one every stride tiles from -half_size * stride to half_size * stride on both axes
+#[derive(Resource, Default)]
+pub struct MapGrid {
+    pub half_size: i32,
+    pub stride: i32,
+    pub tiles: Vec<Tile>,
+    pub samples: Vec<TileSample>,
+}
+
+// The grid's samples split into one array per field, in the same row order, for numeric tools
+pub struct GridFields {
+    pub width: usize,
+    pub height: Vec<f32>,
+    pub temperature: Vec<f32>,
+    pub percipitation: Vec<f32>,
+    // Declaration order in Biome, as in Biome::ALL
+    pub biome: Vec<u8>,
+}
+
+impl GridFields {
+    // Flood fills the grid into regions of connected same-biome tiles, numbered from 0 in the order
+    // their first tile comes in the grid. Two areas of one biome that don't touch get different ids
+    pub fn regions(&self, connectivity: Connectivity) -> Vec<u32> {
+        let width = self.width.max(1) as i32;
+        let rows = (self.biome.len() as i32 + width - 1) / width;
+        let mut regions = vec![u32::MAX; self.biome.len()];
+        let mut next_region = 0;
+
+        for start in 0..self.biome.len() {
+            if regions[start] != u32::MAX {
+                continue;
+            }
+
+            regions[start] = next_region;
+            let mut stack = vec![start];
+
+            while let Some(index) = stack.pop() {
+                let (x, y) = (index as i32 % width, index as i32 / width);
+
+                for &(dx, dy) in connectivity.offsets() {
+                    let (next_x, next_y) = (x + dx, y + dy);
+                    if next_x < 0 || next_x >= width || next_y < 0 || next_y >= rows {
+                        continue;
+                    }
+
+                    let next = (next_y * width + next_x) as usize;
+                    if next < self.biome.len() && regions[next] == u32::MAX && self.biome[next] == self.biome[start] {
+                        regions[next] = next_region;
+                        stack.push(next);
+                    }
+                }
+            }
+
+            next_region += 1;
+        }
+
+        regions
+    }
 }
 
-struct Tile {
-    index: usize,
-    color: Color,
+// Connected component of land tiles, in grid coordinates
+struct Landmass {
+    // Highest tile, which names the landmass
+    peak: (i32, i32),
+    tiles: usize,
+    centroid: (f64, f64),
 }
 
 impl Plugin for TileMapPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Seed(829201))
-            .insert_resource(Zoom(1.0))
-            .insert_resource(XShift(0.0))
-            .insert_resource(YShift(0.0))
-            .add_startup_system(spawn_map)
-            .add_system(update_inputs)
-            .add_system(update_map);
+        app.insert_resource(Seed::number(829201))
+            .insert_resource(Zoom(self.config.initial_zoom))
+            .insert_resource(XShift(self.config.initial_x_shift))
+            .insert_resource(YShift(self.config.initial_y_shift))
+            .insert_resource(NoiseOffset::default())
+            .insert_resource(MapFadeIn::default())
+            .insert_resource(PendingSeed::default())
+            .insert_resource(MapDue::default())
+            .insert_resource(self.config.clone())
+            .insert_resource(DisplayConfig::default())
+            .insert_resource(ShowLabels(true))
+            .insert_resource(ShowClimateZones(false))
+            .insert_resource(SeedEntry::default())
+            .insert_resource(IsolatedBiome::default())
+            .insert_resource(CloudNoise(NoiseMap {
+                noise_map: Perlin::new(CLOUD_SEED),
+                octaves: 2,
+                scale: 1.0,
+                persistance: 0.25,
+                lacunarity: 2.5,
+            }))
+            .insert_resource(CastShadows::default())
+            .add_startup_system(setup_config)
+            .add_startup_system(spawn_seed_entry_text)
+            .add_startup_system(spawn_seed_text)
+            .add_startup_system(spawn_isolated_biome_text)
+            .add_startup_system(spawn_debug_view_text)
+            .add_startup_system(setup_daily_seed.after(setup_config))
+            .add_startup_system(setup_land_seed.after(setup_daily_seed))
+            .add_startup_system(spawn_map.after(setup_land_seed))
+            .add_system_to_stage(CoreStage::PreUpdate, update_seed_entry.label(SeedEntrySystem).after(InputSystem))
+            .add_system(update_seed_inputs)
+            .add_system(update_view_inputs)
+            .add_system(update_display_inputs)
+            .add_system(update_generation_inputs)
+            .add_system(reseed_keeping_view)
+            .add_system(update_zoom)
+            .add_system(schedule_map)
+            .add_system(clear_map.after(schedule_map))
+            .add_system(respawn_map.after(clear_map))
+            .add_system(update_label_visibility)
+            .add_system(update_seed_text)
+            .add_system(update_isolated_biome)
+            .add_system(update_debug_view)
+            .add_system(update_color_adjustments)
+            .add_system(update_tile_sampling)
+            .add_system(export_tile_metadata)
+            .add_system(report_biomes_near_cursor)
+            .add_system(report_center_biome)
+            .add_system(center_on_cursor)
+            .add_system(update_shadows.before(update_tile_colors))
+            .add_system(update_tile_colors)
+            .add_system(fade_out_replaced_tiles)
+            .add_system(snap_tiles_to_pixels)
+            .add_system(finish_map_preview);
+    }
+}
+
+impl Seed {
+    pub fn number(value: u32) -> Self {
+        Seed(value, SeedOrigin::Number)
+    }
+
+    // FNV-1a, so a given string names the same world on every run and platform
+    fn from_str_hashed(text: &str) -> Self {
+        let mut hash: u32 = 0x811c9dc5;
+
+        for byte in text.bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+
+        Seed(hash, SeedOrigin::Text(text.to_string()))
+    }
+
+    // Hashed from today's date so every player gets the same daily map
+    fn daily() -> (Self, String) {
+        let date = daily::today();
+        (Seed::from_str_hashed(&date), date)
     }
 }
 
 impl MapGen {
-    fn new(seed: u32, zoom: f64, x_shift: f64, y_shift: f64, map_size: u32, tile_size: f64, tile_scale: f64) -> Self {
+    pub fn new(seed: u32, zoom: f64, x_shift: f64, y_shift: f64, noise_offset: (f64, f64), config: &MapConfig) -> Self {
+        let scale_factor = config.zoom_scaling.factor(zoom);
 
-        let height_noise = NoiseMap {
-            noise_map: Perlin::new(seed),
-            octaves: 24,
-            scale: 100.0 * zoom,
-            persistance: 0.3,
-            lacunarity: 4.7,
+        let height_seeds = if config.seed_blend.is_empty() {
+            vec![(seed, 1.0)]
+        } else {
+            config.seed_blend.clone()
         };
 
+        let height_noises = height_seeds.into_iter().map(|(seed, weight)| {
+            let noise = NoiseMap {
+                noise_map: Perlin::new(seed),
+                octaves: config.height_octaves,
+                scale: 100.0 * scale_factor,
+                persistance: 0.3,
+                lacunarity: 4.7,
+            };
+            (noise, weight)
+        }).collect();
+
         let temperature_noise = NoiseMap {
             noise_map: Perlin::new(seed),
-            octaves: 24,
-            scale: 70.0 * zoom,
+            octaves: config.temperature_octaves,
+            scale: 70.0 * scale_factor,
             persistance: 0.2,
             lacunarity: 4.1,
         };
 
         let humidity_noise = NoiseMap {
             noise_map: Perlin::new(seed),
-            octaves: 8,
-            scale: 90.0 * zoom,
-            persistance: 0.08,
-            lacunarity: 1.2,
+            octaves: config.humidity_octaves,
+            scale: 90.0 * scale_factor,
+            persistance: config.humidity_persistance,
+            lacunarity: config.humidity_lacunarity,
         };
 
+        let treeline_noise = NoiseMap {
+            noise_map: Perlin::new(seed),
+            octaves: 1,
+            scale: 8.0 * scale_factor,
+            persistance: 1.0,
+            lacunarity: 1.0,
+        };
+
+        let axis_len = config.noise_axis_len();
+        let noise_base = (
+            axis_len * zoom * x_shift + axis_len * noise_offset.0,
+            axis_len * zoom * y_shift + axis_len * noise_offset.1,
+        );
+
         Self {
-            map_size,
-            tile_size, 
-            tile_scale,
-            height_noise,
+            height_noises,
             temperature_noise,
             humidity_noise,
+            climate_samples: AtomicUsize::new(0),
+            treeline_noise,
+            seed,
+            volcano_cell: VOLCANO_CELL * scale_factor,
+            tile_cache: (config.tile_cache_size > 0).then(|| Mutex::new(TileCache::new(config.tile_cache_size))),
             zoom,
-            x_shift,
-            y_shift,
+            noise_offset,
+            noise_base,
+            cancel: None,
+            config: Self::model_config(config),
+        }
+    }
+
+    // Budgeted generation stops with Error::Cancelled once the flag is set
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    // timer with the config's generation_budget and the generator's cancel flag
+    fn budgeted(&self, timer: StageTimer) -> StageTimer {
+        timer.with_budget(self.config.generation_budget()).with_cancel(self.cancel.clone())
+    }
+
+    // The simple model classifies land through its own table in place of the configured one
+    fn model_config(config: &MapConfig) -> MapConfig {
+        let mut config = config.clone();
+
+        if config.biome_model == BiomeModel::Simple {
+            config.biome_table = BiomeTable::from_csv(SIMPLE_BIOME_CSV).ok();
+        }
+
+        config
+    }
+
+    // Frozen land wet enough to build up ice is an ice sheet rather than a polar desert. The simple
+    // model keeps its single snow biome
+    fn split_polar(&self, biome: Biome, percipitation: f64) -> Biome {
+        if biome == Biome::PolarDesert && self.config.biome_model == BiomeModel::Holdridge
+            && percipitation >= self.config.ice_sheet_min_percipitation {
+            Biome::IceSheet
+        } else {
+            biome
         }
     }
 
-    fn pick_biome(&self, height: f64, temperature: f64, percipitation: f64) -> Biome {
+    pub fn pick_biome(&self, height: f64, temperature: f64, percipitation: f64) -> Biome {
 
         if height <= 0.0 {
             return Biome::Ocean;
         }
 
+        let simple = self.config.biome_model == BiomeModel::Simple;
+
+        if height >= self.config.alpine_min_height {
+            return if simple { Biome::PolarDesert } else { Biome::Alpine };
+        }
+
+        if !simple && height <= self.config.marsh_max_height
+            && percipitation >= self.config.marsh_min_percipitation
+            && temperature >= self.config.marsh_min_temperature {
+            return Biome::Marsh;
+        }
+
+        if !simple && height <= self.config.salt_flat_max_height
+            && percipitation <= self.config.salt_flat_max_percipitation
+            && temperature >= self.config.salt_flat_min_temperature {
+            return Biome::SaltFlat;
+        }
+
+        if let Some(table) = &self.config.biome_table {
+            if let Some(biome) = table.classify(temperature, percipitation) {
+                return self.split_polar(biome, percipitation);
+            }
+        }
+
         if temperature <= 0.0 { // polar
-           return Biome::PolarDesert;
+           return self.split_polar(Biome::PolarDesert, percipitation);
         } else if temperature > 0.0 && temperature <= 3.0 { // subpolar
             if percipitation <= 125.0 {
                 return Biome::SubpolarDryTundra;
@@ -227,52 +738,83 @@ impl MapGen {
 
     }
 
-    fn pick_tile(&self, biome: Biome, temperature: f64, percipitation: f64) -> Tile {
-        match biome {
-            Biome::Ocean => Biome::ocean_tile(temperature, percipitation),
+    // Computed color tiles just wetter than a desert fade toward the sand of the desert atlas tiles,
+    // reaching half sand at the boundary. classified_percipitation is the value pick_biome was given
+    fn pick_tile(&self, biome: Biome, height: f64, temperature: f64, percipitation: f64, classified_percipitation: f64) -> Tile {
+        let mut tile = biome.tile(temperature, percipitation);
 
-            Biome::PolarDesert => Biome::polar_desert_tile(temperature, percipitation),
+        let width = self.config.desert_blend_width;
+        let is_desert = |percipitation: f64| {
+            let drier = self.pick_biome(height, temperature, percipitation);
+            is_desert_tile(&drier.tile(temperature, percipitation))
+        };
 
-            Biome::SubpolarDryTundra => Biome::subpolar_dry_tundra_tile(temperature, percipitation),
-            Biome::SubpolarMoistTundra => Biome::subpolar_moist_tundra_tile(temperature, percipitation),
-            Biome::SubpolarWetTundra => Biome::subpolar_wet_tundra_tile(temperature, percipitation),
-            Biome::SubpolarRainTundra => Biome::subpolar_rain_tundra_tile(temperature, percipitation),
-            
-            Biome::BorealDesert => Biome::boreal_desert_tile(temperature, percipitation),
-            Biome::BorealDryScrub => Biome::boreal_dry_scrub_tile(temperature, percipitation),
-            Biome::BorealMoistForest => Biome::boreal_moist_forest_tile(temperature, percipitation),
-            Biome::BorealWetForest => Biome::boreal_wet_forest_tile(temperature, percipitation),
-            Biome::BorealRainForest => Biome::boreal_rain_forest_tile(temperature, percipitation),
-        
-            Biome::TemperateDesert => Biome::temperate_desert_tile(temperature, percipitation),
-            Biome::TemperateDesertScrub => Biome::temperate_desert_scrub_tile(temperature, percipitation),
-            Biome::TemperateSteppe => Biome::temperate_steppe_tile(temperature, percipitation),
-            Biome::TemperateMoistForest => Biome::temperate_moist_forest_tile(temperature, percipitation),
-            Biome::TemperateWetForest => Biome::temperate_wet_forest_tile(temperature, percipitation),
-            Biome::TemperateRainForest => Biome::temperate_rain_forest_tile(temperature, percipitation),
-        
-            Biome::SubtropicalDesert => Biome::subtropical_desert_tile(temperature, percipitation),
-            Biome::SubtropicalDesertScrub => Biome::subtropical_desert_scrub_tile(temperature, percipitation),
-            Biome::SubtropicalThornWoodland => Biome::subtropical_thorn_woodland_tile(temperature, percipitation),
-            Biome::SubtropicalDryForest => Biome::subtropical_dry_forest_tile(temperature, percipitation),
-            Biome::SubtropicalMoistForest => Biome::subtropical_moist_forest_tile(temperature, percipitation),
-            Biome::SubtropicalWetForest => Biome::subtropical_wet_forest_tile(temperature, percipitation),
-            Biome::SubtropicalRainForest => Biome::subtropical_rain_forest_tile(temperature, percipitation),
-            
-            Biome::TropicalDesert => Biome::tropical_desert_tile(temperature, percipitation),
-            Biome::TropicalDesertScrub => Biome::tropical_desert_scrub_tile(temperature, percipitation),
-            Biome::TropicalThornWoodland => Biome::tropical_thorn_woodland_tile(temperature, percipitation),
-            Biome::TropicalVeryDryForest => Biome::tropical_very_dry_forest_tile(temperature, percipitation),
-            Biome::TropicalDryForest => Biome::tropical_dry_forest_tile(temperature, percipitation),
-            Biome::TropicalMoistForest => Biome::tropical_moist_forest_tile(temperature, percipitation),
-            Biome::TropicalWetForest => Biome::tropical_wet_forest_tile(temperature, percipitation),
-            Biome::TropicalRainForest => Biome::tropical_rain_forest_tile(temperature, percipitation),
+        // Desert lies below wetter land, so nothing within the zone if not at its far edge
+        if is_desert_tile(&tile) || width <= 0.0 || !is_desert(classified_percipitation - width) {
+            return tile;
         }
+
+        let steps_to_desert = (1..=DESERT_BLEND_STEPS)
+            .find(|step| is_desert(classified_percipitation - width * *step as f64 / DESERT_BLEND_STEPS as f64))
+            .unwrap_or(DESERT_BLEND_STEPS);
+
+        let mut sand = DESERT_SAND_COLOR;
+        sand.set_a(0.5 * (DESERT_BLEND_STEPS + 1 - steps_to_desert) as f32 / DESERT_BLEND_STEPS as f32);
+        tile.color = blend(tile.color, sand);
+
+        tile
+    }
+
+    // The percipitation biomes are classified by. Vegetation density stands in for the moisture plants
+    // hold, so denser worlds classify as wetter; this is the only place it enters classification
+    fn classification_moisture(&self, percipitation: f64) -> f64 {
+        percipitation * self.config.vegetation_density()
+    }
+
+    // The biome's tile, colored by the biome or, in the continuous coloring mode, by the climate alone
+    // on land, where it keeps the biome's atlas index. The ocean is colored by its temperature in both
+    fn colored_tile(&self, biome: Biome, height: f64, temperature: f64, percipitation: f64) -> Tile {
+        if biome == Biome::Ocean {
+            return Tile {
+                index: biome.tile(temperature, percipitation).index,
+                color: ocean_color(&self.config.ocean_coloring, temperature),
+            };
+        }
+
+        if self.config.coloring_mode == ColoringMode::Continuous {
+            return Tile {
+                index: biome.tile(temperature, percipitation).index,
+                color: continuous_color(&self.config.continuous_coloring, height, temperature, percipitation),
+            };
+        }
+
+        self.pick_tile(biome, height, temperature, percipitation, self.classification_moisture(percipitation))
+    }
+
+    // Weighted sum of the blended height fields
+    fn height_value(&self, base: (f64, f64), x: f64, y: f64) -> f64 {
+        self.height_noises.iter().map(|(noise, weight)| weight * noise.get_value(base, x, y)).sum()
+    }
+
+    // Factor the height noise is scaled by, falling with distance from the map center so land gathers
+    // there. A second, offset sample of the height noise roughens the falloff's circle into a continent outline
+    fn falloff(&self, r_dis: f64, x: f64, y: f64) -> f64 {
+        let noise = match self.config.falloff_noise_offset {
+            Some((offset_x, offset_y)) => self.height_value((self.noise_base.0 + offset_x, self.noise_base.1 + offset_y), x, y),
+            None => self.height_value((-self.noise_base.0, -self.noise_base.1), -x, -y),
+        };
+
+        1.0 - (r_dis + 0.3 + 0.4 * noise)
     }
 
     fn get_heights(&self, r_dis: f64, x: f64, y: f64) -> (f64, f64) {
-        let globe_noise = self.height_noise.get_value(x, y) * (1.0 - (r_dis + 0.3 + 0.4 * self.height_noise.get_value(-x, -y)));
-        let height = 9000.0 * globe_noise - 1000.0;
+        let globe_noise = self.height_value(self.noise_base, x, y) * self.falloff(r_dis, x, y);
+        let mut height = 9000.0 * globe_noise - 1000.0;
+
+        if let Some(mask) = &self.config.land_mask {
+            let (x_dis, y_dis) = self.map_position(x, y);
+            height = mask.constrain(height, x_dis, y_dis);
+        }
 
         let mut absl_height = height;
         if absl_height < 0.0 {
@@ -282,34 +824,124 @@ impl MapGen {
         (height, absl_height)
     }
 
-    fn get_partial_temp(&self, absl_height: f64, y_dis: f64, lapse_rate: f64, x: f64, y: f64) -> f64 {
-        let noisy_temp = 20.0 * self.temperature_noise.get_value(x, y) + 5.0;
-        let temperature = -40.0 * y_dis + noisy_temp - (lapse_rate * absl_height);
+    // Latitude and distance from the map center of a noise space point, both normalized to the map axis.
+    // The noise offset is taken back out so it moves only the noise, not the falloff
+    fn falloff_distances(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x_dis, y_dis) = self.map_position(x, y);
+        let r_dis = ((y_dis * y_dis) + (x_dis * x_dis)).sqrt() / (2.0_f64).sqrt();
 
-        temperature
+        (y_dis, r_dis)
     }
 
-    fn get_percip_temp(&self, absl_height: f64, y_dis: f64, partial_temp: f64, x: f64, y: f64) -> (f64, f64) {
-        
-        let water_dist = 1.0 - (5.5 * y_dis.abs());
-        
-        let mut temp_clamp = partial_temp;
-        if temp_clamp < 0.0 {
-            temp_clamp = 0.0;
+    // Noise space point relative to the map, each axis -1 to 1 across it at zoom 1
+    fn map_position(&self, x: f64, y: f64) -> (f64, f64) {
+        let axis_len = self.config.noise_axis_len();
+        let x = x + self.noise_base.0 - axis_len * self.noise_offset.0;
+        let y = y + self.noise_base.1 - axis_len * self.noise_offset.1;
+
+        (x / axis_len / self.zoom, y / axis_len / self.zoom)
+    }
+
+    // Height at noise space coordinates, before erosion
+    fn height_at(&self, x: f64, y: f64) -> f64 {
+        let (_, r_dis) = self.falloff_distances(x, y);
+
+        self.get_heights(r_dis, x, y).0
+    }
+
+    // Share of the humidity left after the air crossed the highest ground upwind of the noise space
+    // point, 1 in calm air or with nothing higher upwind
+    fn rain_shadow(&self, height: f64, x: f64, y: f64) -> f64 {
+        let wind = self.config.wind;
+        if wind.strength == 0.0 {
+            return 1.0;
         }
-        if temp_clamp > 40.0 {
-            temp_clamp = 0.0;
+
+        let step = self.config.noise_units_per_tile() / self.zoom;
+        let (upwind_x, upwind_y) = wind.upwind();
+        let barrier = (1..=RAIN_SHADOW_REACH)
+            .map(|tiles| self.height_at(x + upwind_x * step * tiles as f64, y + upwind_y * step * tiles as f64))
+            .fold(height, f64::max) - height;
+
+        1.0 - wind.strength * (barrier / RAIN_SHADOW_BARRIER).min(1.0)
+    }
+
+    // Steepest height change [m per tile] around the noise space point, by central differences
+    fn get_slope(&self, x: f64, y: f64) -> f64 {
+        let step = self.config.noise_units_per_tile() / self.zoom;
+        let dx = (self.height_at(x + step, y) - self.height_at(x - step, y)) / 2.0;
+        let dy = (self.height_at(x, y + step) - self.height_at(x, y - step)) / 2.0;
+
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    // Land within the beach width of its slope, see MapConfig::within_beach
+    fn is_beach(&self, height: f64, x: f64, y: f64) -> bool {
+        // slope * beach_width(slope) peaks at a quarter of max_beach_width * beach_max_slope,
+        // so higher land can skip the slope samples
+        if height <= 0.0 || height >= self.config.max_beach_width * self.config.beach_max_slope / 4.0 {
+            return false;
         }
 
-        let mut evap_prob = 1.0 - ((temp_clamp - 20.0) / 20.0).abs();
-        if evap_prob < 0.0 {
-            evap_prob = 0.0;
+        self.config.within_beach(height, self.get_slope(x, y))
+    }
+
+    // Low, warm and wet land with ocean within mangrove_coast_distance tiles along an axis. Checked ahead
+    // of pick_biome, whose marsh would otherwise take these tiles
+    fn is_mangrove(&self, height: f64, temperature: f64, percipitation: f64, x: f64, y: f64) -> bool {
+        if self.config.biome_model == BiomeModel::Simple || height <= 0.0 || height > self.config.mangrove_max_height
+            || temperature < self.config.mangrove_min_temperature || percipitation < self.config.mangrove_min_percipitation {
+            return false;
         }
 
-        let avg_lapse_rate = ((WET_ADB_LAPSE_RATE * evap_prob) + (DRY_ADB_LAPSE_RATE * (1.0 - evap_prob))) / 2.0;
+        self.coast_distance(x, y, self.config.mangrove_coast_distance).is_some()
+    }
+
+    // Tiles along an axis from a noise space point to the nearest ocean, None if there is none within reach
+    fn coast_distance(&self, x: f64, y: f64, reach: u32) -> Option<u32> {
+        let step = self.config.noise_units_per_tile() / self.zoom;
+        (1..=reach).find(|&tiles| {
+            let offset = step * tiles as f64;
+            [(offset, 0.0), (-offset, 0.0), (0.0, offset), (0.0, -offset)].iter()
+                .any(|(dx, dy)| self.is_ocean_probe(x + dx, y + dy))
+        })
+    }
+
+    // coast_distance from a world position
+    #[cfg(test)]
+    pub fn coast_distance_at_world(&self, x: f64, y: f64, reach: u32) -> Option<u32> {
+        let (x, y) = self.noise_position(x, y);
+        self.coast_distance(x, y, reach)
+    }
+
+    // Whether a coast search finds ocean at a noise space point, which past the map edge is up to edge_policy
+    fn is_ocean_probe(&self, x: f64, y: f64) -> bool {
+        let edge = self.config.noise_axis_len() / self.zoom;
+        if x.abs() <= edge && y.abs() <= edge {
+            return self.height_at(x, y) <= 0.0;
+        }
+
+        match self.config.edge_policy {
+            EdgePolicy::Ocean => true,
+            EdgePolicy::Land => false,
+            EdgePolicy::Mirror => {
+                let mirror = |value: f64| if value.abs() > edge { value.signum() * (2.0 * edge - value.abs()) } else { value };
+                self.height_at(mirror(x), mirror(y)) <= 0.0
+            }
+        }
+    }
+
+    fn get_partial_temp(&self, absl_height: f64, y_dis: f64, lapse_rate: f64, x: f64, y: f64) -> f64 {
+        let noisy_temp = 20.0 * self.temperature_noise.get_value(self.noise_base, x, y) + 5.0;
+        let temperature = -40.0 * y_dis + noisy_temp - (lapse_rate * self.config.lapse_height(absl_height));
+
+        temperature
+    }
+
+    // Weighted sum of the moisture from nearby warm water, from the equatorial seas and from the noise
+    fn humidity(&self, absl_height: f64, y_dis: f64, true_temp: f64, x: f64, y: f64) -> f64 {
+        let water_dist = self.config.water_dist(y_dis);
 
-        let true_temp = self.get_partial_temp(absl_height, y_dis, avg_lapse_rate, x, y);
-        
         let mut water_map = absl_height;
         if water_map == 0.0 {
             water_map = 1.0;
@@ -327,459 +959,4059 @@ impl MapGen {
             }
         }
 
-        let humidity = (0.40 * water_map) + (0.30 * water_dist) + (0.30 * self.humidity_noise.get_value(x, y));
-
-        let mut percipitation = 16000.0 * humidity;
-        let percipitation_cap = 500.0 * true_temp - 80.0;
-        if percipitation > percipitation_cap {
-            percipitation = percipitation_cap;
-        }
-
-        (percipitation, true_temp)
+        let (water_weight, distance_weight, noise_weight) = self.config.humidity_weights;
+        (water_weight * water_map) + (distance_weight * water_dist) + (noise_weight * self.humidity_noise.get_value(self.noise_base, x, y))
     }
 
-    fn get_tile(&self, x: f64, y: f64) -> Tile {
+    fn get_percip_temp(&self, absl_height: f64, y_dis: f64, partial_temp: f64, x: f64, y: f64) -> (f64, f64) {
+        let mut temp_clamp = partial_temp;
+        if temp_clamp < 0.0 {
+            temp_clamp = 0.0;
+        }
+        if temp_clamp > 40.0 {
+            temp_clamp = 0.0;
+        }
 
-        let map_axis_len = self.tile_size * self.tile_scale * self.map_size as f64 / 2.0;
+        let mut evap_prob = 1.0 - ((temp_clamp - 20.0) / 20.0).abs();
+        if evap_prob < 0.0 {
+            evap_prob = 0.0;
+        }
+
+        let avg_lapse_rate = ((WET_ADB_LAPSE_RATE * evap_prob) + (DRY_ADB_LAPSE_RATE * (1.0 - evap_prob))) / 2.0;
+
+        let true_temp = self.get_partial_temp(absl_height, y_dis, avg_lapse_rate, x, y);
+        let humidity = self.humidity(absl_height, y_dis, true_temp, x, y);
+
+        let percipitation = self.config.percipitation_scale.percipitation(humidity) * self.rain_shadow(absl_height, x, y);
+        let percipitation_cap = self.config.percipitation_cap(true_temp, absl_height);
+
+        (soft_cap(percipitation, percipitation_cap, self.config.percipitation_cap_hardness), true_temp)
+    }
+
+    fn tile_position(&self, x: i32, y: i32) -> (f64, f64) {
+        self.config.tile_to_world(x, y)
+    }
+
+    // Point of the map under the tile at grid coordinates, see map_position
+    fn tile_map_position(&self, x: i32, y: i32) -> (f64, f64) {
+        let (tile_x, tile_y) = self.tile_position(x, y);
+        let (noise_x, noise_y) = self.noise_position(tile_x, tile_y);
+        self.map_position(noise_x, noise_y)
+    }
+
+    // Cell of the map under the tile at grid coordinates, one tile wide at zoom 1. Cells stay with the
+    // terrain as the view pans and zooms, so biomes painted by cell stay where they were painted
+    pub fn map_cell(&self, x: i32, y: i32) -> (i32, i32) {
+        let (x_dis, y_dis) = self.tile_map_position(x, y);
+        let cells = self.config.tiles_per_axis() as f64 / 2.0;
+        ((x_dis * cells).round() as i32, (y_dis * cells).round() as i32)
+    }
+
+    // Name of a landmass, seeded by the map cell of its highest tile so it keeps its name as the view moves
+    fn landmass_name(&self, landmass: &Landmass) -> String {
+        let (x, y) = self.map_cell(landmass.peak.0, landmass.peak.1);
+        landmass_name(self.seed, x, y)
+    }
+
+    // Whether the land mask wants land at tile coordinates, None without a mask or beyond its edges
+    #[cfg(test)]
+    pub fn masked_land(&self, x: i32, y: i32) -> Option<bool> {
+        let (x_dis, y_dis) = self.tile_map_position(x, y);
+        self.config.land_mask.as_ref()?.land_at(x_dis, y_dis)
+    }
+
+    pub fn generate_grid(&self) -> MapGrid {
+        let mut grid = MapGrid::default();
+        self.generate_grid_into(&mut grid);
+        grid
+    }
+
+    // Regenerates into an existing grid, reusing its tile and sample buffers so repeated generation,
+    // as in a seed search, doesn't allocate them again
+    pub fn generate_grid_into(&self, grid: &mut MapGrid) {
+        self.generate_grid_into_at(grid, self.config.tile_stride(), &mut StageTimer::off()).expect(UNBUDGETED);
+    }
+
+    // generate_grid within the config's generation_budget, giving up on the grid once it runs out
+    pub fn try_generate_grid(&self) -> Result<MapGrid> {
+        let mut grid = MapGrid::default();
+        let mut timer = self.budgeted(StageTimer::off());
+        self.generate_grid_into_at(&mut grid, self.config.tile_stride(), &mut timer)?;
+        Ok(grid)
+    }
+
+    // Grid of every stride-th tile, whatever the tile entity budget asks for
+    pub fn generate_grid_at(&self, stride: i32) -> MapGrid {
+        let mut grid = MapGrid::default();
+        self.generate_grid_into_at(&mut grid, stride, &mut StageTimer::off()).expect(UNBUDGETED);
+        grid
+    }
+
+    // try_generate_grid with the time spent in each stage
+    pub fn generate_grid_profiled(&self) -> Result<(MapGrid, GenerationProfile)> {
+        let mut grid = MapGrid::default();
+        let mut timer = self.budgeted(StageTimer::on());
+        self.generate_grid_into_at(&mut grid, self.config.tile_stride(), &mut timer)?;
+
+        let tiles = grid.tiles.len();
+        Ok((grid, timer.finish(tiles)))
+    }
+
+    fn generate_grid_into_at(&self, grid: &mut MapGrid, stride: i32, timer: &mut StageTimer) -> Result<()> {
+        let (stride, half_size, heights) = self.eroded_heights(stride, timer)?;
+        let width = (half_size * 2 + 1) as usize;
+
+        grid.half_size = half_size;
+        grid.stride = stride;
+        grid.tiles.clear();
+        grid.samples.clear();
+
+        for (index, &height) in heights.iter().enumerate() {
+            if index % width == 0 {
+                timer.check_budget(Stage::Climate)?;
+            }
+            let (tile, sample) = self.grid_climate_tile(stride, half_size, index, height, timer);
+            grid.tiles.push(tile);
+            grid.samples.push(sample);
+        }
+
+        timer.check_budget(Stage::Filter)?;
+
+        let mut biomes: Vec<Biome> = grid.samples.iter().map(|sample| sample.biome).collect();
+        majority_filter(&mut biomes, width, self.config.biome_filter_radius, self.config.biome_filter_passes);
+
+        for (index, biome) in biomes.into_iter().enumerate() {
+            let sample = &mut grid.samples[index];
+            if sample.biome != biome {
+                sample.biome = biome;
+                grid.tiles[index] = self.colored_tile(biome, sample.height, sample.temperature, sample.percipitation);
+            }
+        }
+        timer.lap(Stage::Filter);
+
+        Ok(())
+    }
+
+    // Calls spawn with the grid coordinates, tile, sample and movement cost of each tile as it is generated,
+    // keeping only the heights. Matches generate_grid and cost_grid without the biome filter. Stops with
+    // the tiles spawned so far once the generation_budget runs out
+    pub fn stream_tiles(&self, mut spawn: impl FnMut((i32, i32), Tile, TileSample, f32)) -> Result<()> {
+        let mut timer = self.budgeted(StageTimer::off());
+        let (stride, half_size, heights) = self.eroded_heights(self.config.tile_stride(), &mut timer)?;
+        let width = (half_size * 2 + 1) as usize;
+
+        for (index, &height) in heights.iter().enumerate() {
+            if index % width == 0 {
+                timer.check_budget(Stage::Climate)?;
+            }
+            let (tile, sample) = self.grid_climate_tile(stride, half_size, index, height, &mut timer);
+
+            let (column, row) = (index % width, index / width);
+            let neighbours = [
+                (column > 0).then(|| index - 1),
+                (column + 1 < width).then(|| index + 1),
+                (row > 0).then(|| index - width),
+                (index + width < heights.len()).then(|| index + width),
+            ];
+            let slope = neighbours.into_iter().flatten()
+                .map(|neighbour| (heights[neighbour] - height).abs() / stride as f64)
+                .fold(0.0, f64::max);
+
+            let cost = self.movement_cost(sample.biome, slope);
+            spawn(((column as i32 - half_size) * stride, (row as i32 - half_size) * stride), tile, sample, cost);
+        }
+
+        Ok(())
+    }
+
+    // Grid stride and half size with the height of every grid tile after erosion, row by row from the south edge
+    fn eroded_heights(&self, stride: i32, timer: &mut StageTimer) -> Result<(i32, i32, Vec<f64>)> {
+        let half_size = self.config.map_size as i32 / 2 / stride;
+        let mut heights = Vec::new();
+
+        for y in -half_size..(half_size + 1) {
+            timer.check_budget(Stage::Height)?;
+            for x in -half_size..(half_size + 1) {
+                let (noise_x, noise_y) = self.grid_noise_position(stride, x, y);
+                heights.push(self.height_at(noise_x, noise_y));
+            }
+        }
+        timer.lap(Stage::Height);
+
+        let width = (half_size * 2 + 1) as usize;
+        // One pass at a time, so a budget can stop between them
+        for _ in 0..self.config.erosion_iterations() {
+            timer.check_budget(Stage::Erosion)?;
+            erode(&mut heights, width, 1, self.config.erosion_talus * stride as f64);
+        }
+
+        // Erosion moves material across the shore, so the mask is enforced again after it
+        if let Some(mask) = &self.config.land_mask {
+            for (index, height) in heights.iter_mut().enumerate() {
+                let (x, y) = ((index % width) as i32 - half_size, (index / width) as i32 - half_size);
+                let (noise_x, noise_y) = self.grid_noise_position(stride, x, y);
+                let (x_dis, y_dis) = self.map_position(noise_x, noise_y);
+                *height = mask.clamp(*height, x_dis, y_dis);
+            }
+        }
+        timer.lap(Stage::Erosion);
+
+        Ok((stride, half_size, heights))
+    }
+
+    fn grid_noise_position(&self, stride: i32, x: i32, y: i32) -> (f64, f64) {
+        let (tile_x, tile_y) = self.tile_position(x * stride, y * stride);
+        self.noise_position(tile_x, tile_y)
+    }
+
+    // Climate tile of the index-th tile of eroded_heights
+    fn grid_climate_tile(&self, stride: i32, half_size: i32, index: usize, height: f64, timer: &mut StageTimer) -> (Tile, TileSample) {
+        let width = half_size * 2 + 1;
+        let (x, y) = (index as i32 % width - half_size, index as i32 / width - half_size);
+        let (noise_x, noise_y) = self.grid_noise_position(stride, x, y);
+
+        self.timed_climate_tile(height, noise_x, noise_y, timer)
+    }
+
+    // Each grid tile recolored from factor x factor climate samples spread evenly across it, combined by
+    // mode. A sample's height is the tile's eroded height plus the raw height difference from the tile's
+    // center, so erosion carries over and a single sample at the center classifies as the tile itself.
+    // The biome filter isn't rerun on the samples
+    pub fn subsampled_tiles(&self, grid: &MapGrid, factor: u32, mode: SubsampleMode) -> Vec<Tile> {
+        let factor = factor.max(1);
+        let span = self.config.noise_units_per_tile() * grid.stride as f64 / self.zoom;
+
+        (0..grid.samples.len()).map(|index| {
+            let (x, y) = grid.coords(index);
+            let (tile_x, tile_y) = self.tile_position(x, y);
+            let (center_x, center_y) = self.noise_position(tile_x, tile_y);
+            let center_height = self.height_at(center_x, center_y);
+
+            let mut samples = Vec::new();
+            for j in 0..factor {
+                for i in 0..factor {
+                    let offset = |n: u32| ((n as f64 + 0.5) / factor as f64 - 0.5) * span;
+                    let (noise_x, noise_y) = (center_x + offset(i), center_y + offset(j));
+                    let mut height = grid.samples[index].height + self.height_at(noise_x, noise_y) - center_height;
+                    if let Some(mask) = &self.config.land_mask {
+                        let (x_dis, y_dis) = self.map_position(noise_x, noise_y);
+                        height = mask.clamp(height, x_dis, y_dis);
+                    }
+                    samples.push(self.get_climate_tile(height, noise_x, noise_y));
+                }
+            }
+
+            let mut counts: Vec<(Biome, u32, Tile)> = Vec::new();
+            for &(tile, sample) in &samples {
+                match counts.iter_mut().find(|(biome, _, _)| *biome == sample.biome) {
+                    Some((_, count, _)) => *count += 1,
+                    None => counts.push((sample.biome, 1, tile)),
+                }
+            }
+            // Ties go to the biome sampled first
+            let majority = counts.iter().fold(counts[0], |best, &entry| if entry.1 > best.1 { entry } else { best }).2;
+
+            match mode {
+                SubsampleMode::Majority => majority,
+                SubsampleMode::Blend => {
+                    let sum = samples.iter().fold([0.0; 4], |sum, (tile, _)| {
+                        let [r, g, b, a] = tile.color.as_rgba_f32();
+                        [sum[0] + r, sum[1] + g, sum[2] + b, sum[3] + a]
+                    });
+                    let count = samples.len() as f32;
+                    Tile { index: majority.index, color: Color::rgba(sum[0] / count, sum[1] / count, sum[2] / count, sum[3] / count) }
+                }
+            }
+        }).collect()
+    }
+
+    // Share of the map above sea level, estimated on a coarse lattice before erosion
+    pub fn land_fraction(&self) -> f64 {
+        let half_size = self.config.map_size as f64 / 2.0;
+        let mut land = 0;
+
+        for j in 0..LAND_FRACTION_SAMPLES {
+            for i in 0..LAND_FRACTION_SAMPLES {
+                let step = |n: i32| ((n as f64 + 0.5) / LAND_FRACTION_SAMPLES as f64 * 2.0 - 1.0) * half_size;
+                let (tile_x, tile_y) = self.tile_position(step(i).round() as i32, step(j).round() as i32);
+                let (noise_x, noise_y) = self.noise_position(tile_x, tile_y);
+
+                if self.height_at(noise_x, noise_y) > 0.0 {
+                    land += 1;
+                }
+            }
+        }
+
+        land as f64 / (LAND_FRACTION_SAMPLES * LAND_FRACTION_SAMPLES) as f64
+    }
+
+    // Noise space coordinates of a world position relative to noise_base, which holds the
+    // noise_axis_len * zoom * shift and noise offset terms. Tile by tile, so tile_scale drops out
+    fn noise_position(&self, x: f64, y: f64) -> (f64, f64) {
+        let noise_per_world = self.config.noise_units_per_tile() / self.config.world_units_per_tile();
+
+        (x * noise_per_world / self.zoom, y * noise_per_world / self.zoom)
+    }
+
+    // Movement cost of every grid tile, its biome cost raised by the steepest height change to a neighbour
+    pub fn cost_grid(&self, grid: &MapGrid) -> Vec<f32> {
+        grid.slopes().into_iter().enumerate()
+            .map(|(index, slope)| self.movement_cost(grid.samples[index].biome, slope))
+            .collect()
+    }
+
+    // Biome cost raised by the steepest height change [m per tile] to a neighbour
+    fn movement_cost(&self, biome: Biome, slope: f64) -> f32 {
+        self.config.movement_cost(biome) * (1.0 + slope as f32 * self.config.slope_movement_cost)
+    }
+
+    // Cheapest route between two grid tiles under the cost grid, None if there is none
+    pub fn find_path(&self, grid: &MapGrid, start: (i32, i32), goal: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        find_path(grid, &self.cost_grid(grid), start, goal)
+    }
+
+    // Every land tile of the grid as pick_spawn judges it, in grid order
+    pub fn spawn_sites(&self, grid: &MapGrid) -> Vec<SpawnSite> {
+        let slopes = grid.slopes();
+        let coast_distances = grid.coast_distances();
+
+        (0..grid.samples.len())
+            .filter(|&index| grid.samples[index].height > 0.0)
+            .map(|index| {
+                let (x, y) = grid.coords(index);
+                SpawnSite { x, y, sample: grid.samples[index], coast_distance: coast_distances[index], slope: slopes[index] }
+            })
+            .collect()
+    }
+
+    // A random land tile of the generated grid satisfying predicate, see pick_spawn_on
+    pub fn pick_spawn(&self, rng: &mut impl Rng, predicate: impl Fn(&SpawnSite) -> bool) -> Option<(i32, i32)> {
+        self.pick_spawn_on(&self.generate_grid(), rng, predicate)
+    }
+
+    // A random land tile of grid satisfying predicate, each drawn with a chance proportional to its biome's
+    // spawn weight. None if no tile with a positive weight qualifies
+    pub fn pick_spawn_on(&self, grid: &MapGrid, rng: &mut impl Rng, predicate: impl Fn(&SpawnSite) -> bool) -> Option<(i32, i32)> {
+        let candidates: Vec<(SpawnSite, f64)> = self.spawn_sites(grid).into_iter()
+            .filter(|site| predicate(site))
+            .map(|site| (site, self.config.spawn_weight(site.sample.biome) as f64))
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let total: f64 = candidates.iter().map(|(_, weight)| weight).sum();
+        let mut target = rng.gen_range(0.0..total);
+        for (site, weight) in &candidates {
+            if target < *weight {
+                return Some((site.x, site.y));
+            }
+            target -= weight;
+        }
+
+        // Rounding can leave the target just past the last weight
+        candidates.last().map(|(site, _)| (site.x, site.y))
+    }
+
+    // Biome at the view center, world position (0, 0), as get_tile_at_world samples it
+    pub fn center_biome(&self) -> Biome {
+        self.get_tile_at_world(0.0, 0.0).1.biome
+    }
+
+    // Tile at a world position, sampled on its own and so without erosion. With the tile cache on, the
+    // position snaps to TILE_CACHE_QUANTUM and repeated lookups reuse the first one's tile
+    pub fn get_tile_at_world(&self, x: f64, y: f64) -> (Tile, TileSample) {
+        let cache = match &self.tile_cache {
+            Some(cache) => cache,
+            None => return self.sample_tile_at_world(x, y),
+        };
+
+        let key = TileCache::quantize(x, y);
+        if let Some(cached) = cache.lock().ok().and_then(|mut cache| cache.get(key)) {
+            return cached;
+        }
+
+        let (x, y) = TileCache::position(key);
+        let (tile, sample) = self.sample_tile_at_world(x, y);
+        if let Ok(mut cache) = cache.lock() {
+            cache.insert(key, tile, sample);
+        }
+
+        (tile, sample)
+    }
+
+    fn sample_tile_at_world(&self, x: f64, y: f64) -> (Tile, TileSample) {
+        let (x, y) = self.noise_position(x, y);
+
+        self.get_climate_tile(self.height_at(x, y), x, y)
+    }
+
+    // Tiles held by the tile cache, 0 without one
+    #[cfg(test)]
+    pub fn cached_tiles(&self) -> usize {
+        self.tile_cache.as_ref()
+            .and_then(|cache| cache.lock().ok().map(|cache| cache.tile_count()))
+            .unwrap_or(0)
+    }
+
+    // Every biome some land height, temperature and percipitation under the cap can classify as. Scans
+    // the generator's full ranges on a lattice that also hits the alpine and marsh height limits
+    pub fn reachable_biomes(&self) -> HashSet<Biome> {
+        let mut reachable = HashSet::from([Biome::Ocean]);
+        if self.config.max_beach_width > 0.0 {
+            reachable.insert(Biome::Beach);
+        }
+
+        // Any coast can be low enough, so mangroves only need warm air able to hold the rain
+        let mangrove_cap = self.config.percipitation_cap(TEMPERATURE_RANGE.1, self.config.mangrove_max_height);
+        if self.config.biome_model == BiomeModel::Holdridge && self.config.mangrove_coast_distance > 0
+            && self.config.mangrove_min_temperature <= TEMPERATURE_RANGE.1
+            && self.config.mangrove_min_percipitation <= self.classification_moisture(mangrove_cap.min(PERCIPITATION_RANGE.1)) {
+            reachable.insert(Biome::Mangrove);
+        }
+
+        if self.config.biome_model == BiomeModel::Holdridge && self.config.volcano_density > 0.0
+            && self.config.volcano_min_height <= HEIGHT_RANGE.1 {
+            reachable.insert(Biome::Volcanic);
+        }
+
+        let mut heights: Vec<f64> = (1..=64).map(|step| HEIGHT_RANGE.1 * step as f64 / 64.0).collect();
+        heights.extend([1.0, self.config.marsh_max_height, self.config.alpine_min_height]);
+
+        for height in heights.into_iter().filter(|height| *height > 0.0) {
+            let mut temperature = TEMPERATURE_RANGE.0;
+            while temperature <= TEMPERATURE_RANGE.1 {
+                let cap = self.config.percipitation_cap(temperature, height);
+                // Too cold for any rain the cap lies below the range, and percipitation sits at it
+                let mut percipitation = PERCIPITATION_RANGE.0.min(cap);
+
+                while percipitation <= cap.min(PERCIPITATION_RANGE.1) {
+                    reachable.insert(self.pick_biome(height, temperature, self.classification_moisture(percipitation)));
+                    percipitation += 100.0;
+                }
+                temperature += 1.0;
+            }
+        }
+
+        reachable
+    }
+
+    // Biome counts of the tiles centered within radius of center, both in world units.
+    // Tiles past the map edge count as ocean, as the falloff would make them
+    pub fn biomes_in_radius(&self, center: (f64, f64), radius: f64) -> HashMap<Biome, u32> {
+        self.biomes_in_radius_every(center, radius, 1)
+    }
+
+    // biomes_in_radius over a region too large to sample whole, counting every VIEW_SAMPLES-th share of
+    // it along each axis
+    pub fn view_biomes(&self, center: (f64, f64), radius: f64) -> HashMap<Biome, u32> {
+        let reach = (radius / self.config.world_units_per_tile()).ceil() as i32;
+
+        self.biomes_in_radius_every(center, radius, (2 * reach / VIEW_SAMPLES).max(1) as usize)
+    }
+
+    // biomes_in_radius counting every step-th tile along each axis
+    fn biomes_in_radius_every(&self, center: (f64, f64), radius: f64, step: usize) -> HashMap<Biome, u32> {
+        let half_size = self.config.map_size as i32 / 2;
+        let (center_x, center_y) = self.config.world_to_tile(center.0, center.1);
+        let reach = (radius / self.config.world_units_per_tile()).ceil() as i32;
+        let mut counts = HashMap::new();
+
+        for y in ((center_y - reach)..=(center_y + reach)).step_by(step) {
+            for x in ((center_x - reach)..=(center_x + reach)).step_by(step) {
+                let (world_x, world_y) = self.config.tile_to_world(x, y);
+                if (world_x - center.0).hypot(world_y - center.1) > radius {
+                    continue;
+                }
+
+                let biome = if x.abs() > half_size || y.abs() > half_size {
+                    Biome::Ocean
+                } else {
+                    self.get_tile_at_world(world_x, world_y).1.biome
+                };
+
+                *counts.entry(biome).or_insert(0) += 1;
+            }
+        }
+
+        counts
+    }
+
+    // Climate tiles computed since the generator was made
+    #[cfg(test)]
+    pub fn climate_samples(&self) -> usize {
+        self.climate_samples.load(Ordering::Relaxed)
+    }
+
+    // Center of the volcano in a cell, if it has one: each cell of the seed's hotspot lattice holds one
+    // with chance volcano_density, somewhere far enough inside for the whole cone to fit. Cells are
+    // numbered in absolute noise space, so volcanoes stay put as the view pans
+    pub fn volcano_in_cell(&self, cell: (i64, i64)) -> Option<(f64, f64)> {
+        let hash = tile_hash(self.seed, cell.0, cell.1, VOLCANO_SALT);
+
+        let share = |bits: u64| (bits & 0xffff) as f64 / 65536.0;
+        if share(hash) >= self.config.volcano_density {
+            return None;
+        }
+
+        let inside = |bits: u64| VOLCANO_RADIUS + (1.0 - 2.0 * VOLCANO_RADIUS) * share(bits);
+        Some((
+            (cell.0 as f64 + inside(hash >> 16)) * self.volcano_cell,
+            (cell.1 as f64 + inside(hash >> 32)) * self.volcano_cell,
+        ))
+    }
+
+    // Distance from a point at least volcano_min_height high to the volcano whose cone covers it, in
+    // relative noise space. The simple model has no volcanoes
+    fn volcano_distance(&self, height: f64, x: f64, y: f64) -> Option<f64> {
+        if self.config.biome_model == BiomeModel::Simple || height < self.config.volcano_min_height
+            || self.config.volcano_density <= 0.0 {
+            return None;
+        }
+
+        let point = (self.noise_base.0 + x, self.noise_base.1 + y);
+        let cell = ((point.0 / self.volcano_cell).floor() as i64, (point.1 / self.volcano_cell).floor() as i64);
+        let center = self.volcano_in_cell(cell)?;
+        let distance = ((point.0 - center.0).powi(2) + (point.1 - center.1).powi(2)).sqrt();
+
+        (distance <= VOLCANO_RADIUS * self.volcano_cell).then_some(distance)
+    }
+
+    // Forest too high for trees: above treeline_height, raised by treeline_warmth_rise per degree of
+    // warmth, with the line pushed up or down by up to treeline_raggedness by fine noise
+    fn above_treeline(&self, biome: Biome, height: f64, temperature: f64, x: f64, y: f64) -> bool {
+        if biome.above_treeline() == biome {
+            return false;
+        }
+
+        let treeline = self.config.treeline_height + self.config.treeline_warmth_rise * temperature;
+        let raggedness = self.config.treeline_raggedness;
+        if (height - treeline).abs() > raggedness {
+            return height > treeline;
+        }
+
+        let offset = (2.0 * self.treeline_noise.get_value(self.noise_base, x, y) - 1.0) * raggedness;
+        height > treeline + offset
+    }
+
+    // Climate, biome and tile of a noise space point given its (possibly eroded) height
+    fn get_climate_tile(&self, height: f64, x: f64, y: f64) -> (Tile, TileSample) {
+        self.timed_climate_tile(height, x, y, &mut StageTimer::off())
+    }
+
+    fn timed_climate_tile(&self, height: f64, x: f64, y: f64, timer: &mut StageTimer) -> (Tile, TileSample) {
+        self.climate_samples.fetch_add(1, Ordering::Relaxed);
+
+        let (y_dis, r_dis) = self.falloff_distances(x, y);
+        let absl_height = height.max(0.0);
+
+        let partial_temp = self.get_partial_temp(absl_height, y_dis, DRY_ADB_LAPSE_RATE * 0.5, x, y);
+        let (percipitation, temperature) = self.get_percip_temp(absl_height, y_dis, partial_temp, x, y);
+        let falloff = self.falloff(r_dis, x, y);
+        timer.lap(Stage::Climate);
+
+        let volcano_distance = self.volcano_distance(height, x, y);
+        let moisture = self.classification_moisture(percipitation);
+        let biome: Biome = if volcano_distance.is_some() {
+            Biome::Volcanic
+        } else if self.is_beach(height, x, y) {
+            Biome::Beach
+        } else if self.is_mangrove(height, temperature, moisture, x, y) {
+            Biome::Mangrove
+        } else {
+            self.pick_biome(height, temperature, moisture)
+        };
+
+        let biome = if self.above_treeline(biome, height, temperature, x, y) {
+            biome.above_treeline()
+        } else {
+            biome
+        };
+        timer.lap(Stage::Classification);
+
+        let mut tile = self.colored_tile(biome, height, temperature, percipitation);
+        if self.config.coloring_mode == ColoringMode::Biome
+            && volcano_distance.is_some_and(|distance| distance <= CRATER_RADIUS * self.volcano_cell) {
+            tile.color = LAVA_COLOR;
+        }
+        timer.lap(Stage::Color);
+
+        let sample = TileSample {
+            height,
+            temperature,
+            percipitation,
+            falloff,
+            biome,
+        };
+
+        (tile, sample)
+    }
+}
+
+// Share of the height difference beyond the talus slope moved to each lower neighbour per iteration,
+// small enough that a cell never gives away more than it holds above its neighbours
+const EROSION_RATE: f64 = 0.1;
+
+// Thermal erosion over a width-wide grid of heights, slumping slopes steeper than talus [m per cell]
+fn erode(heights: &mut [f64], width: usize, iterations: u32, talus: f64) {
+    let rows = heights.len() / width;
+    let mut deltas = vec![0.0; heights.len()];
+
+    for _ in 0..iterations {
+        deltas.iter_mut().for_each(|delta| *delta = 0.0);
+
+        for row in 0..rows {
+            for column in 0..width {
+                let index = row * width + column;
+                let neighbours = [
+                    (column > 0).then(|| index - 1),
+                    (column + 1 < width).then(|| index + 1),
+                    (row > 0).then(|| index - width),
+                    (row + 1 < rows).then(|| index + width),
+                ];
+
+                for neighbour in neighbours.into_iter().flatten() {
+                    let excess = heights[index] - heights[neighbour] - talus;
+                    if excess > 0.0 {
+                        deltas[index] -= excess * EROSION_RATE;
+                        deltas[neighbour] += excess * EROSION_RATE;
+                    }
+                }
+            }
+        }
+
+        heights.iter_mut().zip(deltas.iter()).for_each(|(height, delta)| *height += delta);
+    }
+}
+
+// Flips and quarter turns a tile's art is drawn with, so a small atlas doesn't repeat visibly
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TileOrientation {
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub quarter_turns: u8,
+}
+
+// Holds value under cap. Within CAP_ROLL_OFF / hardness of the cap it bends over along a tanh, so
+// values past the cap keep their order instead of all landing on it; an infinite hardness clamps
+pub fn soft_cap(value: f64, cap: f64, hardness: f64) -> f64 {
+    let roll_off = CAP_ROLL_OFF / hardness;
+    let knee = cap - roll_off;
+
+    if roll_off <= 0.0 || value <= knee {
+        value.min(cap)
+    } else {
+        knee + roll_off * ((value - knee) / roll_off).tanh()
+    }
+}
+
+// Salts of the features drawing on tile_hash, one each so their randomness is uncorrelated. A new
+// feature takes the next unused value, and changing one reshuffles that feature on every map
+pub const ORIENTATION_SALT: u64 = 1;
+pub const VOLCANO_SALT: u64 = 2;
+pub const NAME_SALT: u64 = 3;
+
+// SplitMix64's finalizer, spreading every input bit over the whole output
+fn mix_bits(mut hash: u64) -> u64 {
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
+// Stable random bits for a seed, a position and a feature's salt. Each input is mixed in on its own,
+// so neighbouring positions, seeds and salts all give unrelated hashes
+pub fn tile_hash(seed: u32, x: i64, y: i64, salt: u64) -> u64 {
+    let hash = mix_bits(mix_bits(salt.wrapping_add(0x9e3779b97f4a7c15)) ^ seed as u64);
+    let hash = mix_bits(hash ^ x as u64);
+    mix_bits(hash ^ y as u64)
+}
+
+// Hashes the seed and grid position, so the same tile is always oriented the same way
+pub fn tile_orientation(seed: u32, x: i32, y: i32) -> TileOrientation {
+    let hash = tile_hash(seed, x as i64, y as i64, ORIENTATION_SALT);
+
+    TileOrientation {
+        flip_x: hash & 1 == 1,
+        flip_y: hash & 2 == 2,
+        quarter_turns: ((hash >> 2) & 3) as u8,
+    }
+}
+
+// The tile's orientation with tile_flips on, upright when off or when the biome's art is directional
+fn configured_orientation(config: &MapConfig, seed: u32, x: i32, y: i32, biome: Biome) -> TileOrientation {
+    if config.tile_flips && !config.directional_biomes.contains(&biome) {
+        tile_orientation(seed, x, y)
+    } else {
+        TileOrientation::default()
+    }
+}
+
+// Biomes whose extent is fixed by height or the coastline rather than climate, never smoothed away or into
+fn is_filter_fixed(biome: Biome) -> bool {
+    biome == Biome::Ocean || biome == Biome::Beach || biome == Biome::Volcanic
+}
+
+// Mode filter over a width-wide grid of biomes: each land tile takes the most common land biome within
+// radius cells when that biome outnumbers its own there. Passes run on the previous pass's result
+fn majority_filter(biomes: &mut [Biome], width: usize, radius: i32, passes: u32) {
+    let rows = (biomes.len() / width) as i32;
+
+    for _ in 0..passes {
+        let previous = biomes.to_vec();
+
+        for (index, biome) in biomes.iter_mut().enumerate() {
+            if is_filter_fixed(*biome) {
+                continue;
+            }
+
+            let (x, y) = ((index % width) as i32, (index / width) as i32);
+            let mut counts: Vec<(Biome, usize)> = Vec::new();
+
+            for ny in (y - radius).max(0)..(y + radius + 1).min(rows) {
+                for nx in (x - radius).max(0)..(x + radius + 1).min(width as i32) {
+                    let neighbour = previous[ny as usize * width + nx as usize];
+                    if is_filter_fixed(neighbour) {
+                        continue;
+                    }
+
+                    match counts.iter_mut().find(|(counted, _)| *counted == neighbour) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((neighbour, 1)),
+                    }
+                }
+            }
+
+            let own = counts.iter().find(|(counted, _)| *counted == *biome).map_or(0, |(_, count)| *count);
+            // First found wins ties, keeping the result independent of anything but the grid
+            let (common, most) = counts.iter().fold((*biome, own), |best, &(counted, count)| {
+                if count > best.1 { (counted, count) } else { best }
+            });
+
+            if most > own {
+                *biome = common;
+            }
+        }
+    }
+}
+
+// The atlas tiles drawn as textures rather than tinted by a computed color: desert and desert scrub
+fn is_desert_tile(tile: &Tile) -> bool {
+    tile.index == 1 || tile.index == 2
+}
+
+impl ClimateZone {
+    // Same temperature [C] bands as the Holdridge classification in pick_biome
+    pub fn from_temperature(temperature: f64) -> Self {
+        if temperature <= 0.0 {
+            ClimateZone::Polar
+        } else if temperature <= 3.0 {
+            ClimateZone::Subpolar
+        } else if temperature <= 6.0 {
+            ClimateZone::Boreal
+        } else if temperature <= 12.0 {
+            ClimateZone::Temperate
+        } else if temperature <= 24.0 {
+            ClimateZone::Subtropical
+        } else {
+            ClimateZone::Tropical
+        }
+    }
+}
+
+impl TileSample {
+    fn climate_zone(&self) -> ClimateZone {
+        ClimateZone::from_temperature(self.temperature)
+    }
+
+    // Depth [m] below sea level of a submerged tile, None on land. Submerged is height <= 0, where
+    // pick_biome makes ocean and the climate reads the height clamped to sea level
+    pub fn ocean_depth(&self) -> Option<f64> {
+        (self.height <= 0.0).then_some(-self.height)
+    }
+
+    // Height [m] above sea level of a tile on land, None under the sea
+    pub fn land_height(&self) -> Option<f64> {
+        (self.height > 0.0).then_some(self.height)
+    }
+}
+
+impl MapGrid {
+    pub fn width(&self) -> i32 {
+        2 * self.half_size + 1
+    }
+
+    // Tile coordinates of a grid entry, multiples of the stride
+    pub fn coords(&self, index: usize) -> (i32, i32) {
+        let width = self.width() as usize;
+        let cell_x = (index % width) as i32 - self.half_size;
+        let cell_y = (index / width) as i32 - self.half_size;
+
+        (cell_x * self.stride, cell_y * self.stride)
+    }
+
+    pub fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x % self.stride != 0 || y % self.stride != 0 {
+            return None;
+        }
+
+        let (cell_x, cell_y) = (x / self.stride, y / self.stride);
+        if cell_x.abs() > self.half_size || cell_y.abs() > self.half_size {
+            return None;
+        }
+
+        Some(((cell_y + self.half_size) * self.width() + (cell_x + self.half_size)) as usize)
+    }
+
+    // Grid coordinates of the spawned tile nearest to any tile coordinates
+    pub fn snap(&self, x: i32, y: i32) -> (i32, i32) {
+        let snap = |coord: i32| (coord as f64 / self.stride as f64).round() as i32 * self.stride;
+
+        (snap(x), snap(y))
+    }
+
+    // Steepest height change [m per tile] from each tile to a neighbour
+    pub fn slopes(&self) -> Vec<f64> {
+        (0..self.samples.len()).map(|index| {
+            let (x, y) = self.coords(index);
+            let height = self.samples[index].height;
+
+            [(-1, 0), (1, 0), (0, -1), (0, 1)].iter()
+                .filter_map(|(dx, dy)| self.index(x + dx * self.stride, y + dy * self.stride))
+                .map(|neighbour| (self.samples[neighbour].height - height).abs() / self.stride as f64)
+                .fold(0.0, f64::max)
+        }).collect()
+    }
+
+    // Tiles from each tile to the nearest ocean tile stepping along the axes, 0 on the ocean and None
+    // everywhere if the grid has no ocean
+    pub fn coast_distances(&self) -> Vec<Option<u32>> {
+        let mut distances = vec![None; self.samples.len()];
+        let mut open = std::collections::VecDeque::new();
+
+        for (index, sample) in self.samples.iter().enumerate() {
+            if sample.height <= 0.0 {
+                distances[index] = Some(0);
+                open.push_back(index);
+            }
+        }
+
+        while let Some(index) = open.pop_front() {
+            let (x, y) = self.coords(index);
+            let distance = distances[index].unwrap_or(0) + self.stride as u32;
+
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                if let Some(next) = self.index(x + dx * self.stride, y + dy * self.stride) {
+                    if distances[next].is_none() {
+                        distances[next] = Some(distance);
+                        open.push_back(next);
+                    }
+                }
+            }
+        }
+
+        distances
+    }
+
+    pub fn fields(&self) -> GridFields {
+        GridFields {
+            width: self.width() as usize,
+            height: self.samples.iter().map(|sample| sample.height as f32).collect(),
+            temperature: self.samples.iter().map(|sample| sample.temperature as f32).collect(),
+            percipitation: self.samples.iter().map(|sample| sample.percipitation as f32).collect(),
+            biome: self.samples.iter().map(|sample| sample.biome as u8).collect(),
+        }
+    }
+
+    fn is_land(&self, index: usize) -> bool {
+        self.samples[index].biome != Biome::Ocean
+    }
+
+    // Pairs of 4-neighbouring grid tiles where one is land and the other ocean, each shore edge once
+    pub fn coastline_edges(&self) -> Vec<((i32, i32), (i32, i32))> {
+        let mut edges = Vec::new();
+
+        for index in 0..self.samples.len() {
+            let (x, y) = self.coords(index);
+
+            for neighbour in [(x + self.stride, y), (x, y + self.stride)] {
+                if let Some(neighbour_index) = self.index(neighbour.0, neighbour.1) {
+                    if self.is_land(index) != self.is_land(neighbour_index) {
+                        edges.push(((x, y), neighbour));
+                    }
+                }
+            }
+        }
+
+        edges
+    }
+
+    // Shared sides of tiles of the selected biomes with tiles of any other biome, like coastline_edges.
+    // A side between two different selected biomes is listed once
+    pub fn biome_outline_edges(&self, selected: &[Biome]) -> Vec<((i32, i32), (i32, i32))> {
+        let mut edges = Vec::new();
+
+        for index in 0..self.samples.len() {
+            let (x, y) = self.coords(index);
+            let biome = self.samples[index].biome;
+
+            for neighbour in [(x + self.stride, y), (x, y + self.stride)] {
+                if let Some(neighbour_index) = self.index(neighbour.0, neighbour.1) {
+                    let other = self.samples[neighbour_index].biome;
+                    if biome != other && (selected.contains(&biome) || selected.contains(&other)) {
+                        edges.push(((x, y), neighbour));
+                    }
+                }
+            }
+        }
+
+        edges
+    }
+
+    // Flood fills the land tiles into 4-connected landmasses of at least min_tiles
+    fn landmasses(&self, min_tiles: usize) -> Vec<Landmass> {
+        let mut visited = vec![false; self.samples.len()];
+        let mut landmasses = Vec::new();
+
+        for start in 0..self.samples.len() {
+            if visited[start] || !self.is_land(start) {
+                continue;
+            }
+
+            visited[start] = true;
+            let mut stack = vec![start];
+            let mut peak = start;
+            let mut samples = 0;
+            let mut sum_x = 0.0;
+            let mut sum_y = 0.0;
+
+            while let Some(index) = stack.pop() {
+                let (x, y) = self.coords(index);
+                if self.samples[index].height > self.samples[peak].height {
+                    peak = index;
+                }
+                samples += 1;
+                sum_x += x as f64;
+                sum_y += y as f64;
+
+                for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                    if let Some(next) = self.index(x + dx * self.stride, y + dy * self.stride) {
+                        if !visited[next] && self.is_land(next) {
+                            visited[next] = true;
+                            stack.push(next);
+                        }
+                    }
+                }
+            }
+
+            // Each sample stands for stride x stride map tiles, but the centroid averages the samples
+            let tiles = samples * (self.stride * self.stride) as usize;
+
+            if tiles >= min_tiles {
+                landmasses.push(Landmass {
+                    peak: self.coords(peak),
+                    tiles,
+                    centroid: (sum_x / samples as f64, sum_y / samples as f64),
+                });
+            }
+        }
+
+        landmasses
+    }
+}
+
+// Perlin's permutation table has 256 entries, so the noise repeats every 256 units on both axes
+const PERLIN_PERIOD: f64 = 256.0;
+
+// Largest coordinate Perlin noise takes, it panics on any it cannot floor into an isize
+const MAX_PERLIN_COORDINATE: f64 = isize::MAX as f64 / 2.0;
+
+// Stand-in for noise that came out NaN or infinite, e.g. from a zero scale
+const DEGENERATE_NOISE_VALUE: f64 = 0.5;
+
+// Degenerate noise is warned about once, not once per sample
+static DEGENERATE_NOISE_WARNED: AtomicBool = AtomicBool::new(false);
+
+impl NoiseMap {
+    // Noise at base + (x, y). Perlin noise repeats every PERLIN_PERIOD, so each octave wraps the large
+    // base term into one period before adding the small offset, keeping the offset's precision where
+    // the sum would round it away
+    fn get_value(&self, base: (f64, f64), x: f64, y: f64) -> f64 {
+        let mut amplitude: f64 = 1.0;
+        let mut frequency: f64 = 1.0;
+        let mut accumulation: f64 = 0.0;
+
+        for i in 0..self.octaves {
+            let sample_x = (base.0 / self.scale * frequency).rem_euclid(PERLIN_PERIOD) + x / self.scale * frequency;
+            let sample_y = (base.1 / self.scale * frequency).rem_euclid(PERLIN_PERIOD) + y / self.scale * frequency;
+            // Coordinates past what Perlin noise takes, infinite or NaN ones included, count as non-finite output
+            if !(sample_x.abs() < MAX_PERLIN_COORDINATE && sample_y.abs() < MAX_PERLIN_COORDINATE) {
+                accumulation = f64::NAN;
+                break;
+            }
+
+            let value = (self.noise_map.get([sample_x, sample_y]) + 1.0) / 2.0;
+            accumulation += value * amplitude;
+
+            amplitude *= self.persistance;
+            frequency *= self.lacunarity;
+        }
+
+        if !accumulation.is_finite() {
+            if !DEGENERATE_NOISE_WARNED.swap(true, Ordering::Relaxed) {
+                warn!("Noise with scale {} and lacunarity {} is not finite, using {} instead",
+                    self.scale, self.lacunarity, DEGENERATE_NOISE_VALUE);
+            }
+
+            return DEGENERATE_NOISE_VALUE;
+        }
+
+        if accumulation > 1.0 {
+            accumulation = 1.0
+        }
+
+        if accumulation < 0.0 {
+            accumulation = 0.0
+        }
+
+        accumulation
+    }
+}
+
+impl Biome {
+
+    pub const ALL: [Biome; 39] = [
+        Biome::Ocean,
+        Biome::PolarDesert,
+        Biome::SubpolarDryTundra,
+        Biome::SubpolarMoistTundra,
+        Biome::SubpolarWetTundra,
+        Biome::SubpolarRainTundra,
+        Biome::BorealDesert,
+        Biome::BorealDryScrub,
+        Biome::BorealMoistForest,
+        Biome::BorealWetForest,
+        Biome::BorealRainForest,
+        Biome::TemperateDesert,
+        Biome::TemperateDesertScrub,
+        Biome::TemperateSteppe,
+        Biome::TemperateMoistForest,
+        Biome::TemperateWetForest,
+        Biome::TemperateRainForest,
+        Biome::SubtropicalDesert,
+        Biome::SubtropicalDesertScrub,
+        Biome::SubtropicalThornWoodland,
+        Biome::SubtropicalDryForest,
+        Biome::SubtropicalMoistForest,
+        Biome::SubtropicalWetForest,
+        Biome::SubtropicalRainForest,
+        Biome::TropicalDesert,
+        Biome::TropicalDesertScrub,
+        Biome::TropicalThornWoodland,
+        Biome::TropicalVeryDryForest,
+        Biome::TropicalDryForest,
+        Biome::TropicalMoistForest,
+        Biome::TropicalWetForest,
+        Biome::TropicalRainForest,
+        Biome::Marsh,
+        Biome::Beach,
+        Biome::Alpine,
+        Biome::SaltFlat,
+        Biome::Mangrove,
+        Biome::Volcanic,
+        Biome::IceSheet,
+    ];
+
+    // Looks a biome up by its variant name, as used in the biome table CSV
+    pub fn from_name(name: &str) -> Option<Biome> {
+        Biome::ALL.iter().copied().find(|biome| format!("{:?}", biome) == name)
+    }
+
+    // Tundra of the same moisture that replaces a forest above the treeline, other biomes unchanged
+    fn above_treeline(self) -> Biome {
+        match self {
+            Biome::SubtropicalDryForest | Biome::TropicalVeryDryForest | Biome::TropicalDryForest => Biome::SubpolarDryTundra,
+            Biome::BorealMoistForest | Biome::TemperateMoistForest | Biome::SubtropicalMoistForest
+                | Biome::TropicalMoistForest => Biome::SubpolarMoistTundra,
+            Biome::BorealWetForest | Biome::TemperateWetForest | Biome::SubtropicalWetForest
+                | Biome::TropicalWetForest => Biome::SubpolarWetTundra,
+            Biome::BorealRainForest | Biome::TemperateRainForest | Biome::SubtropicalRainForest
+                | Biome::TropicalRainForest => Biome::SubpolarRainTundra,
+            biome => biome,
+        }
+    }
+
+    // Variant name split into words, as in "Temperate Moist Forest"
+    pub fn name(self) -> String {
+        let mut name = String::new();
+
+        for letter in format!("{:?}", self).chars() {
+            if letter.is_uppercase() && !name.is_empty() {
+                name.push(' ');
+            }
+            name.push(letter);
+        }
+
+        name
+    }
+
+    fn tile(self, temperature: f64, percipitation: f64) -> Tile {
+        match self {
+            Biome::Ocean => Biome::ocean_tile(temperature, percipitation),
+
+            Biome::PolarDesert => Biome::polar_desert_tile(temperature, percipitation),
+
+            Biome::SubpolarDryTundra => Biome::subpolar_dry_tundra_tile(temperature, percipitation),
+            Biome::SubpolarMoistTundra => Biome::subpolar_moist_tundra_tile(temperature, percipitation),
+            Biome::SubpolarWetTundra => Biome::subpolar_wet_tundra_tile(temperature, percipitation),
+            Biome::SubpolarRainTundra => Biome::subpolar_rain_tundra_tile(temperature, percipitation),
+            
+            Biome::BorealDesert => Biome::boreal_desert_tile(temperature, percipitation),
+            Biome::BorealDryScrub => Biome::boreal_dry_scrub_tile(temperature, percipitation),
+            Biome::BorealMoistForest => Biome::boreal_moist_forest_tile(temperature, percipitation),
+            Biome::BorealWetForest => Biome::boreal_wet_forest_tile(temperature, percipitation),
+            Biome::BorealRainForest => Biome::boreal_rain_forest_tile(temperature, percipitation),
+        
+            Biome::TemperateDesert => Biome::temperate_desert_tile(temperature, percipitation),
+            Biome::TemperateDesertScrub => Biome::temperate_desert_scrub_tile(temperature, percipitation),
+            Biome::TemperateSteppe => Biome::temperate_steppe_tile(temperature, percipitation),
+            Biome::TemperateMoistForest => Biome::temperate_moist_forest_tile(temperature, percipitation),
+            Biome::TemperateWetForest => Biome::temperate_wet_forest_tile(temperature, percipitation),
+            Biome::TemperateRainForest => Biome::temperate_rain_forest_tile(temperature, percipitation),
+        
+            Biome::SubtropicalDesert => Biome::subtropical_desert_tile(temperature, percipitation),
+            Biome::SubtropicalDesertScrub => Biome::subtropical_desert_scrub_tile(temperature, percipitation),
+            Biome::SubtropicalThornWoodland => Biome::subtropical_thorn_woodland_tile(temperature, percipitation),
+            Biome::SubtropicalDryForest => Biome::subtropical_dry_forest_tile(temperature, percipitation),
+            Biome::SubtropicalMoistForest => Biome::subtropical_moist_forest_tile(temperature, percipitation),
+            Biome::SubtropicalWetForest => Biome::subtropical_wet_forest_tile(temperature, percipitation),
+            Biome::SubtropicalRainForest => Biome::subtropical_rain_forest_tile(temperature, percipitation),
+            
+            Biome::TropicalDesert => Biome::tropical_desert_tile(temperature, percipitation),
+            Biome::TropicalDesertScrub => Biome::tropical_desert_scrub_tile(temperature, percipitation),
+            Biome::TropicalThornWoodland => Biome::tropical_thorn_woodland_tile(temperature, percipitation),
+            Biome::TropicalVeryDryForest => Biome::tropical_very_dry_forest_tile(temperature, percipitation),
+            Biome::TropicalDryForest => Biome::tropical_dry_forest_tile(temperature, percipitation),
+            Biome::TropicalMoistForest => Biome::tropical_moist_forest_tile(temperature, percipitation),
+            Biome::TropicalWetForest => Biome::tropical_wet_forest_tile(temperature, percipitation),
+            Biome::TropicalRainForest => Biome::tropical_rain_forest_tile(temperature, percipitation),
+
+            Biome::Marsh => Biome::marsh_tile(temperature, percipitation),
+
+            Biome::Beach => Biome::beach_tile(temperature, percipitation),
+
+            Biome::Alpine => Biome::alpine_tile(temperature, percipitation),
+
+            Biome::SaltFlat => Biome::salt_flat_tile(temperature, percipitation),
+
+            Biome::Mangrove => Biome::mangrove_tile(temperature, percipitation),
+
+            Biome::Volcanic => Biome::volcanic_tile(temperature, percipitation),
+
+            Biome::IceSheet => Biome::ice_sheet_tile(temperature, percipitation),
+        }
+    }
+
+    fn calculate_grass_color(temperature: f64, percipitation: f64) -> Color {
+
+        let percip_p = (percipitation + 10000.0) / 10000.0;
+        let temp_p = temperature / 30.0;
+
+        let mut deadness = ((1.0 - percip_p) + temp_p) / 2.0;
+        if deadness < 0.0 {
+            deadness = 0.0;
+        }
+
+        let mut alpine_p = 0.6 * (1.0 - temp_p) + 0.4 * (deadness);
+        if alpine_p < 0.0 {
+            alpine_p = 0.0;
+        }
+        
+        let green_color = GREEN_COLOR.mul(deadness as f32);
+        let yellow_color = YELLOW_COLOR.mul(percip_p as f32);
+        let blue_color = BLUE_COLOR.mul(alpine_p as f32);
+
+        let grass_color = green_color.add(yellow_color).mul(0.5);
+
+        // Adding colors adds their alphas too, and very wet tiles push the channels past 1
+        Color::rgb(
+            grass_color.r().clamp(0.0, 1.0),
+            grass_color.g().clamp(0.0, 1.0),
+            grass_color.b().clamp(0.0, 1.0),
+        )
+    }
+
+    fn ocean_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 3,
+            color: Color::rgb(0.0, 0.2, 0.8),
+        }
+    }
+
+    fn polar_desert_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 3,
+            color: Color::rgb(0.86, 0.86, 0.83),
+        }
+    }
+
+    // Subpolar ------------------
+
+    fn subpolar_dry_tundra_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 4,
+            color: Color::rgb(1.0, 1.0, 1.0),
+        }
+    }
+
+    fn subpolar_moist_tundra_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn subpolar_wet_tundra_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn subpolar_rain_tundra_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    // Boreal ------------------
+
+    fn boreal_desert_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn boreal_dry_scrub_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn boreal_moist_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn boreal_wet_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn boreal_rain_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    // Temperate ------------------
+
+    fn temperate_desert_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn temperate_desert_scrub_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn temperate_steppe_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn temperate_moist_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn temperate_wet_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn temperate_rain_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    // Subtropical ------------------
+
+    fn subtropical_desert_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn subtropical_desert_scrub_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+    
+    fn subtropical_thorn_woodland_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn subtropical_dry_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn subtropical_moist_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn subtropical_wet_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn subtropical_rain_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+     // Tropical ------------------
+
+    fn tropical_desert_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 2,
+            color: Color::rgb(1.0, 1.0, 1.0),
+        }
+    }
+
+    fn tropical_desert_scrub_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 1,
+            color: Color::rgb(1.0, 1.0, 1.0),
+        }
+    }
+
+    fn tropical_thorn_woodland_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn tropical_very_dry_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn tropical_dry_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn tropical_moist_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn tropical_wet_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    fn tropical_rain_forest_tile(temperature: f64, percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Biome::calculate_grass_color(temperature, percipitation),
+        }
+    }
+
+    // Azonal ------------------
+
+    fn marsh_tile(_temperature: f64, _percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Color::rgb(0.30, 0.36, 0.18),
+        }
+    }
+
+    fn beach_tile(_temperature: f64, _percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Color::rgb(0.86, 0.80, 0.55),
+        }
+    }
+
+    fn alpine_tile(_temperature: f64, _percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Color::rgb(0.56, 0.54, 0.50),
+        }
+    }
+
+    fn salt_flat_tile(_temperature: f64, _percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Color::rgb(0.93, 0.91, 0.85),
+        }
+    }
+
+    fn mangrove_tile(_temperature: f64, _percipitation: f64) -> Tile {
+        Tile {
+            index: 0,
+            color: Color::rgb(0.22, 0.30, 0.16),
+        }
+    }
+
+    // Dark basalt, snowcapped where it is cold enough to freeze
+    fn volcanic_tile(temperature: f64, _percipitation: f64) -> Tile {
+        let color = if temperature <= 0.0 {
+            Color::rgb(0.78, 0.78, 0.80)
+        } else {
+            Color::rgb(0.16, 0.13, 0.12)
+        };
+
+        Tile {
+            index: 0,
+            color,
+        }
+    }
+
+    // Bright blue-white glacial ice, against the duller polar desert
+    fn ice_sheet_tile(_temperature: f64, _percipitation: f64) -> Tile {
+        Tile {
+            index: 3,
+            color: Color::rgb(0.84, 0.93, 1.0),
+        }
+    }
+
+}
+
+// What every tile sprite of one generated map shares
+struct TileSpawner<'a> {
+    atlas: Handle<TextureAtlas>,
+    font: Handle<Font>,
+    mapgen: &'a MapGen,
+    config: &'a MapConfig,
+    seed: u32,
+}
+
+impl TileSpawner<'_> {
+    fn spawn_tile(&self, commands: &mut Commands, stride: i32, (x, y): (i32, i32), tile: Tile, sample: TileSample, movement_cost: f32) {
+        let (tile_x, tile_y) = self.mapgen.tile_position(x, y);
+
+        let mut sprite = TextureAtlasSprite::new(tile.index);
+        sprite.color = tile.color;
+        let sprite_scale = self.config.tile_scale * stride as f64;
+
+        let orientation = configured_orientation(self.config, self.seed, x, y, sample.biome);
+        sprite.flip_x = orientation.flip_x;
+        sprite.flip_y = orientation.flip_y;
+        sprite.anchor = self.config.tile_anchor.clone();
+
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: self.atlas.clone(),
+                sprite: sprite,
+                transform: Transform {
+                    translation: Vec3::new(tile_x as f32, tile_y as f32, 0.0),
+                    rotation: Quat::from_rotation_z(orientation.quarter_turns as f32 * std::f32::consts::FRAC_PI_2),
+                    scale: Vec3::splat(sprite_scale as f32),
+                },
+                ..default()
+            },
+            MapTile {
+                coords: (x, y),
+                cell: self.mapgen.map_cell(x, y),
+                tile,
+                sample,
+                movement_cost,
+            },
+            MapEntity,
+        ));
+    }
+
+    fn spawn_grid_tiles(&self, commands: &mut Commands, grid: &MapGrid) {
+        let movement_costs = self.mapgen.cost_grid(grid);
+
+        for (index, (tile, sample)) in grid.tiles.iter().zip(grid.samples.iter()).enumerate() {
+            self.spawn_tile(commands, grid.stride, grid.coords(index), *tile, *sample, movement_costs[index]);
+        }
+    }
+
+    // Landmass names, when there is a grid to find them in, and the resolution note of a strided map
+    fn spawn_labels(&self, commands: &mut Commands, grid: Option<&MapGrid>) {
+        for landmass in grid.iter().flat_map(|grid| grid.landmasses(MIN_LANDMASS_TILES)) {
+            let name = self.mapgen.landmass_name(&landmass);
+            let label_x = landmass.centroid.0 * self.config.world_units_per_tile();
+            let label_y = landmass.centroid.1 * self.config.world_units_per_tile();
+            let font_size = if landmass.tiles > 2000 { 22.0 } else { 14.0 };
+
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(name, TextStyle {
+                        font: self.font.clone(),
+                        font_size,
+                        color: Color::WHITE,
+                    }).with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_xyz(label_x as f32, label_y as f32, 1.0),
+                    ..default()
+                },
+                LandmassLabel,
+                MapEntity,
+            ));
+        }
+
+        let stride = self.config.tile_stride();
+        if stride > 1 {
+            let width = self.config.map_size as i32 / 2 / stride * 2 + 1;
+            let resolution = format!("Resolution 1/{} ({}x{} tiles)", stride, width, width);
+
+            commands.spawn((
+                TextBundle::from_section(resolution, TextStyle {
+                    font: self.font.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                }).with_style(Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(10.0),
+                        left: Val::Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                MapEntity,
+            ));
+        }
+    }
+}
+
+fn spawn_map(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    seed: Res<Seed>,
+    view: MapView,
+) {
+    let MapView { zoom, x_shift, y_shift, noise_offset, config, .. } = view;
+
+    let tile_size = config.tile_size;
+
+    let mapgen = MapGen::new(seed.0, zoom.0, x_shift.0, y_shift.0, (noise_offset.0, noise_offset.1), &config);
+
+    let texture_handle = asset_server.load(TILEMAP_TEXTURE_PATH);
+    let texture_atlas =
+        TextureAtlas::from_grid(texture_handle, Vec2::new(tile_size as f32, tile_size as f32), ATLAS_TILES, 1, None, None);
+
+    let spawner = TileSpawner {
+        atlas: texture_atlases.add(texture_atlas),
+        font: asset_server.load(FONT_PATH),
+        mapgen: &mapgen,
+        config: &config,
+        seed: seed.0,
+    };
+
+    // In low memory mode each tile is spawned as soon as it is generated and no grid is kept.
+    // A preview spawns a coarse grid now and leaves the full one to a thread, see finish_map_preview
+    let stride = config.tile_stride();
+    let grid = if config.low_memory {
+        let streamed = mapgen.stream_tiles(|coords, tile, sample, movement_cost| spawner.spawn_tile(&mut commands, stride, coords, tile, sample, movement_cost));
+        if let Err(error) = streamed {
+            error!("{}, keeping the tiles generated so far", error);
+        }
+        spawner.spawn_labels(&mut commands, None);
+        None
+    } else if config.preview_stride > stride {
+        let preview = mapgen.generate_grid_at(config.preview_stride);
+        spawner.spawn_grid_tiles(&mut commands, &preview);
+
+        let (seed, zoom, x_shift, y_shift, noise_offset) = (seed.0, zoom.0, x_shift.0, y_shift.0, (noise_offset.0, noise_offset.1));
+        let full_config = config.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let thread_cancel = cancel.clone();
+        let generating = std::thread::spawn(move || {
+            let mapgen = MapGen::new(seed, zoom, x_shift, y_shift, noise_offset, &full_config).with_cancel(thread_cancel);
+            generate_profiled(&mapgen, &full_config, seed, zoom)
+        });
+
+        commands.spawn((
+            MapPreview {
+                generating: Some(generating),
+                cancel,
+                atlas: spawner.atlas.clone(),
+                seed,
+            },
+            MapEntity,
+        ));
+        Some(preview)
+    } else {
+        match generate_profiled(&mapgen, &config, seed.0, zoom.0) {
+            Ok(grid) => {
+                spawner.spawn_grid_tiles(&mut commands, &grid);
+                spawner.spawn_labels(&mut commands, Some(&grid));
+                Some(grid)
+            }
+            Err(error) => {
+                error!("{}, leaving the map empty", error);
+                None
+            }
+        }
+    };
+
+    commands.insert_resource(mapgen);
+    match grid {
+        Some(grid) => commands.insert_resource(grid),
+        None => commands.remove_resource::<MapGrid>(),
+    }
+}
+
+// The map's grid, with its stage timings added to the profile file when profile_path is set, or the
+// error once it runs over the generation_budget
+fn generate_profiled(mapgen: &MapGen, config: &MapConfig, seed: u32, zoom: f64) -> Result<MapGrid> {
+    let path = match &config.profile_path {
+        Some(path) => path,
+        None => return mapgen.try_generate_grid(),
+    };
+
+    let (grid, profile) = mapgen.generate_grid_profiled()?;
+    if let Err(error) = append_profile(path, seed, zoom, &profile) {
+        warn!("Failed to write the generation profile to {}: {}", path, error);
+    }
+
+    Ok(grid)
+}
+
+// Swaps the preview for the full map once its thread has generated it. Regenerating first despawns
+// the MapPreview, which cancels the thread and drops its result
+fn finish_map_preview(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<MapConfig>,
+    mapgen: Option<Res<MapGen>>,
+    mut previews: Query<&mut MapPreview>,
+    map_entities: Query<Entity, With<MapEntity>>,
+) {
+    let mapgen = match mapgen {
+        Some(mapgen) => mapgen,
+        None => return,
+    };
+
+    for mut preview in previews.iter_mut() {
+        if !preview.generating.as_ref().is_some_and(|generating| generating.is_finished()) {
+            continue;
+        }
+
+        let grid = match preview.generating.take().map(|generating| generating.join()) {
+            Some(Ok(Ok(grid))) => grid,
+            Some(Ok(Err(error))) => {
+                error!("{}, keeping the preview", error);
+                continue;
+            }
+            _ => {
+                error!("Generating the full map failed, keeping the preview");
+                continue;
+            }
+        };
+
+        for entity in map_entities.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        let spawner = TileSpawner {
+            atlas: preview.atlas.clone(),
+            font: asset_server.load(FONT_PATH),
+            mapgen: &mapgen,
+            config: &config,
+            seed: preview.seed,
+        };
+        spawner.spawn_grid_tiles(&mut commands, &grid);
+        spawner.spawn_labels(&mut commands, Some(&grid));
+        commands.insert_resource(grid);
+    }
+}
+
+fn spawn_seed_entry_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font: asset_server.load(FONT_PATH),
+            font_size: 20.0,
+            color: Color::WHITE,
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        SeedEntryText,
+    ));
+}
+
+fn spawn_seed_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font: asset_server.load(FONT_PATH),
+            font_size: 20.0,
+            color: Color::WHITE,
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        SeedText,
+    ));
+}
+
+fn spawn_isolated_biome_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font: asset_server.load(FONT_PATH),
+            font_size: 20.0,
+            color: Color::WHITE,
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        IsolatedBiomeText,
+    ));
+}
+
+fn spawn_debug_view_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font: asset_server.load(FONT_PATH),
+            font_size: 20.0,
+            color: Color::WHITE,
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(35.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        DebugViewText,
+    ));
+}
+
+// Only the windowed app starts on the daily seed. The headless exports and searches take their seeds
+// from the command line, so their output never depends on the date
+fn setup_daily_seed(config: Res<MapConfig>, mut seed: ResMut<Seed>) {
+    if config.daily_seed {
+        *seed = Seed::daily().0;
+    }
+}
+
+// Rolls seeds, starting from start and then drawn from an RNG seeded by it, until one has at least
+// min_land_fraction land. Gives up after max_reseed_attempts with the landiest seed seen. Returns the
+// seed with the number of seeds tried, none without a minimum
+fn seed_with_land(start: u32, zoom: f64, x_shift: f64, y_shift: f64, noise_offset: (f64, f64), config: &MapConfig) -> (u32, u32) {
+    if config.min_land_fraction <= 0.0 {
+        return (start, 0);
+    }
+
+    let mut rng = StdRng::seed_from_u64(start as u64);
+    let mut candidate = start;
+    let mut best = (start, f64::NEG_INFINITY);
+
+    for attempt in 1..=config.max_reseed_attempts.max(1) {
+        let land = MapGen::new(candidate, zoom, x_shift, y_shift, noise_offset, config).land_fraction();
+
+        if land >= config.min_land_fraction {
+            info!("Seed {} has {:.0}% land, found after {} attempts", candidate, land * 100.0, attempt);
+            return (candidate, attempt);
+        }
+
+        if land > best.1 {
+            best = (candidate, land);
+        }
+        candidate = rng.gen_range(0..99999);
+    }
+
+    warn!(
+        "No seed with {:.0}% land in {} attempts, using {} with {:.0}%",
+        config.min_land_fraction * 100.0, config.max_reseed_attempts, best.0, best.1 * 100.0,
+    );
+    (best.0, config.max_reseed_attempts.max(1))
+}
+
+// How close a view comes to interesting, the land and biome variety shares of their minimums capped at
+// 1 each and added
+fn view_score(counts: &HashMap<Biome, u32>, config: &MapConfig) -> f64 {
+    let total: u32 = counts.values().sum();
+    let land = total - counts.get(&Biome::Ocean).copied().unwrap_or(0);
+    let land_biomes = counts.keys().filter(|&&biome| biome != Biome::Ocean).count();
+
+    let share = |value: f64, minimum: f64| if minimum <= 0.0 { 1.0 } else { (value / minimum).min(1.0) };
+    share(land as f64 / total.max(1) as f64, config.view_min_land_fraction) + share(land_biomes as f64, config.view_min_biomes as f64)
+}
+
+// Rolls seeds like seed_with_land until one whose visible region, center and radius in world units, is
+// interesting. Gives up after max_reseed_attempts with the one that came closest. The result depends only
+// on start and the view, so the same reseed from the same place always lands on the same seed
+#[allow(clippy::too_many_arguments)]
+pub fn seed_with_interesting_view(
+    start: u32,
+    zoom: f64,
+    x_shift: f64,
+    y_shift: f64,
+    noise_offset: (f64, f64),
+    center: (f64, f64),
+    radius: f64,
+    config: &MapConfig,
+) -> u32 {
+    let mut rng = StdRng::seed_from_u64(start as u64);
+    let mut candidate = start;
+    let mut best = (start, f64::NEG_INFINITY);
+
+    for attempt in 1..=config.max_reseed_attempts.max(1) {
+        let counts = MapGen::new(candidate, zoom, x_shift, y_shift, noise_offset, config).view_biomes(center, radius);
+        let score = view_score(&counts, config);
+
+        if score >= 2.0 {
+            info!("Seed {} has an interesting view, found after {} attempts", candidate, attempt);
+            return candidate;
+        }
+
+        if score > best.1 {
+            best = (candidate, score);
+        }
+        candidate = rng.gen_range(0..99999);
+    }
+
+    warn!("No seed with an interesting view in {} attempts, using {}", config.max_reseed_attempts, best.0);
+    best.0
+}
+
+// Shift + the random seed key rerolls to a seed with land and biome variety in the visible part of the
+// view, rather than anywhere on the map. The visible part is capped at the map, past which is all ocean
+fn reseed_keeping_view(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Res<Windows>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    view: MapView,
+    mut seed: ResMut<Seed>,
+) {
+    if !bindings.just_pressed(&keyboard, Action::ReseedKeepingView) {
+        return;
+    }
+
+    let MapView { zoom, x_shift, y_shift, noise_offset, config, .. } = view;
+
+    let (center, radius) = match (windows.get_primary(), camera.get_single()) {
+        (Some(window), Ok((transform, projection))) => (
+            (transform.translation.x as f64, transform.translation.y as f64),
+            (window.width().min(window.height()) / 2.0 * projection.scale) as f64,
+        ),
+        _ => ((0.0, 0.0), config.map_axis_len()),
+    };
+
+    let start = thread_rng().gen_range(0..99999);
+    *seed = Seed::number(seed_with_interesting_view(
+        start, zoom.0, x_shift.0, y_shift.0, (noise_offset.0, noise_offset.1), center, radius.min(config.map_axis_len()), &config,
+    ));
+}
+
+// The launch seed is rerolled like a random one, except the daily seed which must match every player's
+fn setup_land_seed(
+    config: Res<MapConfig>,
+    zoom: Res<Zoom>,
+    x_shift: Res<XShift>,
+    y_shift: Res<YShift>,
+    noise_offset: Res<NoiseOffset>,
+    mut seed: ResMut<Seed>,
+) {
+    if config.daily_seed {
+        return;
+    }
+
+    let (land_seed, _) = seed_with_land(seed.0, zoom.0, x_shift.0, y_shift.0, (noise_offset.0, noise_offset.1), &config);
+    if land_seed != seed.0 {
+        *seed = Seed::number(land_seed);
+    }
+}
+
+fn setup_config(mut config: ResMut<MapConfig>) {
+    config.biome_table = Some(load_biome_table());
+    if let Some(path) = config.land_mask_path.clone() {
+        match read_land_mask(&path) {
+            Ok(mask) => config.land_mask = Some(mask),
+            Err(error) => warn!("Failed to load land mask {}: {}, generating without it", path, error),
+        }
+    }
+    let quality = config.quality;
+    quality.apply(&mut config);
+
+    if let Err(error) = config.validate() {
+        error!("{}, falling back to the default config", error);
+        *config = MapConfig {
+            biome_table: config.biome_table.take(),
+            ..default()
+        };
+    }
+
+    if config.check_determinism {
+        match determinism_mismatches(&config) {
+            0 => info!("Generation is deterministic"),
+            changed => error!("Generating the same map twice changed {} pixels", changed),
+        }
+    }
+
+    if config.report_unreachable_biomes {
+        // Classification doesn't depend on the seed or view, so any MapGen answers for the config
+        let reachable = MapGen::new(0, 1.0, 0.0, 0.0, (0.0, 0.0), &config).reachable_biomes();
+        let unreachable: Vec<Biome> = Biome::ALL.into_iter().filter(|biome| !reachable.contains(biome)).collect();
+        if !unreachable.is_empty() {
+            info!("Biomes this config can never generate: {:?}", unreachable);
+        }
+    }
+}
+
+// Seed and size of the small map the determinism check renders twice
+const DETERMINISM_SEED: u32 = 829201;
+const DETERMINISM_MAP_SIZE: u32 = 64;
+
+// Renders a small fixed map twice under config and counts the pixels that differ at all, 0 unless
+// generation depends on something besides its inputs
+fn determinism_mismatches(config: &MapConfig) -> usize {
+    let config = MapConfig {
+        map_size: DETERMINISM_MAP_SIZE,
+        ..config.clone()
+    };
+    let render = || export::grid_image(&MapGen::new(DETERMINISM_SEED, 1.0, 0.0, 0.0, (0.0, 0.0), &config).generate_grid(), 1.0, 1);
+
+    let (first, second) = (render(), render());
+    first.pixels().zip(second.pixels()).filter(|(a, b)| a != b).count()
+}
+
+fn update_seed_inputs(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    view: MapView,
+    mut seed: ResMut<Seed>,
+) {
+    let pressed = |action| bindings.just_pressed(&keyboard, action);
+
+    if pressed(Action::RandomSeed) {
+        let mut rng = thread_rng();
+
+        let MapView { zoom, x_shift, y_shift, noise_offset, config, .. } = view;
+        let random_seed = rng.gen_range(0..99999);
+        *seed = Seed::number(seed_with_land(random_seed, zoom.0, x_shift.0, y_shift.0, (noise_offset.0, noise_offset.1), &config).0);
+    }
+
+    if pressed(Action::DailySeed) {
+        *seed = Seed::daily().0;
+    }
+
+    if pressed(Action::NextSeed) {
+        *seed = Seed::number(seed.0.wrapping_add(1));
+    }
+
+    if pressed(Action::PreviousSeed) {
+        *seed = Seed::number(seed.0.wrapping_sub(1));
+    }
+}
+
+// The noise scroll keys move the noise under the map instead of moving the view, without bounds.
+// In explore mode the pan keys move the player instead
+fn update_view_inputs(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    explore: Res<ExploreMode>,
+    zoom: Res<Zoom>,
+    mut x_shift: ResMut<XShift>,
+    mut y_shift: ResMut<YShift>,
+    mut noise_offset: ResMut<NoiseOffset>,
+) {
+    if explore.0 {
+        return;
+    }
+
+    let pressed = |action| bindings.just_pressed(&keyboard, action);
+
+    if pressed(Action::PanWest) && x_shift.0 > -MAX_SHIFT {
+        x_shift.0 -= 0.1 / zoom.0;
+    }
+
+    if pressed(Action::PanEast) && x_shift.0 < MAX_SHIFT {
+        x_shift.0 += 0.1 / zoom.0;
+    }
+
+    if pressed(Action::PanSouth) && y_shift.0 > -MAX_SHIFT {
+        y_shift.0 -= 0.1 / zoom.0;
+    }
+
+    if pressed(Action::PanNorth) && y_shift.0 < MAX_SHIFT {
+        y_shift.0 += 0.1 / zoom.0;
+    }
+
+    if pressed(Action::ScrollNoiseWest) {
+        noise_offset.0 -= 0.1 / zoom.0;
+    }
+
+    if pressed(Action::ScrollNoiseEast) {
+        noise_offset.0 += 0.1 / zoom.0;
+    }
+
+    if pressed(Action::ScrollNoiseSouth) {
+        noise_offset.1 -= 0.1 / zoom.0;
+    }
+
+    if pressed(Action::ScrollNoiseNorth) {
+        noise_offset.1 += 0.1 / zoom.0;
+    }
+}
+
+fn update_display_inputs(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut show_labels: ResMut<ShowLabels>,
+    mut show_climate_zones: ResMut<ShowClimateZones>,
+    mut display: ResMut<DisplayConfig>,
+) {
+    let pressed = |action| bindings.just_pressed(&keyboard, action);
+
+    if pressed(Action::ToggleLabels) {
+        show_labels.0 = !show_labels.0;
+    }
+
+    if pressed(Action::ToggleClimateZones) {
+        show_climate_zones.0 = !show_climate_zones.0;
+    }
+
+    if pressed(Action::ToggleDayNight) {
+        display.day_night = !display.day_night;
+    }
+}
+
+fn update_generation_inputs(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut config: ResMut<MapConfig>,
+) {
+    let pressed = |action| bindings.just_pressed(&keyboard, action);
+
+    if pressed(Action::CycleZoomScaling) {
+        config.zoom_scaling = config.zoom_scaling.next();
+        info!("Zoom scaling: {:?}", config.zoom_scaling);
+    }
+
+    if pressed(Action::CycleBiomeModel) {
+        config.biome_model = config.biome_model.next();
+        info!("Biome model: {:?}", config.biome_model);
+    }
+
+    if pressed(Action::CycleColoringMode) {
+        config.coloring_mode = config.coloring_mode.next();
+        info!("Coloring mode: {:?}", config.coloring_mode);
+    }
+
+    if pressed(Action::CycleQuality) {
+        let quality = config.quality.next();
+        quality.apply(&mut config);
+        info!("Quality: {:?}", quality);
+    }
+}
+
+// The map regenerates when anything it is generated from changes, a debounced seed once it settles.
+// clear_map and respawn_map then replace it in the same frame
+fn schedule_map(
+    time: Res<Time>,
+    mut pending_seed: ResMut<PendingSeed>,
+    display: Res<DisplayConfig>,
+    seed: Res<Seed>,
+    view: MapView,
+    mut due: ResMut<MapDue>,
+) {
+    let seed_due = pending_seed.due(seed.is_changed(), time.elapsed_seconds(), view.config.seed_debounce);
+
+    if seed_due || view.is_changed() {
+        pending_seed.0 = None;
+        due.0 = Some(display.seed_fade > 0.0 && seed_due);
+    }
+}
+
+// With a crossfade the old tiles stay until they have faded out, everything else goes at once
+fn clear_map(
+    mut commands: Commands,
+    due: Res<MapDue>,
+    gen_time: Res<GenTime>,
+    mut fade_in: ResMut<MapFadeIn>,
+    query: Query<(Entity, Option<&MapTile>), With<MapEntity>>,
+) {
+    let crossfade = match due.0 {
+        Some(crossfade) => crossfade,
+        None => return,
+    };
+
+    if crossfade {
+        fade_in.0 = Some(gen_time.elapsed);
+    }
+
+    for (entity, tile) in query.iter() {
+        if crossfade && tile.is_some() {
+            commands.entity(entity)
+                .remove::<(MapEntity, MapTile)>()
+                .insert(FadingOut(gen_time.elapsed));
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn respawn_map(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    texture_atlases: ResMut<Assets<TextureAtlas>>,
+    seed: Res<Seed>,
+    view: MapView,
+    mut due: ResMut<MapDue>,
+) {
+    if due.0.take().is_some() {
+        spawn_map(commands, asset_server, texture_atlases, seed, view);
+    }
+}
+
+// Enter starts typing a seed and applies it, Escape cancels. Numbers are used as-is, anything else is hashed.
+// Runs right after input is gathered so it can hide the typed keys from every other binding
+fn update_seed_entry(
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut seed_entry: ResMut<SeedEntry>,
+    mut seed: ResMut<Seed>,
+    mut query: Query<&mut Text, With<SeedEntryText>>,
+) {
+    let typed: Vec<char> = characters.iter().map(|event| event.char).collect();
+
+    if let Some(text) = seed_entry.0.as_mut() {
+        text.extend(typed.into_iter().filter(|c| !c.is_control()));
+
+        if keyboard.just_pressed(KeyCode::Back) {
+            text.pop();
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) {
+        match seed_entry.0.take() {
+            Some(text) if !text.is_empty() => {
+                *seed = match text.parse::<u32>() {
+                    Ok(number) => Seed::number(number),
+                    Err(_) => Seed::from_str_hashed(&text),
+                };
+            }
+            Some(_) => {}
+            None => seed_entry.0 = Some(String::new()),
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        seed_entry.0 = None;
+    }
+
+    if seed_entry.0.is_some() {
+        keyboard.clear();
+    }
+
+    if seed_entry.is_changed() {
+        for mut text in query.iter_mut() {
+            text.sections[0].value = match &seed_entry.0 {
+                Some(entry) => format!("Seed: {}_", entry),
+                None => String::new(),
+            };
+        }
+    }
+}
+
+// O cycles the format the seed is shown in
+fn update_seed_text(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    seed: Res<Seed>,
+    mut display: ResMut<DisplayConfig>,
+    mut query: Query<&mut Text, With<SeedText>>,
+) {
+    if bindings.just_pressed(&keyboard, Action::CycleSeedFormat) {
+        display.seed_format = display.seed_format.next();
+    }
+
+    if !seed.is_changed() && !display.is_changed() {
+        return;
+    }
+
+    let (daily_seed, date) = Seed::daily();
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = if seed.0 == daily_seed.0 {
+            format!("Daily map {}", date)
+        } else {
+            format!("Seed {}", display.seed_format.format(&seed))
+        };
+    }
+}
+
+// I steps through every biome and then back to showing them all
+fn update_isolated_biome(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut isolated: ResMut<IsolatedBiome>,
+    mut query: Query<&mut Text, With<IsolatedBiomeText>>,
+) {
+    if bindings.just_pressed(&keyboard, Action::IsolateNextBiome) {
+        isolated.0 = match isolated.0 {
+            None => Some(Biome::ALL[0]),
+            Some(biome) => {
+                let next = Biome::ALL.iter().position(|&b| b == biome).unwrap() + 1;
+                Biome::ALL.get(next).copied()
+            }
+        };
+    }
+
+    if !isolated.is_changed() {
+        return;
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = match isolated.0 {
+            Some(biome) => format!("Isolated biome: {:?}", biome),
+            None => String::new(),
+        };
+    }
+}
+
+// Insert writes the current map's tile metadata for other engines next to the executable
+#[allow(clippy::too_many_arguments)]
+fn export_tile_metadata(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    seed: Res<Seed>,
+    zoom: Res<Zoom>,
+    x_shift: Res<XShift>,
+    y_shift: Res<YShift>,
+    noise_offset: Res<NoiseOffset>,
+    config: Res<MapConfig>,
+    grid: Option<Res<MapGrid>>,
+) {
+    let grid = match grid {
+        Some(grid) if bindings.just_pressed(&keyboard, Action::ExportTiles) => grid,
+        _ => return,
+    };
+
+    let path = format!("tiles_{}.json", seed.0);
+    let json = export::tiles_json(seed.0, zoom.0, x_shift.0, y_shift.0, (noise_offset.0, noise_offset.1), &config, &grid);
+
+    match std::fs::write(&path, json) {
+        Ok(()) => info!("Wrote {}", path),
+        Err(error) => warn!("Failed to write {}: {}", path, error),
+    }
+}
+
+// J switches nearest and linear tile filtering, Shift + J switches MSAA between 1 and 4 samples.
+// The atlas sampler is set again once the texture has loaded, since it may load after the setting
+fn update_tile_sampling(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    asset_server: Res<AssetServer>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut images: ResMut<Assets<Image>>,
+    mut msaa: ResMut<Msaa>,
+    mut display: ResMut<DisplayConfig>,
+) {
+    if bindings.just_pressed(&keyboard, Action::CycleTileFiltering) {
+        let filtering = display.tile_filtering.next();
+        display.tile_filtering = filtering;
+        info!("Tile filtering: {:?}", filtering);
+    }
+
+    if bindings.just_pressed(&keyboard, Action::ToggleMsaa) {
+        let samples = if display.msaa_samples > 1 { 1 } else { 4 };
+        display.msaa_samples = samples;
+        info!("MSAA samples: {}", samples);
+    }
+
+    let texture = asset_server.load(TILEMAP_TEXTURE_PATH);
+    let loaded = image_events.iter().any(|event| matches!(event, AssetEvent::Created { handle } if *handle == texture));
+
+    if !display.is_changed() && !loaded {
+        return;
+    }
+
+    if msaa.samples != display.msaa_samples {
+        msaa.samples = display.msaa_samples;
+    }
+
+    if let Some(image) = images.get_mut(&texture) {
+        image.sampler_descriptor = match display.tile_filtering {
+            TileFiltering::Nearest => ImageSampler::nearest(),
+            TileFiltering::Linear => ImageSampler::linear(),
+        };
+    }
+}
+
+// V cycles the debug views, H toggles the height view directly, - and = lower and raise its exaggeration.
+// C switches the color space the biome colors are read in
+fn update_debug_view(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut display: ResMut<DisplayConfig>,
+    mut query: Query<&mut Text, With<DebugViewText>>,
+) {
+    if bindings.just_pressed(&keyboard, Action::CycleDebugView) {
+        display.debug_view = display.debug_view.next();
+    }
+
+    if bindings.just_pressed(&keyboard, Action::CycleColorSpace) {
+        let color_space = display.color_space.next();
+        display.color_space = color_space;
+        info!("Color space: {:?}", color_space);
+    }
+
+    if bindings.just_pressed(&keyboard, Action::ToggleHeightView) {
+        display.debug_view = match display.debug_view {
+            DebugView::Height => DebugView::Biome,
+            _ => DebugView::Height,
+        };
+    }
+
+    if bindings.just_pressed(&keyboard, Action::LowerHeightExaggeration) {
+        display.height_exaggeration = (display.height_exaggeration - 0.5).max(1.0);
+    }
+
+    if bindings.just_pressed(&keyboard, Action::RaiseHeightExaggeration) {
+        display.height_exaggeration = (display.height_exaggeration + 0.5).min(10.0);
+    }
+
+    if !display.is_changed() {
+        return;
+    }
+
+    let mut lines = vec![match display.debug_view {
+        DebugView::Biome => String::new(),
+        DebugView::Height => format!("Height view x{:.1}", display.height_exaggeration),
+        DebugView::Combined => "Temperature red, height green, moisture blue".to_string(),
+        DebugView::MovementCost => "Movement cost, impassable in black".to_string(),
+        DebugView::Falloff => "Radial falloff, white keeps the most height".to_string(),
+    }];
+
+    if (display.brightness, display.contrast, display.saturation) != (1.0, 1.0, 1.0) {
+        lines.push(format!("Brightness {:.1}, contrast {:.1}, saturation {:.1}",
+            display.brightness, display.contrast, display.saturation));
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = lines.join("\n").trim().to_string();
+    }
+}
+
+// F5 and F6 lower and raise the brightness, F7 and F8 the contrast, F9 and F10 the saturation,
+// F12 resets all three
+fn update_color_adjustments(keyboard: Res<Input<KeyCode>>, bindings: Res<KeyBindings>, mut display: ResMut<DisplayConfig>) {
+    let step = |value: f32, down: Action, up: Action| {
+        if bindings.just_pressed(&keyboard, down) {
+            (value - COLOR_ADJUSTMENT_STEP).max(0.0)
+        } else if bindings.just_pressed(&keyboard, up) {
+            (value + COLOR_ADJUSTMENT_STEP).min(MAX_COLOR_ADJUSTMENT)
+        } else {
+            value
+        }
+    };
+
+    let brightness = step(display.brightness, Action::LowerBrightness, Action::RaiseBrightness);
+    let contrast = step(display.contrast, Action::LowerContrast, Action::RaiseContrast);
+    let saturation = step(display.saturation, Action::LowerSaturation, Action::RaiseSaturation);
+
+    let adjusted = if bindings.just_pressed(&keyboard, Action::ResetColorAdjustments) {
+        (1.0, 1.0, 1.0)
+    } else {
+        (brightness, contrast, saturation)
+    };
+
+    if adjusted != (display.brightness, display.contrast, display.saturation) {
+        (display.brightness, display.contrast, display.saturation) = adjusted;
+    }
+}
+
+// R logs the biomes within BIOME_REPORT_RADIUS tiles of the cursor, most common first
+fn report_biomes_near_cursor(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Res<Windows>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    mapgen: Option<Res<MapGen>>,
+) {
+    if !bindings.just_pressed(&keyboard, Action::InspectCursor) {
+        return;
+    }
+
+    let cursor = match (windows.get_primary(), camera.get_single()) {
+        (Some(window), Ok((transform, projection))) => cursor_world_position(window, transform, projection),
+        _ => None,
+    };
+
+    let (cursor, mapgen) = match (cursor, mapgen) {
+        (Some(cursor), Some(mapgen)) => (cursor, mapgen),
+        _ => return,
+    };
+
+    let radius = BIOME_REPORT_RADIUS * mapgen.config.world_units_per_tile();
+    let mut counts: Vec<(Biome, u32)> = mapgen.biomes_in_radius((cursor.x as f64, cursor.y as f64), radius).into_iter().collect();
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    for (biome, count) in counts {
+        info!("{:?}: {} tiles", biome, count);
+    }
+}
+
+// Shift + R toggles logging the biome at the center of each regenerated view, sampled on its own
+// like the cursor report, so scrubbing seeds shows where each one lands
+fn report_center_biome(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut display: ResMut<DisplayConfig>,
+    mapgen: Option<Res<MapGen>>,
+) {
+    if bindings.just_pressed(&keyboard, Action::ToggleCenterBiomeReport) {
+        let report = !display.report_center_biome;
+        display.report_center_biome = report;
+        info!("Center biome report: {}", if report { "on" } else { "off" });
+    }
 
-        let x = (x / self.zoom) + (map_axis_len * self.zoom * self.x_shift);
-        let y = (y / self.zoom) + (map_axis_len * self.zoom * self.y_shift);
+    let mapgen = match mapgen {
+        Some(mapgen) if display.report_center_biome && mapgen.is_changed() => mapgen,
+        _ => return,
+    };
 
-        let y_dis = y / map_axis_len / self.zoom;
-        let x_dis = x / map_axis_len / self.zoom;
-        let r_dis = ((y_dis * y_dis) + (x_dis * x_dis)).sqrt() / (2.0_f64).sqrt();
+    info!("Center: {}", mapgen.center_biome().name());
+}
 
-        let (height, absl_height) = self.get_heights(r_dis, x, y);
-        let partial_temp = self.get_partial_temp(absl_height, y_dis, DRY_ADB_LAPSE_RATE * 0.5, x, y);
-        let (percipitation, temperature) = self.get_percip_temp(absl_height, y_dis, partial_temp, x, y);
+// Shift that brings world coordinate world (on one axis) to the center of the regenerated view,
+// undoing the noise space x / zoom + map_axis_len * zoom * shift. Noise units and noise_axis_len are
+// world units and map_axis_len scaled alike, so the world ones give the same shift
+fn recentered_shift(shift: f64, world: f64, map_axis_len: f64, zoom: f64) -> f64 {
+    (shift + world / (map_axis_len * zoom * zoom)).clamp(-MAX_SHIFT, MAX_SHIFT)
+}
 
-        let height_clamp = height as f32 / 6000.0;
-        let temp_clamp = (temperature as f32 + 33.0) / 88.0;
-        let mut humidity_clamp = percipitation as f32;
+// Shift that keeps the noise position under world coordinate world (on one axis) in place as the zoom
+// changes, solving the noise space x / zoom + map_axis_len * zoom * shift at the new zoom
+fn zoomed_shift(shift: f64, world: f64, map_axis_len: f64, zoom: f64, new_zoom: f64) -> f64 {
+    let noise = world / zoom + map_axis_len * zoom * shift;
+    ((noise - world / new_zoom) / (map_axis_len * new_zoom)).clamp(-MAX_SHIFT, MAX_SHIFT)
+}
 
-        let height_color = Color::rgb(height_clamp, height_clamp, height_clamp);
-        let temperature_color = Color::rgb(temp_clamp, temp_clamp, temp_clamp);
-        let humidity_color = Color::rgb(humidity_clamp, humidity_clamp, humidity_clamp);
+// Up and Down zoom about the view center, or about the point under the cursor with zoom_to_cursor
+#[allow(clippy::too_many_arguments)]
+fn update_zoom(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Res<Windows>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    config: Res<MapConfig>,
+    mut zoom: ResMut<Zoom>,
+    mut x_shift: ResMut<XShift>,
+    mut y_shift: ResMut<YShift>,
+) {
+    let new_zoom = if bindings.just_pressed(&keyboard, Action::ZoomIn) {
+        zoom.0 + 0.1
+    } else if bindings.just_pressed(&keyboard, Action::ZoomOut) && zoom.0 > 0.2 {
+        zoom.0 - 0.1
+    } else {
+        return;
+    };
 
-        let mix_color  = Color::rgb(1.0 * temp_clamp, 1.0 * height_clamp, 0.0 * humidity_clamp);
+    let cursor = match (windows.get_primary(), camera.get_single()) {
+        (Some(window), Ok((transform, projection))) if config.zoom_to_cursor => {
+            cursor_world_position(window, transform, projection)
+        }
+        _ => None,
+    };
 
-        let biome: Biome = self.pick_biome(height, temperature, percipitation);
+    if let Some(cursor) = cursor {
+        x_shift.0 = zoomed_shift(x_shift.0, cursor.x as f64, config.map_axis_len(), zoom.0, new_zoom);
+        y_shift.0 = zoomed_shift(y_shift.0, cursor.y as f64, config.map_axis_len(), zoom.0, new_zoom);
+    }
 
-        let tile = self.pick_tile(biome, temperature, percipitation);
+    zoom.0 = new_zoom;
+}
 
-        tile
+// F regenerates the view centered on the tile under the cursor
+#[allow(clippy::too_many_arguments)]
+fn center_on_cursor(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Res<Windows>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    config: Res<MapConfig>,
+    zoom: Res<Zoom>,
+    mut x_shift: ResMut<XShift>,
+    mut y_shift: ResMut<YShift>,
+) {
+    if !bindings.just_pressed(&keyboard, Action::CenterOnCursor) {
+        return;
+    }
+
+    let cursor = match (windows.get_primary(), camera.get_single()) {
+        (Some(window), Ok((transform, projection))) => cursor_world_position(window, transform, projection),
+        _ => None,
+    };
+
+    let cursor = match cursor {
+        Some(cursor) => cursor,
+        None => return,
+    };
 
-        // Tile {
-        //     index: 0,
-        //     color: humidity_color,
-        // }
+    let (tile_x, tile_y) = config.world_to_tile(cursor.x as f64, cursor.y as f64);
+    let (world_x, world_y) = config.tile_to_world(tile_x, tile_y);
 
+    x_shift.0 = recentered_shift(x_shift.0, world_x, config.map_axis_len(), zoom.0);
+    y_shift.0 = recentered_shift(y_shift.0, world_y, config.map_axis_len(), zoom.0);
+}
+
+fn update_label_visibility(
+    show_labels: Res<ShowLabels>,
+    mut query: Query<&mut Visibility, With<LandmassLabel>>,
+) {
+    for mut visibility in query.iter_mut() {
+        visibility.is_visible = show_labels.0;
     }
 }
 
-impl NoiseMap {
-    fn get_value(&self, x: f64, y: f64) -> f64 {
-        let mut amplitude: f64 = 1.0;
-        let mut frequency: f64 = 1.0;
-        let mut accumulation: f64 = 0.0;
+// Cloud cover over a tile at a GenTime, 0 for clear sky to 1 under full cloud. The cloud field drifts
+// downwind by clouds.speed tiles a second
+fn cloud_cover(noise: &NoiseMap, clouds: &Clouds, upwind: (f64, f64), coords: (i32, i32), time: f32) -> f32 {
+    let time = clouds.frozen_at.unwrap_or(time) as f64;
+    let drift = clouds.speed as f64 * time;
+    let x = (coords.0 as f64 + upwind.0 * drift) / clouds.size.max(1.0);
+    let y = (coords.1 as f64 + upwind.1 * drift) / clouds.size.max(1.0);
 
-        for i in 0..self.octaves {
-            let sample_x = x / self.scale * frequency;
-            let sample_y = y / self.scale * frequency;
+    // Two octaves at persistance 0.25 top out at 1.25
+    let value = noise.get_value((0.0, 0.0), x, y) / 1.25;
+    ((value - (1.0 - clouds.density as f64)) / CLOUD_EDGE).clamp(0.0, 1.0) as f32
+}
 
-            let value = (self.noise_map.get([sample_x, sample_y]) + 1.0) / 2.0;
-            accumulation += value * amplitude;
+// Recasts the shadows over the whole grid when the map or the sun changes, the expensive part of them
+fn update_shadows(display: Res<DisplayConfig>, grid: Option<Res<MapGrid>>, mut shadows: ResMut<CastShadows>) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
 
-            amplitude *= self.persistance;
-            frequency *= self.lacunarity;
+    if !grid.is_changed() && !display.is_changed() {
+        return;
+    }
+
+    let cast = match display.shadows {
+        Some(sun) => {
+            let heights: Vec<f64> = grid.samples.iter().map(|sample| sample.height).collect();
+            cast_shadows(&heights, grid.width() as usize, sun.tile_length * grid.stride as f64, &sun)
         }
+        None => Vec::new(),
+    };
 
-        if accumulation > 1.0 {
-            accumulation = 1.0
+    // Only a change in the shadows themselves has every tile recolored
+    if cast != shadows.0 {
+        shadows.0 = cast;
+    }
+}
+
+// Recolors new tiles, or every tile once the overlay settings or debug view change.
+// Works only from the samples cached in MapTile, so switching views never samples the noise again
+#[allow(clippy::too_many_arguments)]
+fn update_tile_colors(
+    display: Res<DisplayConfig>,
+    config: Res<MapConfig>,
+    cloud_noise: Res<CloudNoise>,
+    shadows: Res<CastShadows>,
+    grid: Option<Res<MapGrid>>,
+    show_climate_zones: Res<ShowClimateZones>,
+    overrides: Res<BiomeOverrides>,
+    isolated: Res<IsolatedBiome>,
+    gen_time: Res<GenTime>,
+    mut fade_in: ResMut<MapFadeIn>,
+    mut query: Query<(&MapTile, ChangeTrackers<MapTile>, &mut TextureAtlasSprite)>,
+) {
+    let refresh = display.is_changed() || show_climate_zones.is_changed() || overrides.is_changed()
+        || isolated.is_changed() || shadows.is_changed() || fade_in.0.is_some()
+        || (display.day_night && display.frozen_time_of_day.is_none() && gen_time.is_changed())
+        || (display.clouds.is_some_and(|clouds| clouds.frozen_at.is_none()) && gen_time.is_changed());
+    let tint = match display.frozen_time_of_day {
+        Some(phase) => day_tint(phase * display.day_length, display.day_length),
+        None => day_tint(gen_time.elapsed, display.day_length),
+    };
+    let fade_alpha = fade_in.0.map(|start| fade_progress(start, gen_time.elapsed, display.seed_fade));
+
+    for (tile, tracker, mut sprite) in query.iter_mut() {
+        if !refresh && !tracker.is_added() {
+            continue;
         }
 
-        if accumulation < 0.0 {
-            accumulation = 0.0
+        // A mostly painted tile counts as the painted biome, like its atlas index
+        let (index, mut color, biome) = match overrides.0.get(&tile.cell) {
+            Some(&(biome, strength)) => {
+                let mut painted = biome.tile(tile.sample.temperature, tile.sample.percipitation);
+                let (index, shown) = if strength >= 0.5 {
+                    (painted.index, biome)
+                } else {
+                    (tile.tile.index, tile.sample.biome)
+                };
+                painted.color.set_a(strength);
+                (index, blend(tile.tile.color, painted.color), shown)
+            }
+            None => (tile.tile.index, tile.tile.color, tile.sample.biome),
+        };
+
+        color = display.color_space.interpret(color);
+        color = adjust(color, display.brightness, display.contrast, display.saturation);
+
+        match display.debug_view {
+            DebugView::Biome => {}
+            DebugView::Height => color = height_color(tile.sample.height, display.height_exaggeration),
+            DebugView::Combined => {
+                color = combined_color(tile.sample.height, tile.sample.temperature, tile.sample.percipitation)
+            }
+            DebugView::MovementCost => color = cost_color(tile.movement_cost),
+            DebugView::Falloff => color = falloff_color(tile.sample.falloff),
         }
 
-        accumulation
+        if let Some(settings) = display.haze.filter(|_| matches!(display.debug_view, DebugView::Biome | DebugView::Height)) {
+            color = haze(color, tile.sample.height, &settings);
+        }
+
+        if let (Some(sun), Some(grid)) = (display.shadows, &grid) {
+            let shadowed = grid.index(tile.coords.0, tile.coords.1).and_then(|index| shadows.0.get(index)).copied().unwrap_or(false);
+            if shadowed && matches!(display.debug_view, DebugView::Biome | DebugView::Height) {
+                let light = 1.0 - sun.darkness.clamp(0.0, 1.0);
+                color = modulate(color, Color::rgb(light, light, light));
+            }
+        }
+
+        if let Some(clouds) = display.clouds.filter(|_| display.debug_view == DebugView::Biome) {
+            let cover = cloud_cover(&cloud_noise.0, &clouds, config.wind.upwind(), tile.coords, gen_time.elapsed);
+            color = blend(color, Color::rgba(1.0, 1.0, 1.0, cover * clouds.opacity));
+        }
+
+        if isolated.0.is_some_and(|isolated| isolated != biome) {
+            color = ISOLATION_GRAY;
+        }
+
+        if show_climate_zones.0 {
+            color = blend(color, display.climate_zone_colors[tile.sample.climate_zone() as usize]);
+        }
+
+        if display.day_night {
+            color = modulate(color, tint);
+        }
+
+        if let Some(alpha) = fade_alpha {
+            color.set_a(alpha);
+        }
+
+        sprite.index = index;
+        sprite.color = dither(color, tile.coords, display.dither_strength);
+    }
+
+    // The finished fade still ran once above, leaving every tile fully opaque
+    if fade_alpha.is_some_and(|alpha| alpha >= 1.0) {
+        fade_in.0 = None;
     }
 }
 
-impl Biome {
+// Share of a fade of the given duration done since start, 1 once done or if the fade was turned off
+fn fade_progress(start: f32, now: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        return 1.0;
+    }
 
-    fn calculate_grass_color(temperature: f64, percipitation: f64) -> Color {
+    ((now - start) / duration).clamp(0.0, 1.0)
+}
 
-        let percip_p = (percipitation + 10000.0) / 10000.0;
-        let temp_p = temperature / 30.0;
+// Fades the replaced map's tiles out over the new map, despawning them once invisible
+fn fade_out_replaced_tiles(
+    mut commands: Commands,
+    display: Res<DisplayConfig>,
+    gen_time: Res<GenTime>,
+    mut query: Query<(Entity, &FadingOut, &mut TextureAtlasSprite, &mut Transform)>,
+) {
+    for (entity, fading, mut sprite, mut transform) in query.iter_mut() {
+        let progress = fade_progress(fading.0, gen_time.elapsed, display.seed_fade);
+        if progress >= 1.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
 
-        let mut deadness = ((1.0 - percip_p) + temp_p) / 2.0;
-        if deadness < 0.0 {
-            deadness = 0.0;
+        transform.translation.z = FADING_OUT_Z;
+        sprite.color.set_a(1.0 - progress);
+    }
+}
+
+// With pixel_snap, tile positions are rounded to whole screen pixels at the camera's magnification so
+// neighbouring sprites meet on the same pixel instead of shimmering or leaving hairline seams. Helps at
+// fractional tile_scale or mouse wheel magnification, changes nothing when tiles already cover whole pixels
+fn snap_tiles_to_pixels(
+    config: Res<MapConfig>,
+    camera: Query<&OrthographicProjection, With<Camera>>,
+    added: Query<(), Added<MapTile>>,
+    mut tiles: Query<(&MapTile, &mut Transform)>,
+    mut snapped_scale: Local<Option<f32>>,
+) {
+    let scale = match camera.get_single() {
+        Ok(projection) if config.pixel_snap => Some(projection.scale),
+        _ => None,
+    };
+
+    // Spawned positions are already unsnapped, only turning snapping off needs them restored
+    if scale.is_none() && snapped_scale.is_none() {
+        return;
+    }
+
+    if scale == *snapped_scale && added.is_empty() && !config.is_changed() {
+        return;
+    }
+    *snapped_scale = scale;
+
+    let snap = |coord: f64| match scale {
+        Some(scale) => (coord as f32 / scale).round() * scale,
+        None => coord as f32,
+    };
+
+    for (tile, mut transform) in tiles.iter_mut() {
+        let (x, y) = config.tile_to_world(tile.coords.0, tile.coords.1);
+        transform.translation.x = snap(x);
+        transform.translation.y = snap(y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::config::{ColorSpace, InversionBand, PercipitationScale, Quality, SeedFormat, Wind, ZoomScaling};
+    use crate::error::Error;
+    use crate::export;
+    use crate::test_support::*;
+
+    const REFERENCE_PATH: &str = "assets/reference/map.png";
+
+    // Largest per-channel difference a pixel may have before it counts as changed
+    const CHANNEL_TOLERANCE: u8 = 2;
+
+    fn render_reference() -> RgbaImage {
+        let config = reference_config();
+        export::grid_image(&reference_map_gen(&config).generate_grid(), config.export_gamma, config.export_tile_pixels)
+    }
+
+    // Pins the generator's visual output. After an intended change, rewrite the image with
+    // cargo test write_reference -- --ignored
+    #[test]
+    fn reference_map_matches_committed_image() {
+        let expected = image::open(REFERENCE_PATH).unwrap().to_rgba8();
+        let actual = render_reference();
+        assert_eq!(actual.dimensions(), expected.dimensions());
+
+        let changed = actual.pixels().zip(expected.pixels())
+            .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+            .count();
+        assert_eq!(changed, 0, "{} pixels differ from {}", changed, REFERENCE_PATH);
+    }
+
+    #[test]
+    #[ignore]
+    fn write_reference() {
+        render_reference().save(REFERENCE_PATH).unwrap();
+    }
+
+    // Within an inversion band temperature rises with altitude, outside it falls as usual, and without
+    // bands it falls all the way up
+    #[test]
+    fn temperature_rises_with_altitude_inside_an_inversion_band() {
+        let (bottom, top) = (1000.0, 2000.0);
+        let inverted_config = MapConfig { inversions: vec![InversionBand { bottom, top }], ..reference_config() };
+        let plain_config = reference_config();
+        let (inverted, plain) = (reference_map_gen(&inverted_config), reference_map_gen(&plain_config));
+        let temperature = |mapgen: &MapGen, height: f64| mapgen.get_partial_temp(height, 0.2, DRY_ADB_LAPSE_RATE, 0.3, -0.1);
+
+        for step in 0..30 {
+            let (low, high) = (step as f64 * 100.0, (step + 1) as f64 * 100.0);
+            let inside = low >= bottom && high <= top;
+
+            assert_eq!(temperature(&inverted, high) > temperature(&inverted, low), inside, "{} to {} m", low, high);
+            assert!(temperature(&plain, high) < temperature(&plain, low), "{} to {} m without a band", low, high);
         }
+    }
 
-        let mut alpine_p = 0.6 * (1.0 - temp_p) + 0.4 * (deadness);
-        if alpine_p < 0.0 {
-            alpine_p = 0.0;
+    // FNV-1a's published values, so a seed typed once names the same world on every build
+    #[test]
+    fn text_seeds_hash_to_fixed_values() {
+        assert_eq!(Seed::from_str_hashed("").0, 0x811c9dc5);
+        assert_eq!(Seed::from_str_hashed("a").0, 0xe40c292c);
+        assert_eq!(Seed::from_str_hashed("misty fjords").0, 0xc7dcfe05);
+    }
+
+    // The tile at grid (0, 0) samples the world origin exactly, on even and odd map sizes alike
+    #[test]
+    fn center_tile_samples_the_world_origin() {
+        for map_size in [64, 65] {
+            let config = MapConfig { map_size, ..reference_config() };
+            let mapgen = reference_map_gen(&config);
+            let grid = mapgen.generate_grid();
+
+            assert_eq!(mapgen.tile_position(0, 0), (0.0, 0.0), "map_size {}", map_size);
+            assert!(grid.samples[grid.index(0, 0).unwrap()] == mapgen.sample_tile_at_world(0.0, 0.0).1, "map_size {}", map_size);
         }
-        
-        let green_color = GREEN_COLOR.mul(deadness as f32);
-        let yellow_color = YELLOW_COLOR.mul(percip_p as f32);
-        let blue_color = BLUE_COLOR.mul(alpine_p as f32);
+    }
 
-        let grass_color = green_color.add(yellow_color).mul(0.5);
-        
-        grass_color
+    // The reported center biome is what pick_biome makes of the height and climate at the view's
+    // center, on a few seeds and a panned view
+    #[test]
+    fn center_biome_is_picked_at_the_view_center() {
+        let config = reference_config();
+        for (seed, x_shift, y_shift) in [(REFERENCE_SEED, 0.0, 0.0), (REFERENCE_SEED + 1, 0.0, 0.0), (REFERENCE_SEED, 0.3, -0.2)] {
+            let mapgen = MapGen::new(seed, 1.0, x_shift, y_shift, (0.0, 0.0), &config);
+            let (x, y) = mapgen.noise_position(0.0, 0.0);
+            let (_, center) = mapgen.get_climate_tile(mapgen.height_at(x, y), x, y);
+            let picked = mapgen.pick_biome(center.height, center.temperature, mapgen.classification_moisture(center.percipitation));
+
+            assert_eq!(mapgen.center_biome(), picked, "seed {} at ({}, {})", seed, x_shift, y_shift);
+        }
     }
 
-    fn ocean_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 3,
-            color: Color::rgb(0.0, 0.2, 0.8),
+    // Warm, very wet land just above sea level is marsh, the same climate higher up or under the simple
+    // model is not
+    #[test]
+    fn low_warm_wet_land_is_marsh() {
+        let config = reference_config();
+        let mapgen = reference_map_gen(&config);
+        let (temperature, percipitation) = (config.marsh_min_temperature + 8.0, config.marsh_min_percipitation + 1000.0);
+
+        assert_eq!(mapgen.pick_biome(config.marsh_max_height / 2.0, temperature, percipitation), Biome::Marsh);
+        assert_ne!(mapgen.pick_biome(config.marsh_max_height + 200.0, temperature, percipitation), Biome::Marsh);
+        assert_ne!(mapgen.pick_biome(config.marsh_max_height / 2.0, config.marsh_min_temperature - 5.0, percipitation), Biome::Marsh);
+
+        let simple = MapConfig { biome_model: BiomeModel::Simple, ..reference_config() };
+        assert_ne!(reference_map_gen(&simple).pick_biome(config.marsh_max_height / 2.0, temperature, percipitation), Biome::Marsh);
+    }
+
+    // Hot, nearly rainless lowland is a salt flat, while the same climate above salt_flat_max_height
+    // stays desert
+    #[test]
+    fn hot_rainless_lowland_is_salt_flat() {
+        let config = reference_config();
+        let mapgen = reference_map_gen(&config);
+        let (temperature, percipitation) = (config.salt_flat_min_temperature + 10.0, config.salt_flat_max_percipitation / 2.0);
+
+        assert_eq!(mapgen.pick_biome(config.salt_flat_max_height / 2.0, temperature, percipitation), Biome::SaltFlat);
+        let above = mapgen.pick_biome(config.salt_flat_max_height + 100.0, temperature, percipitation);
+        assert!(format!("{:?}", above).ends_with("Desert"), "{:?} above the salt flat height", above);
+    }
+
+    // Warm, very wet lowland grows mangroves on a tile next to the sea, while the same climate a few
+    // tiles inland stays rain forest once marsh, which takes such land by default, is out of the way
+    #[test]
+    fn warm_wet_coast_is_mangrove() {
+        let config = MapConfig { marsh_min_percipitation: f64::INFINITY, ..reference_config() };
+        let mapgen = reference_map_gen(&config);
+        let grid = mapgen.generate_grid();
+        let distances = grid.coast_distances();
+        let tile_at = |distance: u32| {
+            let index = (0..grid.samples.len()).find(|&index| grid.samples[index].height > 0.0 && distances[index] == Some(distance)).unwrap();
+            let (x, y) = grid.coords(index);
+            let (tile_x, tile_y) = mapgen.tile_position(x, y);
+            mapgen.noise_position(tile_x, tile_y)
+        };
+        let (height, temperature, percipitation) = (config.mangrove_max_height / 2.0, 27.0, 9000.0);
+
+        let (x, y) = tile_at(1);
+        assert!(mapgen.is_mangrove(height, temperature, percipitation, x, y));
+
+        let (x, y) = tile_at(4);
+        assert!(!mapgen.is_mangrove(height, temperature, percipitation, x, y));
+        assert_eq!(mapgen.pick_biome(height, temperature, percipitation), Biome::TropicalRainForest);
+    }
+
+    // Forest 50 m either side of the treeline lies wholly on its side of a clean contour, while a ragged
+    // treeline puts some of it on each side across a row of points
+    #[test]
+    fn ragged_treeline_departs_from_the_contour() {
+        let temperature = 10.0;
+        let lattice: Vec<(f64, f64)> = (0..40).map(|step| (step as f64 * 3.7 - 70.0, step as f64 * -2.3 + 40.0)).collect();
+        let above = |raggedness: f64, offset: f64| {
+            let config = MapConfig { treeline_raggedness: raggedness, ..reference_config() };
+            let mapgen = reference_map_gen(&config);
+            let height = config.treeline_height + config.treeline_warmth_rise * temperature + offset;
+            lattice.iter().filter(|&&(x, y)| mapgen.above_treeline(Biome::TemperateMoistForest, height, temperature, x, y)).count()
+        };
+
+        assert_eq!((above(0.0, 50.0), above(0.0, -50.0)), (lattice.len(), 0));
+        let (ragged_above, ragged_below) = (above(200.0, 50.0), above(200.0, -50.0));
+        assert!(ragged_above < lattice.len() && ragged_below > 0, "{} and {} of {} points above", ragged_above, ragged_below, lattice.len());
+    }
+
+    // Land above the alpine height is alpine whatever its climate, tropical heat and rain included, while
+    // the same climate just below it stays tropical
+    #[test]
+    fn high_land_is_alpine_even_in_the_tropics() {
+        let config = reference_config();
+        let mapgen = reference_map_gen(&config);
+        let (temperature, percipitation) = (27.0, 4000.0);
+
+        assert_eq!(mapgen.pick_biome(config.alpine_min_height + 1000.0, temperature, percipitation), Biome::Alpine);
+        assert_eq!(mapgen.pick_biome(config.alpine_min_height, temperature, percipitation), Biome::Alpine);
+        let below = mapgen.pick_biome(config.alpine_min_height - 1.0, temperature, percipitation);
+        assert!(format!("{:?}", below).starts_with("Tropical"), "{:?} just below the alpine height", below);
+    }
+
+    // Under the simple model whole maps only hold the biomes of its table, besides the ocean and beaches
+    // that height and slope decide, and a few seeds between them show most of the table
+    #[test]
+    fn simple_model_keeps_to_its_biomes() {
+        let config = MapConfig { biome_model: BiomeModel::Simple, ..reference_config() };
+        let table = BiomeTable::from_csv(SIMPLE_BIOME_CSV).unwrap();
+        let allowed: Vec<Biome> = table.rules.iter().map(|rule| rule.biome).chain([Biome::Ocean, Biome::Beach]).collect();
+
+        let mut seen = HashSet::new();
+        for seed in REFERENCE_SEED..REFERENCE_SEED + 3 {
+            let grid = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config).generate_grid();
+            for sample in &grid.samples {
+                assert!(allowed.contains(&sample.biome), "seed {} has {:?}", seed, sample.biome);
+                seen.insert(sample.biome);
+            }
         }
+        assert!(seen.len() >= 5, "only {:?}", seen);
     }
 
-    fn polar_desert_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 3,
-            color: Color::rgb(1.0, 1.0, 1.0),
+    // An app running just the recoloring system, over whatever tiles a test spawns into it
+    fn recolor_app(config: MapConfig, display: DisplayConfig) -> App {
+        let mut app = App::new();
+        app.insert_resource(config)
+            .insert_resource(display)
+            .insert_resource(CloudNoise(NoiseMap { noise_map: Perlin::new(CLOUD_SEED), octaves: 1, scale: 1.0, persistance: 1.0, lacunarity: 1.0 }))
+            .insert_resource(CastShadows::default())
+            .insert_resource(ShowClimateZones(false))
+            .insert_resource(BiomeOverrides::default())
+            .insert_resource(IsolatedBiome::default())
+            .insert_resource(GenTime { elapsed: 0.0, paused: true, speed: 1.0 })
+            .insert_resource(MapFadeIn::default())
+            .add_system(update_tile_colors);
+        app
+    }
+
+    // Switching the debug view recolors the spawned tiles from their cached samples, without classifying
+    // a single tile from the noise again
+    #[test]
+    fn switching_views_does_not_resample_the_noise() {
+        let config = reference_config();
+        let mapgen = reference_map_gen(&config);
+        let mut app = recolor_app(config, DisplayConfig::default());
+
+        let grid = mapgen.generate_grid();
+        for (index, (&tile, &sample)) in grid.tiles.iter().zip(&grid.samples).enumerate() {
+            let coords = grid.coords(index);
+            app.world.spawn((MapTile { coords, cell: coords, tile, sample, movement_cost: 1.0 }, TextureAtlasSprite::new(tile.index)));
+        }
+        let streamed = mapgen.climate_samples();
+        assert!(streamed > 0);
+
+        app.insert_resource(mapgen);
+        app.update();
+
+        app.world.resource_mut::<DisplayConfig>().debug_view = DebugView::Height;
+        app.update();
+
+        let display = app.world.resource::<DisplayConfig>().clone();
+        let mut tiles = app.world.query::<(&MapTile, &TextureAtlasSprite)>();
+        for (tile, sprite) in tiles.iter(&app.world) {
+            let expected = dither(height_color(tile.sample.height, display.height_exaggeration), tile.coords, display.dither_strength);
+            assert_eq!(sprite.color, expected, "tile {:?} not recolored", tile.coords);
+        }
+        assert_eq!(app.world.resource::<MapGen>().climate_samples(), streamed);
+    }
+
+    // Sprite colors by tile after a frame at the given GenTime
+    fn colors_at(app: &mut App, elapsed: f32) -> Vec<((i32, i32), Color)> {
+        app.world.resource_mut::<GenTime>().elapsed = elapsed;
+        app.update();
+
+        let mut sprites = app.world.query::<(&MapTile, &TextureAtlasSprite)>();
+        let mut colors: Vec<((i32, i32), Color)> = sprites.iter(&app.world).map(|(tile, sprite)| (tile.coords, sprite.color)).collect();
+        colors.sort_by_key(|(coords, _)| *coords);
+        colors
+    }
+
+    // Frozen clouds and a frozen time of day tint every tile the same on every frame, however far the
+    // clock runs on, while unfrozen they move with it
+    #[test]
+    fn frozen_clouds_and_daylight_hold_still() {
+        let config = reference_config();
+        let clouds = Clouds { density: 0.5, speed: 2.0, size: 4.0, opacity: 0.5, frozen_at: Some(3.0) };
+        let display = DisplayConfig { clouds: Some(clouds), day_night: true, frozen_time_of_day: Some(0.3), ..DisplayConfig::default() };
+        let mut app = recolor_app(config.clone(), display);
+        let grid = reference_map_gen(&config).generate_grid();
+        for (index, (&tile, &sample)) in grid.tiles.iter().zip(&grid.samples).enumerate() {
+            let coords = grid.coords(index);
+            app.world.spawn((MapTile { coords, cell: coords, tile, sample, movement_cost: 1.0 }, TextureAtlasSprite::new(tile.index)));
+        }
+
+        let frozen = colors_at(&mut app, 0.0);
+        for elapsed in [0.5, 17.0, 44.0] {
+            assert!(colors_at(&mut app, elapsed) == frozen, "the tiles changed at {} s", elapsed);
+        }
+
+        let mut display = app.world.resource_mut::<DisplayConfig>();
+        display.clouds = Some(Clouds { frozen_at: None, ..clouds });
+        display.frozen_time_of_day = None;
+        assert!(colors_at(&mut app, 17.0) != colors_at(&mut app, 44.0));
+    }
+
+    // A mid-gray tile keeps its channels as sRGB, while read as linear light it shows as the lighter
+    // sRGB gray encoding 0.5 linear
+    #[test]
+    fn linear_color_space_lightens_mid_gray() {
+        let sprite_color = |color_space: ColorSpace| {
+            let mut app = recolor_app(reference_config(), DisplayConfig { color_space, ..Default::default() });
+            let tile = Tile { index: 0, color: Color::rgb(0.5, 0.5, 0.5) };
+            let sample = TileSample { height: 100.0, temperature: 10.0, percipitation: 500.0, falloff: 1.0, biome: Biome::TemperateSteppe };
+            let entity = app.world.spawn((MapTile { coords: (0, 0), cell: (0, 0), tile, sample, movement_cost: 1.0 }, TextureAtlasSprite::new(0))).id();
+            app.update();
+            app.world.get::<TextureAtlasSprite>(entity).unwrap().color
+        };
+
+        let (srgb, linear) = (sprite_color(ColorSpace::Srgb), sprite_color(ColorSpace::Linear));
+        assert_eq!(srgb.as_rgba_f32(), [0.5, 0.5, 0.5, 1.0]);
+        assert!((linear.as_linear_rgba_f32()[0] - 0.5).abs() < 1e-4);
+        for channel in &linear.as_rgba_f32()[..3] {
+            assert!((channel - 0.735).abs() < 0.001, "linear mid-gray shows as {}", channel);
+        }
+    }
+
+    // Noise space point of world coordinate (x, y), the view's base included
+    fn absolute_noise(mapgen: &MapGen, x: f64, y: f64) -> (f64, f64) {
+        let (noise_x, noise_y) = mapgen.noise_position(x, y);
+        (mapgen.noise_base.0 + noise_x, mapgen.noise_base.1 + noise_y)
+    }
+
+    fn assert_same_point(actual: (f64, f64), expected: (f64, f64)) {
+        assert!((actual.0 - expected.0).abs() < 1e-6 && (actual.1 - expected.1).abs() < 1e-6,
+            "noise point {:?}, expected {:?}", actual, expected);
+    }
+
+    // Recentering on a world coordinate brings the noise under it to the center of the regenerated
+    // view, and a coordinate far past the map clamps to the largest shift
+    #[test]
+    fn recentered_shifts_center_the_picked_point() {
+        let config = reference_config();
+        let (zoom, x_shift, y_shift) = (2.0, 0.1, -0.2);
+        let mapgen = MapGen::new(REFERENCE_SEED, zoom, x_shift, y_shift, (0.0, 0.0), &config);
+        let (world_x, world_y) = (37.0, -50.0);
+
+        let recentered = MapGen::new(REFERENCE_SEED, zoom,
+            recentered_shift(x_shift, world_x, config.map_axis_len(), zoom),
+            recentered_shift(y_shift, world_y, config.map_axis_len(), zoom),
+            (0.0, 0.0), &config);
+        assert_same_point(absolute_noise(&recentered, 0.0, 0.0), absolute_noise(&mapgen, world_x, world_y));
+
+        assert_eq!(recentered_shift(x_shift, 100.0 * config.map_axis_len(), config.map_axis_len(), zoom), MAX_SHIFT);
+    }
+
+    // Blending just the map seed at weight 1 reproduces the unblended map exactly
+    #[test]
+    fn single_seed_blend_matches_the_plain_seed() {
+        let plain = reference_map_gen(&reference_config()).generate_grid();
+        let blended_config = MapConfig { seed_blend: vec![(REFERENCE_SEED, 1.0)], ..reference_config() };
+        assert!(blended_config.validate().is_ok());
+        let blended = reference_map_gen(&blended_config).generate_grid();
+
+        for index in 0..plain.samples.len() {
+            assert!(plain.tiles[index] == blended.tiles[index] && plain.samples[index] == blended.samples[index],
+                "blended tile {:?} differs", plain.coords(index));
+        }
+    }
+
+    // Ten seed steps a frame apart, then a second of frames without any, regenerate once with the
+    // debounce and on every step without it. schedule_map clears the pending seed once it regenerates
+    #[test]
+    fn rapid_seed_changes_regenerate_once() {
+        let regenerations = |debounce: f32| {
+            let mut pending = PendingSeed::default();
+            let mut regenerations = 0;
+
+            for frame in 0..70 {
+                if pending.due(frame < 10, frame as f32 / 60.0, debounce) {
+                    pending.0 = None;
+                    regenerations += 1;
+                }
+            }
+            regenerations
+        };
+
+        assert_eq!(regenerations(0.3), 1);
+        assert_eq!(regenerations(0.0), 10);
+    }
+
+    // Just past the boundary where tropical desert scrub gives way to computed grass colors, a tile's color
+    // lies between the desert sand and the grass it would otherwise have, while land well past the
+    // blend zone keeps its grass color
+    #[test]
+    fn boundary_tiles_blend_desert_sand_into_grass() {
+        let config = reference_config();
+        let mapgen = reference_map_gen(&config);
+        let (height, temperature) = (500.0, 26.0);
+        let boundary = (0..4000).map(|step| step as f64)
+            .find(|&percipitation| !is_desert_tile(&mapgen.pick_biome(height, temperature, percipitation).tile(temperature, percipitation)))
+            .unwrap();
+
+        let tile = |percipitation: f64| {
+            let biome = mapgen.pick_biome(height, temperature, percipitation);
+            (mapgen.pick_tile(biome, height, temperature, percipitation, percipitation), biome.tile(temperature, percipitation))
+        };
+
+        let (blended, grass) = tile(boundary);
+        assert!(blended.color != grass.color);
+        for (channel, (sand, grass)) in blended.color.as_rgba_f32()[..3].iter()
+            .zip(DESERT_SAND_COLOR.as_rgba_f32()[..3].iter().zip(&grass.color.as_rgba_f32()[..3])) {
+            assert!(*channel >= sand.min(*grass) - 1e-6 && *channel <= sand.max(*grass) + 1e-6,
+                "{} outside sand {} and grass {}", channel, sand, grass);
+        }
+
+        let (unblended, grass) = tile(boundary + 2.0 * config.desert_blend_width);
+        assert!(unblended.color == grass.color);
+    }
+
+    // A world seeded from text shows that text in the original format and its hash in the others, and a
+    // numeric seed has no text to show
+    #[test]
+    fn text_seeded_worlds_show_their_text() {
+        let seed = Seed::from_str_hashed("misty fjords");
+        assert_eq!(SeedFormat::Original.format(&seed), "\"misty fjords\"");
+        assert_eq!(SeedFormat::Decimal.format(&seed), seed.0.to_string());
+        assert_eq!(SeedFormat::Hex.format(&seed), format!("{:#010x}", seed.0));
+
+        assert_eq!(SeedFormat::Original.format(&Seed::number(829201)), "829201");
+    }
+
+    // With all weight on the noise, humidity is the humidity noise wherever the point is, whatever its
+    // height, latitude or warmth, while the default weights make those count
+    #[test]
+    fn noise_only_weights_leave_humidity_to_the_noise() {
+        let noise_config = MapConfig { humidity_weights: (0.0, 0.0, 1.0), ..reference_config() };
+        let default_config = reference_config();
+        let (noise_only, default) = (reference_map_gen(&noise_config), reference_map_gen(&default_config));
+        let conditions = [(0.0, 0.0, 28.0), (400.0, 0.1, 28.0), (2500.0, 0.6, -5.0), (800.0, -0.9, 12.0)];
+
+        for (x, y) in [(0.3, -0.1), (-12.0, 40.0), (75.0, 3.5)] {
+            let noise = noise_only.humidity_noise.get_value(noise_only.noise_base, x, y);
+            for (height, y_dis, temperature) in conditions {
+                assert_eq!(noise_only.humidity(height, y_dis, temperature, x, y), noise);
+            }
+
+            let humidities: Vec<f64> = conditions.iter().map(|&(height, y_dis, temperature)| default.humidity(height, y_dis, temperature, x, y)).collect();
+            assert!(humidities.iter().any(|humidity| *humidity != humidities[0]));
+        }
+    }
+
+    // Three tiles either side of the map's highest tile, a west wind leaves the east side drier than the
+    // west and an east wind turns that around
+    #[test]
+    fn wind_direction_moves_the_rain_shadow() {
+        let grid = reference_map_gen(&reference_config()).generate_grid();
+        let peak = (0..grid.samples.len()).max_by(|&a, &b| grid.samples[a].height.total_cmp(&grid.samples[b].height)).unwrap();
+        let (x, y) = grid.coords(peak);
+
+        let shadows = |direction| {
+            let config = MapConfig { wind: Wind { direction, strength: 1.0 }, ..reference_config() };
+            let mapgen = reference_map_gen(&config);
+            let shadow = |x: i32| {
+                let (tile_x, tile_y) = mapgen.tile_position(x, y);
+                let (noise_x, noise_y) = mapgen.noise_position(tile_x, tile_y);
+                mapgen.rain_shadow(mapgen.height_at(noise_x, noise_y), noise_x, noise_y)
+            };
+            (shadow(x - 3 * grid.stride), shadow(x + 3 * grid.stride))
+        };
+
+        let (west, east) = shadows(270.0);
+        assert!(east < west, "under a west wind the east side keeps {} of its rain, the west {}", east, west);
+        let (west, east) = shadows(90.0);
+        assert!(west < east, "under an east wind the west side keeps {} of its rain, the east {}", west, east);
+    }
+
+    // Mean and standard deviation of the humidity noise over a lattice of the map's tiles
+    fn humidity_spread(config: &MapConfig) -> (f64, f64) {
+        let mapgen = reference_map_gen(config);
+        let half = config.map_size as i32 / 2;
+        let values: Vec<f64> = (-half..=half).step_by(2)
+            .flat_map(|y| (-half..=half).step_by(2).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (tile_x, tile_y) = mapgen.tile_position(x, y);
+                let (noise_x, noise_y) = mapgen.noise_position(tile_x, tile_y);
+                mapgen.humidity_noise.get_value(mapgen.noise_base, noise_x, noise_y)
+            })
+            .collect();
+
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64;
+        (mean, variance.sqrt())
+    }
+
+    // The default humidity noise varies across the map rather than lying flat, and since its octaves are
+    // summed without normalizing, a higher persistance lifts the whole field
+    #[test]
+    fn humidity_noise_varies_across_the_map() {
+        let (mean, deviation) = humidity_spread(&reference_config());
+        assert!(deviation > 0.1, "the humidity noise only deviates by {}", deviation);
+
+        let (persistent_mean, _) = humidity_spread(&MapConfig { humidity_persistance: 0.35, ..reference_config() });
+        assert!(persistent_mean > mean, "a persistance of 0.35 gives a mean of {}, down from {}", persistent_mean, mean);
+    }
+
+    // Orientations are the same on every call for a seed and tile, vary across the map and between
+    // seeds, and leave directional biomes and maps without flips upright
+    #[test]
+    fn tile_flips_are_reproducible() {
+        let tiles: Vec<(i32, i32)> = (-8..8).flat_map(|y| (-8..8).map(move |x| (x, y))).collect();
+        let orientations = |seed: u32| tiles.iter().map(|&(x, y)| tile_orientation(seed, x, y)).collect::<Vec<_>>();
+
+        let first = orientations(REFERENCE_SEED);
+        assert_eq!(first, orientations(REFERENCE_SEED));
+        assert_ne!(first, orientations(REFERENCE_SEED + 1));
+        assert!(first.iter().any(|orientation| *orientation != first[0]));
+
+        let config = MapConfig { tile_flips: true, directional_biomes: [Biome::Beach].into_iter().collect(), ..reference_config() };
+        let unflipped = MapConfig { tile_flips: false, ..config.clone() };
+        for (&(x, y), &orientation) in tiles.iter().zip(&first) {
+            assert_eq!(configured_orientation(&config, REFERENCE_SEED, x, y, Biome::TemperateSteppe), orientation);
+            assert_eq!(configured_orientation(&config, REFERENCE_SEED, x, y, Biome::Beach), TileOrientation::default());
+            assert_eq!(configured_orientation(&unflipped, REFERENCE_SEED, x, y, Biome::TemperateSteppe), TileOrientation::default());
+        }
+    }
+
+    // A single marsh tile inside steppe is absorbed by one pass, while the fixed ocean and beach tiles
+    // stay put even where steppe surrounds them, and no passes leave the grid as it was
+    #[test]
+    fn majority_filter_absorbs_a_single_tile_island() {
+        let (s, m, o, b) = (Biome::TemperateSteppe, Biome::Marsh, Biome::Ocean, Biome::Beach);
+        let grid = vec![
+            s, s, s, s, o,
+            s, s, s, b, o,
+            s, s, m, s, o,
+            s, s, s, b, o,
+            s, s, s, s, o,
+        ];
+
+        let mut unfiltered = grid.clone();
+        majority_filter(&mut unfiltered, 5, 1, 0);
+        assert_eq!(unfiltered, grid);
+
+        let mut filtered = grid.clone();
+        majority_filter(&mut filtered, 5, 1, 1);
+        let mut expected = grid;
+        expected[12] = s;
+        assert_eq!(filtered, expected);
+    }
+
+    // A lenient minimum takes the first seed at once, while one no map can meet tries every attempt and
+    // settles for the landiest seed it saw
+    #[test]
+    fn land_reseed_passes_lenient_and_exhausts_strict_minimums() {
+        let lenient = MapConfig { min_land_fraction: 0.01, max_reseed_attempts: 5, ..reference_config() };
+        assert_eq!(seed_with_land(REFERENCE_SEED, 1.0, 0.0, 0.0, (0.0, 0.0), &lenient), (REFERENCE_SEED, 1));
+
+        let strict = MapConfig { min_land_fraction: 1.0, ..lenient.clone() };
+        let (seed, attempts) = seed_with_land(REFERENCE_SEED, 1.0, 0.0, 0.0, (0.0, 0.0), &strict);
+        assert_eq!(attempts, strict.max_reseed_attempts);
+        let land = |seed: u32| MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &strict).land_fraction();
+        assert!(land(seed) >= land(REFERENCE_SEED));
+    }
+
+    // Zooming in and back out keeps the noise under a fixed cursor where it was
+    #[test]
+    fn zooming_keeps_the_point_under_the_cursor() {
+        let config = reference_config();
+        let (cursor_x, cursor_y) = (-120.0, 75.0);
+        let mut view = (1.0, 0.05, -0.1);
+
+        for new_zoom in [1.1, 1.2, 1.1, 1.0] {
+            let (zoom, x_shift, y_shift) = view;
+            let before = MapGen::new(REFERENCE_SEED, zoom, x_shift, y_shift, (0.0, 0.0), &config);
+            view = (
+                new_zoom,
+                zoomed_shift(x_shift, cursor_x, config.map_axis_len(), zoom, new_zoom),
+                zoomed_shift(y_shift, cursor_y, config.map_axis_len(), zoom, new_zoom),
+            );
+            let after = MapGen::new(REFERENCE_SEED, view.0, view.1, view.2, (0.0, 0.0), &config);
+
+            assert_same_point(absolute_noise(&after, cursor_x, cursor_y), absolute_noise(&before, cursor_x, cursor_y));
+        }
+    }
+
+    // The default config can reach the everyday biomes, frozen ones included. Ice sheets wetter than the
+    // cap lets frozen air hold are excluded, until orographic uplift raises the cap on high ground
+    #[test]
+    fn reachable_biomes_follow_the_cap() {
+        let reachable = reference_map_gen(&reference_config()).reachable_biomes();
+        for biome in [Biome::Ocean, Biome::Beach, Biome::PolarDesert, Biome::TemperateSteppe, Biome::TropicalRainForest, Biome::Marsh, Biome::Alpine] {
+            assert!(reachable.contains(&biome), "{:?} is unreachable", biome);
+        }
+
+        let wet_ice = MapConfig { ice_sheet_min_percipitation: 500.0, ..reference_config() };
+        assert!(!reference_map_gen(&wet_ice).reachable_biomes().contains(&Biome::IceSheet));
+        let uplifted = MapConfig { orographic_cap: 1.0, ..wet_ice };
+        assert!(reference_map_gen(&uplifted).reachable_biomes().contains(&Biome::IceSheet));
+    }
+
+    // The plugin starts the view where the launch config says
+    #[test]
+    fn plugin_starts_at_the_configured_view() {
+        let config = MapConfig { initial_zoom: 3.0, initial_x_shift: -0.4, initial_y_shift: 0.6, ..Default::default() };
+        let mut app = App::new();
+        app.add_plugin(TileMapPlugin { config });
+
+        assert_eq!(app.world.resource::<Zoom>().0, 3.0);
+        assert_eq!((app.world.resource::<XShift>().0, app.world.resource::<YShift>().0), (-0.4, 0.6));
+        assert_eq!(app.world.resource::<MapConfig>().initial_zoom, 3.0);
+    }
+
+    // Clamping the latitude term only ever adds moisture, and only past where the term would turn negative
+    #[test]
+    fn water_dist_clamp_keeps_polar_percipitation_sensible() {
+        let unclamped_config = reference_config();
+        let clamped_config = MapConfig { water_dist_min: 0.0, ..reference_config() };
+        let (unclamped, clamped) = (reference_map_gen(&unclamped_config), reference_map_gen(&clamped_config));
+
+        for step in 0..=10 {
+            let y_dis = step as f64 / 10.0;
+            let percipitation = |mapgen: &MapGen| {
+                let partial_temp = mapgen.get_partial_temp(100.0, y_dis, DRY_ADB_LAPSE_RATE * 0.5, 0.3, -0.1);
+                mapgen.get_percip_temp(100.0, y_dis, partial_temp, 0.3, -0.1).0
+            };
+
+            if y_dis < 1.0 / clamped_config.water_dist_falloff {
+                assert_eq!(percipitation(&clamped), percipitation(&unclamped), "latitude {}", y_dis);
+            } else {
+                assert!(percipitation(&clamped) >= percipitation(&unclamped), "latitude {}", y_dis);
+            }
+        }
+    }
+
+    // On a grid of every other tile, an island in the corner counts the map tiles its samples cover, and
+    // its label lands on it rather than toward the map center
+    #[test]
+    fn labels_land_on_their_landmass_at_stride_2() {
+        let sample = |biome| TileSample { height: 0.0, temperature: 0.0, percipitation: 0.0, falloff: 0.0, biome };
+        let mut grid = MapGrid { half_size: 2, stride: 2, tiles: Vec::new(), samples: vec![sample(Biome::Ocean); 25] };
+        for (x, y) in [(2, 2), (4, 2), (2, 4), (4, 4)] {
+            let index = grid.index(x, y).unwrap();
+            grid.samples[index] = sample(Biome::TemperateSteppe);
         }
+
+        let landmasses = grid.landmasses(1);
+        assert_eq!(landmasses.len(), 1);
+        assert_eq!(landmasses[0].tiles, 16);
+        assert_eq!(landmasses[0].centroid, (3.0, 3.0));
+
+        let (x, y) = grid.snap(landmasses[0].centroid.0.round() as i32, landmasses[0].centroid.1.round() as i32);
+        assert!(grid.is_land(grid.index(x, y).unwrap()), "the label at ({}, {}) is off the island", x, y);
     }
 
-    // Subpolar ------------------
+    // The same seed and map give the same landmasses the same names, and the reference map's island keeps
+    // its name when the view pans a few tiles
+    #[test]
+    fn landmass_names_stay_with_the_terrain() {
+        let config = reference_config();
+        let names = |x_shift, y_shift| {
+            let mapgen = MapGen::new(REFERENCE_SEED, 1.0, x_shift, y_shift, (0.0, 0.0), &config);
+            let grid = mapgen.generate_grid();
+            grid.landmasses(1).iter().map(|landmass| mapgen.landmass_name(landmass)).collect::<Vec<_>>()
+        };
 
-    fn subpolar_dry_tundra_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 4,
-            color: Color::rgb(1.0, 1.0, 1.0),
-        }
+        let first = names(0.0, 0.0);
+        assert!(!first.is_empty());
+        assert_eq!(first, names(0.0, 0.0));
+
+        assert_eq!(names(0.0625, -0.03125), first);
     }
 
-    fn subpolar_moist_tundra_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Map cells are the grid coordinates of the unzoomed, centered view, and stay with the terrain as the
+    // view pans, so a painted biome moves with it
+    #[test]
+    fn map_cells_follow_the_terrain() {
+        let config = reference_config();
+        let mapgen = reference_map_gen(&config);
+        for (x, y) in [(0, 0), (5, -3), (-32, 32)] {
+            assert_eq!(mapgen.map_cell(x, y), (x, y));
         }
+
+        // A shift of 0.25 is an eighth of the map's width, 8 of its 65 tiles
+        let panned = MapGen::new(REFERENCE_SEED, 1.0, 0.25, -0.125, (0.0, 0.0), &config);
+        assert_eq!(panned.map_cell(0, 0), (8, -4));
+        assert_eq!(panned.map_cell(-8, 4), (0, 0));
     }
 
-    fn subpolar_wet_tundra_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // At zoom 2 linear scaling halves the height noise's frequency, so it reads at twice the coordinates
+    // what unscaled noise reads, and inverse scaling doubles it
+    #[test]
+    fn zoom_scaling_sets_the_noise_frequency() {
+        let map_gen = |zoom_scaling| {
+            let config = MapConfig { zoom_scaling, ..reference_config() };
+            MapGen::new(REFERENCE_SEED, 2.0, 0.0, 0.0, (0.0, 0.0), &config)
+        };
+        let (linear, none, inverse) = (map_gen(ZoomScaling::Linear), map_gen(ZoomScaling::None), map_gen(ZoomScaling::Inverse));
+        let height = |mapgen: &MapGen, x: f64, y: f64| mapgen.height_noises[0].0.get_value(mapgen.noise_base, x, y);
+
+        for (x, y) in [(3.0, -7.0), (40.0, 12.5), (-91.0, 64.0)] {
+            assert_eq!(height(&linear, 2.0 * x, 2.0 * y), height(&none, x, y), "({}, {})", x, y);
+            assert_eq!(height(&inverse, x / 2.0, y / 2.0), height(&none, x, y), "({}, {})", x, y);
         }
+        assert_ne!(height(&linear, 40.0, 12.5), height(&none, 40.0, 12.5));
     }
 
-    fn subpolar_rain_tundra_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
-        }
+    // Zoomed a trillion times into a view near the map edge, the tiles of a row still sample different
+    // height noise. Adding their offsets to the view's base first, as the generator once did, rounds
+    // every tile of the row onto the same point
+    #[test]
+    fn neighbouring_tiles_stay_distinct_at_extreme_zoom() {
+        let config = MapConfig { zoom_scaling: ZoomScaling::None, ..reference_config() };
+        let mapgen = MapGen::new(REFERENCE_SEED, 1e12, 0.9, -0.7, (0.0, 0.0), &config);
+        let noise = &mapgen.height_noises[0].0;
+        let row: Vec<(f64, f64)> = (0..16).map(|x| {
+            let (tile_x, tile_y) = mapgen.tile_position(x, 0);
+            mapgen.noise_position(tile_x, tile_y)
+        }).collect();
+
+        let relative: Vec<f64> = row.iter().map(|&(x, y)| noise.get_value(mapgen.noise_base, x, y)).collect();
+        assert!(relative.windows(2).all(|pair| pair[0] != pair[1]), "{:?}", relative);
+
+        let summed: Vec<f64> = row.iter().map(|&(x, y)| noise.get_value((0.0, 0.0), mapgen.noise_base.0 + x, mapgen.noise_base.1 + y)).collect();
+        assert!(summed.windows(2).all(|pair| pair[0] == pair[1]), "{:?}", summed);
     }
 
-    // Boreal ------------------
+    // Offsetting the noise by a quarter of the map axis east and half south samples at p the height noise
+    // the unshifted map has at p plus that offset, while the falloff's center stays where it was
+    #[test]
+    fn noise_offset_translates_the_noise() {
+        let config = reference_config();
+        let offset = (0.25, -0.5);
+        let (plain, shifted) = (reference_map_gen(&config), MapGen::new(REFERENCE_SEED, 1.0, 0.0, 0.0, offset, &config));
+        let axis_len = config.noise_axis_len();
 
-    fn boreal_desert_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+        for (x, y) in [(0.0, 0.0), (13.0, -42.0), (-77.5, 20.25)] {
+            let moved = plain.height_value(plain.noise_base, x + axis_len * offset.0, y + axis_len * offset.1);
+            assert!((shifted.height_value(shifted.noise_base, x, y) - moved).abs() < 1e-9, "({}, {})", x, y);
+            assert_eq!(shifted.falloff_distances(x, y), plain.falloff_distances(x, y), "({}, {})", x, y);
         }
     }
 
-    fn boreal_dry_scrub_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // The falloff keeps more height at the map center than at any corner, on the generated grid and with
+    // the falloff's noise offset elsewhere
+    #[test]
+    fn falloff_is_highest_at_the_center() {
+        for config in [reference_config(), MapConfig { falloff_noise_offset: Some((37.0, -11.0)), ..reference_config() }] {
+            let grid = reference_map_gen(&config).generate_grid();
+            let half = grid.half_size * grid.stride;
+            let falloff = |x, y| grid.samples[grid.index(x, y).unwrap()].falloff;
+
+            for (x, y) in [(-half, -half), (half, -half), (-half, half), (half, half)] {
+                assert!(falloff(0, 0) > falloff(x, y), "corner ({}, {})", x, y);
+            }
         }
     }
 
-    fn boreal_moist_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Negating the coordinates for the falloff's noise ties the falloff at p to the height noise at -p.
+    // The default offset sample breaks that tie, and with it the heights at p and -p
+    #[test]
+    fn falloff_offset_breaks_the_point_symmetry() {
+        let mirrored_config = MapConfig { falloff_noise_offset: None, ..reference_config() };
+        let offset_config = reference_config();
+        let (mirrored, offset) = (reference_map_gen(&mirrored_config), reference_map_gen(&offset_config));
+        let falloff_noise = |mapgen: &MapGen, x: f64, y: f64| (0.7 - mapgen.falloff(0.0, x, y)) / 0.4;
+
+        for (x, y) in [(13.0, -42.0), (-77.5, 20.25), (51.0, 64.0)] {
+            let opposite = mirrored.height_value(mirrored.noise_base, -x, -y);
+            assert!((falloff_noise(&mirrored, x, y) - opposite).abs() < 1e-9, "({}, {})", x, y);
+            assert!((falloff_noise(&offset, x, y) - opposite).abs() > 1e-6, "({}, {})", x, y);
+            assert_ne!(offset.height_at(x, y), offset.height_at(-x, -y), "({}, {})", x, y);
         }
     }
 
-    fn boreal_wet_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // A radius of 3 tiles around the map's corner holds 29 tile centers, 11 of them on the map, which
+    // count as the grid's biomes, and 18 past the edge, which count as ocean
+    #[test]
+    fn biomes_in_radius_counts_past_the_edge_as_ocean() {
+        let config = reference_config();
+        let mapgen = reference_map_gen(&config);
+        let corner = config.map_size as i32 / 2;
+        let radius = 3.0 * config.world_units_per_tile();
+
+        let mut expected = HashMap::from([(Biome::Ocean, 18)]);
+        for y in (corner - 3)..=corner {
+            for x in (corner - 3)..=corner {
+                if (x - corner).pow(2) + (y - corner).pow(2) <= 9 {
+                    let (world_x, world_y) = config.tile_to_world(x, y);
+                    *expected.entry(mapgen.get_tile_at_world(world_x, world_y).1.biome).or_insert(0) += 1;
+                }
+            }
         }
+
+        let counts = mapgen.biomes_in_radius(config.tile_to_world(corner, corner), radius);
+        assert_eq!(counts.values().sum::<u32>(), 29);
+        assert_eq!(counts, expected);
     }
 
-    fn boreal_rain_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Configs whose noise sums to nothing or overflows still give finite heights, climates and colors on
+    // every tile: no height octaves, and humidity octaves whose frequency runs off to infinity
+    #[test]
+    fn degenerate_noise_gives_finite_tiles() {
+        for config in [
+            MapConfig { height_octaves: 0, ..reference_config() },
+            MapConfig { humidity_lacunarity: 1e300, ..reference_config() },
+            MapConfig { humidity_octaves: 0, temperature_octaves: 0, falloff_noise_offset: Some((1e300, -1e300)), ..reference_config() },
+        ] {
+            let grid = reference_map_gen(&config).generate_grid();
+            for (tile, sample) in grid.tiles.iter().zip(&grid.samples) {
+                assert!([sample.height, sample.temperature, sample.percipitation, sample.falloff].iter().all(|value| value.is_finite()));
+                assert!(tile.color.as_rgba_f32().iter().all(|channel| channel.is_finite()));
+            }
         }
     }
 
-    // Temperate ------------------
+    // After the first generation and after a reseed, the grid resource holds exactly the tiles and
+    // samples of the spawned tile entities
+    #[test]
+    fn grid_resource_matches_spawned_tiles_after_regeneration() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Image>()
+            .add_asset::<Font>()
+            .add_asset::<TextureAtlas>()
+            .insert_resource(reference_config())
+            .insert_resource(DisplayConfig::default())
+            .insert_resource(GenTime { elapsed: 0.0, paused: true, speed: 1.0 })
+            .insert_resource(PendingSeed::default())
+            .insert_resource(MapFadeIn::default())
+            .insert_resource(MapDue::default())
+            .insert_resource(Zoom(1.0))
+            .insert_resource(XShift(0.0))
+            .insert_resource(YShift(0.0))
+            .insert_resource(NoiseOffset::default())
+            .add_system(schedule_map)
+            .add_system(clear_map.after(schedule_map))
+            .add_system(respawn_map.after(clear_map));
 
-    fn temperate_desert_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
-        }
-    }
+        let mut first_heights = Vec::new();
+        for seed in [REFERENCE_SEED, REFERENCE_SEED + 1] {
+            app.insert_resource(Seed::number(seed));
+            app.update();
 
-    fn temperate_desert_scrub_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+            let mut tiles = app.world.query::<&MapTile>();
+            let grid = app.world.resource::<MapGrid>();
+            let mut spawned = 0;
+            for tile in tiles.iter(&app.world) {
+                let index = grid.index(tile.coords.0, tile.coords.1).unwrap();
+                assert!(grid.tiles[index] == tile.tile && grid.samples[index] == tile.sample,
+                    "seed {} tile {:?} differs from the grid", seed, tile.coords);
+                spawned += 1;
+            }
+            assert_eq!(spawned, grid.tiles.len(), "seed {}", seed);
+
+            let heights: Vec<f64> = grid.samples.iter().map(|sample| sample.height).collect();
+            assert_ne!(heights, first_heights);
+            first_heights = heights;
         }
     }
 
-    fn temperate_steppe_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
-        }
+    #[test]
+    fn generating_twice_gives_identical_output() {
+        assert_eq!(determinism_mismatches(&MapConfig::default()), 0);
+        assert_eq!(determinism_mismatches(&reference_config()), 0);
     }
 
-    fn temperate_moist_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
-        }
+    // A grid left over from a larger map of another seed, regenerated into, holds just what a fresh
+    // grid of the reference map does
+    #[test]
+    fn reused_grid_matches_a_fresh_one() {
+        let config = reference_config();
+        let mut grid = MapGen::new(REFERENCE_SEED + 1, 1.0, 0.0, 0.0, (0.0, 0.0), &MapConfig { map_size: 96, ..reference_config() })
+            .generate_grid();
+        let mapgen = reference_map_gen(&config);
+        mapgen.generate_grid_into(&mut grid);
+        let fresh = mapgen.generate_grid();
+
+        assert_eq!((grid.half_size, grid.stride), (fresh.half_size, fresh.stride));
+        assert!(grid.tiles == fresh.tiles);
+        assert!(grid.samples == fresh.samples);
     }
 
-    fn temperate_wet_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // The field arrays hold each grid sample's values at its own index, its biome as its Biome::ALL
+    // position, and are as wide as the grid
+    #[test]
+    fn fields_match_the_grid_samples() {
+        let grid = reference_map_gen(&reference_config()).generate_grid();
+        let fields = grid.fields();
+
+        assert_eq!(fields.width, grid.width() as usize);
+        assert_eq!(fields.biome.len(), grid.tiles.len());
+        for (index, sample) in grid.samples.iter().enumerate() {
+            assert_eq!(fields.height[index], sample.height as f32);
+            assert_eq!(fields.temperature[index], sample.temperature as f32);
+            assert_eq!(fields.percipitation[index], sample.percipitation as f32);
+            assert_eq!(Biome::ALL[fields.biome[index] as usize], sample.biome, "tile {:?}", grid.coords(index));
         }
     }
 
-    fn temperate_rain_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
-        }
+    // On a map with more tiles than Draft's entity budget, Draft classifies fewer tiles than High, which
+    // generates every one
+    #[test]
+    fn draft_quality_samples_less_than_high() {
+        let samples = |quality: Quality| {
+            let mut config = MapConfig { map_size: 200, ..reference_config() };
+            quality.apply(&mut config);
+            let mapgen = reference_map_gen(&config);
+            mapgen.generate_grid();
+            mapgen.climate_samples()
+        };
+
+        let (draft, high) = (samples(Quality::Draft), samples(Quality::High));
+        assert_eq!(high, 201 * 201);
+        assert!(draft < high, "Draft took {} samples, High {}", draft, high);
     }
 
-    // Subtropical ------------------
+    // Streaming the map the way low memory mode spawns it must give the stored grid's tiles, samples
+    // and movement costs, or the two generation paths have drifted apart
+    #[test]
+    fn streamed_tiles_match_the_stored_grid() {
+        let config = reference_config();
+        let mapgen = reference_map_gen(&config);
+        let grid = mapgen.generate_grid();
+        let costs = mapgen.cost_grid(&grid);
+        let mut streamed = 0;
 
-    fn subtropical_desert_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
-        }
+        mapgen.stream_tiles(|(x, y), tile, sample, cost| {
+            let index = grid.index(x, y).unwrap();
+            assert!(grid.tiles[index] == tile && grid.samples[index] == sample && costs[index] == cost,
+                "streamed tile ({}, {}) differs from the grid", x, y);
+            streamed += 1;
+        }).unwrap();
+        assert_eq!(streamed, grid.tiles.len());
     }
 
-    fn subtropical_desert_scrub_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Erosion and biome smoothing look at neighbours a stride apart, so they stay off, as by default
+    #[test]
+    fn coarse_preview_matches_the_full_grid() {
+        let config = MapConfig { world_age: 0.0, biome_filter_passes: 0, ..reference_config() };
+        let mapgen = reference_map_gen(&config);
+        let grid = mapgen.generate_grid();
+        let preview = mapgen.generate_grid_at(4);
+
+        for index in 0..preview.samples.len() {
+            let (x, y) = preview.coords(index);
+            let full = grid.index(x, y).unwrap();
+            assert!(grid.tiles[full] == preview.tiles[index] && grid.samples[full] == preview.samples[index],
+                "preview tile ({}, {}) differs from the full grid", x, y);
         }
     }
-    
-    fn subtropical_thorn_woodland_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+
+    #[test]
+    fn volcanoes_keep_to_their_height_and_density() {
+        let config = MapConfig { volcano_density: 0.3, volcano_min_height: 500.0, ..reference_config() };
+        let mapgen = reference_map_gen(&config);
+
+        for (index, sample) in mapgen.generate_grid().samples.iter().enumerate() {
+            assert!(sample.biome != Biome::Volcanic || sample.height >= config.volcano_min_height,
+                "volcanic tile {} is only {:.0} m high", index, sample.height);
         }
+
+        let cells = 100;
+        let volcanoes = (0..cells * cells).filter(|cell| mapgen.volcano_in_cell((cell % cells, cell / cells)).is_some()).count();
+        let share = volcanoes as f64 / (cells * cells) as f64;
+        assert!((share - config.volcano_density).abs() <= 0.02, "{:.3} of the hotspot cells hold a volcano", share);
     }
 
-    fn subtropical_dry_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Lookups through the tile cache match sampling the same points afresh, on a first pass that fills
+    // the cache and a second that hits it. A second seed's generator starts empty and answers for its
+    // own map rather than the first seed's
+    #[test]
+    fn cached_lookups_match_fresh_sampling() {
+        let config = reference_config();
+        let uncached_config = MapConfig { tile_cache_size: 0, ..config.clone() };
+        let step = config.world_units_per_tile();
+        let points: Vec<(f64, f64)> = (-8..8).flat_map(|y| (-8..8).map(move |x| (x as f64 * step, y as f64 * step))).collect();
+
+        for seed in [REFERENCE_SEED, REFERENCE_SEED + 1] {
+            let cached = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config);
+            let uncached = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &uncached_config);
+            assert_eq!(cached.cached_tiles(), 0);
+
+            for _ in 0..2 {
+                for &(x, y) in &points {
+                    assert!(cached.get_tile_at_world(x, y) == uncached.get_tile_at_world(x, y), "seed {} at ({}, {})", seed, x, y);
+                }
+            }
+            assert_eq!(cached.cached_tiles(), points.len().min(config.tile_cache_size));
         }
     }
 
-    fn subtropical_moist_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // On a zoomed in map, whose edges are mostly land, off-map ocean puts every land border tile one
+    // tile from the coast, while treating off-map as land or mirroring moves some further inland
+    #[test]
+    fn edge_policies_set_the_border_coast_distances() {
+        let config = reference_config();
+        let mapgen = |edge_policy| MapGen::new(REFERENCE_SEED, 4.0, 0.0, 0.0, (0.0, 0.0), &MapConfig { edge_policy, ..config.clone() });
+        let (ocean, land, mirror) = (mapgen(EdgePolicy::Ocean), mapgen(EdgePolicy::Land), mapgen(EdgePolicy::Mirror));
+
+        let half_size = REFERENCE_MAP_SIZE as i32 / 2;
+        let border = (-half_size..=half_size).flat_map(|i| [(i, -half_size), (i, half_size), (-half_size, i), (half_size, i)]);
+        let (mut land_moved, mut mirror_moved) = (false, false);
+
+        for (x, y) in border {
+            let (world_x, world_y) = config.tile_to_world(x, y);
+            if ocean.get_tile_at_world(world_x, world_y).1.height <= 0.0 {
+                continue;
+            }
+
+            let distance = |mapgen: &MapGen| mapgen.coast_distance_at_world(world_x, world_y, 3);
+            assert_eq!(distance(&ocean), Some(1), "border tile ({}, {})", x, y);
+            land_moved |= distance(&land) != Some(1);
+            mirror_moved |= distance(&mirror) != Some(1);
         }
+
+        assert!(land_moved, "treating off-map as land left every border tile on the coast");
+        assert!(mirror_moved, "mirroring the map left every border tile on the coast");
     }
 
-    fn subtropical_wet_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Values worked out when tile_hash was written, as (seed, x, y, salt, hash). A change to any means
+    // every feature built on it changed on every map
+    #[test]
+    fn tile_hashes_are_stable() {
+        for (seed, x, y, salt, expected) in [
+            (829201, 0, 0, 1, 0xa5b6a79592e50215),
+            (829201, -3, 7, 2, 0x05e3ed3e44a0ad4e),
+            (1, 100, -100, 3, 0x300f3d00235a7524),
+        ] {
+            assert_eq!(tile_hash(seed, x, y, salt), expected);
         }
     }
 
-    fn subtropical_rain_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    #[test]
+    fn tile_hashes_differ_between_salts() {
+        for y in -16..16 {
+            for x in -16..16 {
+                let hashes: Vec<u64> = (0..8).map(|salt| tile_hash(REFERENCE_SEED, x, y, salt)).collect();
+                assert!((1..hashes.len()).all(|i| !hashes[..i].contains(&hashes[i])), "two salts hashed tile ({}, {}) the same", x, y);
+            }
         }
     }
 
-     // Tropical ------------------
+    // Frozen land either side of the ice sheet threshold splits into ice sheets and polar deserts, with
+    // the biome table and with the fallback classification
+    #[test]
+    fn wet_frozen_land_is_ice_sheet_and_dry_polar_desert() {
+        for biome_table in [reference_config().biome_table, None] {
+            let config = MapConfig { biome_table, ..reference_config() };
+            let mapgen = reference_map_gen(&config);
+            let threshold = config.ice_sheet_min_percipitation;
 
-    fn tropical_desert_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 2,
-            color: Color::rgb(1.0, 1.0, 1.0),
+            for temperature in [-20.0, -5.0, -0.5] {
+                assert_eq!(mapgen.pick_biome(100.0, temperature, threshold + 200.0), Biome::IceSheet);
+                assert_eq!(mapgen.pick_biome(100.0, temperature, threshold - 200.0), Biome::PolarDesert);
+            }
         }
     }
 
-    fn tropical_desert_scrub_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 1,
-            color: Color::rgb(1.0, 1.0, 1.0),
+    fn biome_fields(width: usize, biome: Vec<u8>) -> GridFields {
+        GridFields {
+            width,
+            height: vec![0.0; biome.len()],
+            temperature: vec![0.0; biome.len()],
+            percipitation: vec![0.0; biome.len()],
+            biome,
         }
     }
 
-    fn tropical_thorn_woodland_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
-        }
+    #[test]
+    fn separate_areas_of_one_biome_get_their_own_regions() {
+        let (land, ocean) = (Biome::PolarDesert as u8, Biome::Ocean as u8);
+
+        // Two land islands split by an ocean column
+        let islands = biome_fields(3, vec![land, ocean, land, land, ocean, land]);
+        assert_eq!(islands.regions(Connectivity::Four), [0, 1, 2, 0, 1, 2]);
+        assert_eq!(islands.regions(Connectivity::Eight), [0, 1, 2, 0, 1, 2]);
+
+        // A checkerboard, which only diagonals connect
+        let checkerboard = biome_fields(2, vec![land, ocean, ocean, land]);
+        assert_eq!(checkerboard.regions(Connectivity::Four), [0, 1, 2, 3]);
+        assert_eq!(checkerboard.regions(Connectivity::Eight), [0, 1, 1, 0]);
     }
 
-    fn tropical_very_dry_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Caps either side of zero, as under freezing. The hard clamp lands exactly on the cap, the soft
+    // one stays strictly under it while keeping the values in order, and neither moves values far below
+    #[test]
+    fn percipitation_caps_clamp_and_keep_order() {
+        for cap in [-2000.0, 0.0, 3500.0] {
+            let mut previous = f64::NEG_INFINITY;
+
+            for step in 1..=40 {
+                let percipitation = cap + step as f64 * 250.0;
+                assert_eq!(soft_cap(percipitation, cap, f64::INFINITY), cap);
+
+                let soft = soft_cap(percipitation, cap, 1.0);
+                assert!(soft < cap && soft > previous, "soft cap {} took {} to {}, after {}", cap, percipitation, soft, previous);
+                previous = soft;
+            }
+
+            for hardness in [1.0, f64::INFINITY] {
+                assert_eq!(soft_cap(cap - 5000.0, cap, hardness), cap - 5000.0);
+            }
         }
     }
 
-    fn tropical_dry_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Share of land tiles over a few seeds in the dry and moist biomes between deserts and wet forests
+    fn mid_moisture_share(percipitation_scale: PercipitationScale) -> f64 {
+        let config = MapConfig { percipitation_scale, ..reference_config() };
+        let (mut land, mut mid) = (0, 0);
+
+        for seed in REFERENCE_SEED..REFERENCE_SEED + 4 {
+            let grid = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config).generate_grid();
+            for sample in grid.samples.iter().filter(|sample| sample.biome != Biome::Ocean) {
+                let name = format!("{:?}", sample.biome);
+                land += 1;
+                if name.contains("Dry") || name.contains("Moist") {
+                    mid += 1;
+                }
+            }
         }
+
+        mid as f64 / land.max(1) as f64
     }
 
-    fn tropical_moist_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
-        }
+    #[test]
+    fn log_percipitation_scale_favours_mid_moisture_biomes() {
+        let (linear, log) = (mid_moisture_share(PercipitationScale::Linear), mid_moisture_share(PercipitationScale::Log));
+        assert!(log > linear, "the log scale put {:.3} of the land in mid-moisture biomes, the linear {:.3}", log, linear);
     }
 
-    fn tropical_wet_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // Every tile reports exactly one of an ocean depth and a land height, whichever its biome calls
+    // for, both not negative and matching its signed height
+    #[test]
+    fn ocean_tiles_report_a_depth_and_land_tiles_a_height() {
+        let grid = reference_map_gen(&reference_config()).generate_grid();
+        assert!(grid.samples.iter().any(|sample| sample.biome == Biome::Ocean));
+        assert!(grid.samples.iter().any(|sample| sample.biome != Biome::Ocean));
+
+        for sample in &grid.samples {
+            match (sample.biome == Biome::Ocean, sample.ocean_depth(), sample.land_height()) {
+                (true, Some(depth), None) => assert!(depth >= 0.0 && depth == -sample.height),
+                (false, None, Some(height)) => assert!(height > 0.0 && height == sample.height),
+                (ocean, depth, height) => panic!("ocean {} tile at {} m reported depth {:?} and height {:?}", ocean, sample.height, depth, height),
+            }
         }
     }
 
-    fn tropical_rain_forest_tile(temperature: f64, percipitation: f64) -> Tile {
-        Tile {
-            index: 0,
-            color: Biome::calculate_grass_color(temperature, percipitation),
+    // At a fixed zoom and shift every tile_scale shows the same terrain, and a point query at a tile's
+    // world position finds that tile's climate
+    #[test]
+    fn tile_scale_leaves_the_terrain_unchanged() {
+        for zoom in [1.0, 2.5] {
+            // The cache snaps queries to world positions, which would move them by different amounts
+            // of terrain at each scale
+            let config = |tile_scale| MapConfig { tile_scale, tile_cache_size: 0, ..reference_config() };
+            let reference = MapGen::new(REFERENCE_SEED, zoom, 0.3, -0.2, (0.0, 0.0), &config(0.25)).generate_grid();
+
+            for tile_scale in [0.1, 0.5, 1.0, 3.0] {
+                let config = config(tile_scale);
+                let mapgen = MapGen::new(REFERENCE_SEED, zoom, 0.3, -0.2, (0.0, 0.0), &config);
+                let grid = mapgen.generate_grid();
+
+                assert_eq!(grid.samples.len(), reference.samples.len());
+                for (sample, expected) in grid.samples.iter().zip(&reference.samples) {
+                    assert!(sample.biome == expected.biome && (sample.height - expected.height).abs() <= 1e-6,
+                        "zoom {} tile_scale {} changed the terrain", zoom, tile_scale);
+                }
+
+                let (x, y) = grid.coords(grid.samples.len() / 3);
+                let (world_x, world_y) = config.tile_to_world(x, y);
+                let sampled = mapgen.get_tile_at_world(world_x, world_y).1;
+                assert!((sampled.temperature - grid.samples[grid.index(x, y).unwrap()].temperature).abs() <= 1e-6);
+            }
         }
     }
 
-}
+    // Mild land close to the sea and not too steep, as a game start might ask for
+    fn mild_coastal_spawn(site: &SpawnSite) -> bool {
+        let name = format!("{:?}", site.sample.biome);
+        (name.starts_with("Temperate") || name.starts_with("Subtropical"))
+            && site.coast_distance.is_some_and(|distance| distance <= 5)
+            && site.slope < 100.0
+    }
 
-fn spawn_map(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    seed: Res<Seed>,
-    zoom: Res<Zoom>,
-    x_shift: Res<XShift>,
-    y_shift: Res<YShift>,
-) {
+    // Every spawn is a land tile satisfying the predicate with a positive spawn weight, and a predicate
+    // nothing satisfies gives None
+    #[test]
+    fn spawns_satisfy_their_predicate() {
+        let config = reference_config();
 
-    let tile_size = 16.0;
-    let tile_scale = 0.25;
-    let map_size = 250;
+        for seed in [REFERENCE_SEED, 1, 2] {
+            let mapgen = MapGen::new(seed, 1.0, 0.0, 0.0, (0.0, 0.0), &config);
+            let grid = mapgen.generate_grid();
+            let sites = mapgen.spawn_sites(&grid);
+            let mut rng = StdRng::seed_from_u64(seed as u64);
 
-    let mapgen = MapGen::new(seed.0, zoom.0, x_shift.0, y_shift.0, map_size, tile_size, tile_scale);
+            // The first through pick_spawn, which generates the same grid itself
+            let first = mapgen.pick_spawn(&mut rng, mild_coastal_spawn);
+            assert_eq!(first.is_some(), sites.iter().any(mild_coastal_spawn), "seed {}", seed);
 
-    let texture_handle = asset_server.load("textures/tilemap.png");
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2::new(tile_size as f32, tile_size as f32), 6, 1, None, None);
-    commands.spawn(Camera2dBundle::default());
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+            for spawn in first.into_iter().chain((1..50).filter_map(|_| mapgen.pick_spawn_on(&grid, &mut rng, mild_coastal_spawn))) {
+                let site = sites.iter().find(|site| (site.x, site.y) == spawn).expect("spawned off the land tiles");
+                assert!(mild_coastal_spawn(site), "seed {} spawned at {:?}, which the predicate rules out", seed, spawn);
+                assert!(config.spawn_weight(site.sample.biome) > 0.0, "seed {} spawned on {:?}", seed, site.sample.biome);
+            }
 
-    let map_half_size: i32 = map_size as i32 / 2;
+            assert_eq!(mapgen.pick_spawn_on(&grid, &mut rng, |site| site.slope < 0.0), None);
+        }
+    }
 
-    for y in -map_half_size..(map_half_size + 1) {
-        for x in -map_half_size..(map_half_size + 1) {
-            
-            let tile_x = x as f64 * tile_size * tile_scale;
-            let tile_y = y as f64 * tile_size * tile_scale;
+    // Whether biome counts of a view show at least view_min_land_fraction land and view_min_biomes land biomes
+    fn view_is_interesting(counts: &HashMap<Biome, u32>, config: &MapConfig) -> bool {
+        view_score(counts, config) >= 2.0
+    }
 
-            let tile = mapgen.get_tile(tile_x, tile_y);
+    // From each start at each view, given as (zoom, x_shift, y_shift, share of the map radius shown),
+    // the reseed picks a seed whose view has the land and biome variety asked for, the same each time
+    #[test]
+    fn reseed_keeps_the_view_interesting() {
+        let config = reference_config();
 
-            let mut sprite = TextureAtlasSprite::new(tile.index);
-            sprite.color = tile.color;
+        for (zoom, x_shift, y_shift, share) in [(1.0, 0.0, 0.0, 1.0), (1.5, 0.15, -0.1, 0.5), (2.0, -0.1, 0.1, 0.25)] {
+            let radius = config.map_axis_len() * share;
 
-            commands.spawn((
-                SpriteSheetBundle {
-                    texture_atlas: texture_atlas_handle.clone(),
-                    sprite: sprite,
-                    transform: Transform {
-                        translation: Vec3::new(tile_x as f32, tile_y as f32, 0.0),
-                        scale: Vec3::splat(tile_scale as f32),
-                        ..Default::default()
-                    },
-                    ..default()
-                },
-            ));
+            for start in [REFERENCE_SEED, 4, 31337] {
+                let seed = seed_with_interesting_view(start, zoom, x_shift, y_shift, (0.0, 0.0), (0.0, 0.0), radius, &config);
+                let counts = MapGen::new(seed, zoom, x_shift, y_shift, (0.0, 0.0), &config).view_biomes((0.0, 0.0), radius);
+
+                assert!(view_is_interesting(&counts, &config), "from {} at zoom {} the reseed picked {}, whose view is {:?}", start, zoom, seed, counts);
+                assert_eq!(seed_with_interesting_view(start, zoom, x_shift, y_shift, (0.0, 0.0), (0.0, 0.0), radius, &config), seed);
+            }
         }
     }
-}
 
-fn update_inputs(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    keyboard: Res<Input<KeyCode>>,
-    query: Query<Entity>,
-    mut seed: ResMut<Seed>,
-    mut zoom: ResMut<Zoom>,
-    mut x_shift: ResMut<XShift>,
-    mut y_shift: ResMut<YShift>,
-) {
+    // One sample per tile reproduces the tiles exactly in either mode. Three a side only smooth the biome
+    // borders, so the majority keeps at least three quarters of the tiles' biomes
+    #[test]
+    fn subsampling_smooths_without_moving_the_tiles() {
+        let mapgen = reference_map_gen(&reference_config());
+        let grid = mapgen.generate_grid();
 
-    if keyboard.just_pressed(KeyCode::Space) {
-        let mut rng = thread_rng();
+        for mode in [SubsampleMode::Majority, SubsampleMode::Blend] {
+            let tiles = mapgen.subsampled_tiles(&grid, 1, mode);
+            assert!(tiles.len() == grid.tiles.len() && tiles.iter().zip(&grid.tiles).all(|(sampled, tile)| sampled == tile), "{:?}", mode);
 
-        seed.0 = rng.gen_range(0..99999);
-    }
-    
-    if keyboard.just_pressed(KeyCode::Up) {
-        zoom.0 += 0.1;
+            let smoothed = mapgen.subsampled_tiles(&grid, 3, mode);
+            assert_eq!(smoothed.len(), grid.tiles.len());
+            if mode == SubsampleMode::Majority {
+                let changed = smoothed.iter().zip(&grid.tiles).filter(|(sampled, tile)| sampled.index != tile.index).count();
+                assert!(changed * 4 <= grid.tiles.len(), "the majority changed the biome tile of {} of {} tiles", changed, grid.tiles.len());
+            }
+        }
     }
 
-    if keyboard.just_pressed(KeyCode::Down) && zoom.0 > 0.2 {
-        zoom.0 -= 0.1;
-    }
+    // An expensive config with a tiny budget stops with the budget error on both the grid and the
+    // streaming path, while a generous budget changes nothing
+    #[test]
+    fn tiny_budget_stops_an_expensive_config() {
+        let expensive = MapConfig { map_size: 1024, world_age: 1.0, generation_budget: Some(0.001), ..reference_config() };
+        expensive.validate().unwrap();
+        let mapgen = reference_map_gen(&expensive);
 
-    if keyboard.just_pressed(KeyCode::A) && x_shift.0 > -0.9 {
-        x_shift.0 -= 0.1 / zoom.0;
-    }
+        assert!(matches!(mapgen.try_generate_grid(), Err(Error::BudgetExceeded { .. })));
+        assert!(matches!(mapgen.stream_tiles(|_, _, _, _| {}), Err(Error::BudgetExceeded { .. })));
 
-    if keyboard.just_pressed(KeyCode::D) && x_shift.0 < 0.9 {
-        x_shift.0 += 0.1 / zoom.0;
+        let generous = MapConfig { generation_budget: Some(600.0), ..reference_config() };
+        let mapgen = reference_map_gen(&generous);
+        assert!(mapgen.try_generate_grid().unwrap().tiles == mapgen.generate_grid().tiles);
     }
 
-    if keyboard.just_pressed(KeyCode::S) && y_shift.0 > -0.9 {
-        y_shift.0 -= 0.1 / zoom.0;
-    }
+    // Despawning a preview cancels the full map's thread, which stops at its next check instead of
+    // generating an expensive map nobody will see
+    #[test]
+    fn despawning_a_preview_cancels_its_generation() {
+        let expensive = MapConfig { map_size: 1024, world_age: 1.0, ..reference_config() };
+        let cancel = Arc::new(AtomicBool::new(false));
+        let thread_cancel = cancel.clone();
+        let generating = std::thread::spawn(move || reference_map_gen(&expensive).with_cancel(thread_cancel).try_generate_grid());
 
-    if keyboard.just_pressed(KeyCode::W) && y_shift.0 < 0.9 {
-        y_shift.0 += 0.1 / zoom.0;
-    }
-}
+        let mut world = World::new();
+        let preview = world.spawn(MapPreview { generating: None, cancel, atlas: Handle::default(), seed: REFERENCE_SEED }).id();
+        world.despawn(preview);
 
-fn update_map(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    keyboard: Res<Input<KeyCode>>,
-    query: Query<Entity>,
-    seed: Res<Seed>,
-    zoom: Res<Zoom>,
-    x_shift: Res<XShift>,
-    y_shift: Res<YShift>,
+        assert!(matches!(generating.join().unwrap(), Err(Error::Cancelled { .. })));
+    }
 
-) {
-    if seed.is_changed() || zoom.is_changed() || x_shift.is_changed() || y_shift.is_changed() {
-        for entity in query.iter() {
-            commands.entity(entity).despawn();
+    #[test]
+    fn generation_budget_must_be_positive() {
+        for budget in [0.0, -1.0, f64::NAN] {
+            assert!(MapConfig { generation_budget: Some(budget), ..Default::default() }.validate().is_err(), "{}", budget);
         }
-    
-        spawn_map(commands, asset_server, texture_atlases, seed.into(), zoom.into(), x_shift.into(), y_shift.into());
-        //println!("{}", zoom.0);
     }
-}
\ No newline at end of file
+}
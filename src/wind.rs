@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+
+use crate::config::{MapConfig, FONT_PATH};
+use crate::keybindings::{Action, KeyBindings};
+
+// Side [px] of each square of the rose
+const CELL_PIXELS: f32 = 14.0;
+
+const CALM_CELL_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+// Clockwise from north, as bearings count
+const COMPASS_POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+pub struct WindPlugin;
+
+#[derive(Resource)]
+struct ShowWindRose(bool);
+
+#[derive(Component)]
+struct WindRose;
+
+// Compass offset of the cell, (0, 0) for the center
+#[derive(Component)]
+struct WindRoseCell(i32, i32);
+
+#[derive(Component)]
+struct WindRoseText;
+
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShowWindRose(false))
+            .add_startup_system(spawn_wind_rose)
+            .add_system(update_wind_rose);
+    }
+}
+
+// A 3x3 grid in the bottom right corner, above the isolated biome text, north up, whose outer cells light up by how much of the
+// wind comes from their direction, with the wind written beneath
+fn spawn_wind_rose(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(60.0),
+                    right: Val::Px(10.0),
+                    ..default()
+                },
+                size: Size::new(Val::Px(3.0 * CELL_PIXELS), Val::Px(3.0 * CELL_PIXELS)),
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..default()
+        },
+        WindRose,
+    )).with_children(|rose| {
+        for row in 0..3 {
+            for column in 0..3 {
+                rose.spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: Val::Px(column as f32 * CELL_PIXELS),
+                                top: Val::Px(row as f32 * CELL_PIXELS),
+                                ..default()
+                            },
+                            size: Size::new(Val::Px(CELL_PIXELS), Val::Px(CELL_PIXELS)),
+                            ..default()
+                        },
+                        background_color: CALM_CELL_COLOR.into(),
+                        ..default()
+                    },
+                    WindRoseCell(column - 1, 1 - row),
+                ));
+            }
+        }
+
+        rose.spawn((
+            TextBundle::from_section("", TextStyle {
+                font: asset_server.load(FONT_PATH),
+                font_size: 16.0,
+                color: Color::WHITE,
+            }).with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(3.0 * CELL_PIXELS + 4.0),
+                    right: Val::Px(0.0),
+                    ..default()
+                },
+                ..default()
+            }),
+            WindRoseText,
+        ));
+    });
+}
+
+// F11 toggles the rose, which redraws from MapConfig's wind whenever the config changes
+fn update_wind_rose(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut show: ResMut<ShowWindRose>,
+    config: Res<MapConfig>,
+    mut rose: Query<&mut Visibility, With<WindRose>>,
+    mut cells: Query<(&WindRoseCell, &mut BackgroundColor)>,
+    mut text: Query<&mut Text, With<WindRoseText>>,
+) {
+    if bindings.just_pressed(&keyboard, Action::ToggleWindRose) {
+        show.0 = !show.0;
+    }
+
+    if !show.is_changed() && !config.is_changed() {
+        return;
+    }
+
+    for mut visibility in rose.iter_mut() {
+        visibility.is_visible = show.0;
+    }
+
+    let wind = config.wind;
+    let (upwind_x, upwind_y) = wind.upwind();
+
+    for (cell, mut color) in cells.iter_mut() {
+        let length = ((cell.0 * cell.0 + cell.1 * cell.1) as f64).sqrt();
+        let share = if length == 0.0 {
+            0.0
+        } else {
+            wind.strength * ((cell.0 as f64 * upwind_x + cell.1 as f64 * upwind_y) / length).max(0.0)
+        };
+
+        color.0 = if share > 0.0 {
+            Color::rgba(0.4, 0.8, 1.0, share as f32)
+        } else {
+            CALM_CELL_COLOR
+        };
+    }
+
+    let description = if wind.strength == 0.0 {
+        "Calm".to_string()
+    } else {
+        let point = COMPASS_POINTS[(wind.direction.rem_euclid(360.0) / 45.0).round() as usize % COMPASS_POINTS.len()];
+        format!("Wind from {} ({:.0}), {:.1}", point, wind.direction.rem_euclid(360.0), wind.strength)
+    };
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = description.clone();
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy::window::{WindowCloseRequested, WindowMoved, WindowResized};
+
+use crate::config::MapConfig;
+use crate::error::{Error, Result};
+
+pub const WINDOW_PATH: &str = "window.csv";
+
+// Smaller saved sizes are taken for a broken file rather than restored
+const MIN_WINDOW_SIZE: f32 = 100.0;
+
+pub struct WindowGeometryPlugin {
+    // Why initial_window fell back to the default, reported once logging is up
+    pub load_error: Option<Error>,
+}
+
+#[derive(Resource)]
+struct WindowLoadError(String);
+
+// Logical size and top-left screen position [px] of the primary window, None where the platform has none
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    pub position: Option<IVec2>,
+}
+
+impl Plugin for WindowGeometryPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(error) = &self.load_error {
+            app.insert_resource(WindowLoadError(error.to_string()));
+        }
+
+        app.add_startup_system(report_window_load_error)
+            .add_system(track_window_geometry)
+            .add_system(save_window_geometry.after(track_window_geometry));
+    }
+}
+
+impl WindowGeometry {
+    pub fn to_csv(self) -> String {
+        match self.position {
+            Some(position) => format!("width,height,x,y\n{},{},{},{}\n", self.width, self.height, position.x, position.y),
+            None => format!("width,height\n{},{}\n", self.width, self.height),
+        }
+    }
+
+    pub fn from_csv(csv: &str) -> Result<Self> {
+        let (line_number, line) = csv.lines().enumerate()
+            .find(|(_, line)| !line.trim().is_empty() && !line.starts_with("width,"))
+            .ok_or_else(|| Error::Parse { line: 1, message: "no window geometry".to_string() })?;
+
+        let parse_error = |message: String| Error::Parse {
+            line: line_number + 1,
+            message,
+        };
+
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        if fields.len() != 2 && fields.len() != 4 {
+            return Err(parse_error(format!("expected 2 or 4 fields, found {}", fields.len())));
+        }
+
+        let size = |field: &str| -> Result<f32> {
+            field.parse::<f32>().ok()
+                .filter(|size| size.is_finite() && *size >= MIN_WINDOW_SIZE)
+                .ok_or_else(|| parse_error(format!("invalid window size '{}'", field)))
+        };
+        let coordinate = |field: &str| -> Result<i32> {
+            field.parse::<i32>().map_err(|_| parse_error(format!("invalid window position '{}'", field)))
+        };
+
+        Ok(WindowGeometry {
+            width: size(fields[0])?,
+            height: size(fields[1])?,
+            position: match fields.len() {
+                4 => Some(IVec2::new(coordinate(fields[2])?, coordinate(fields[3])?)),
+                _ => None,
+            },
+        })
+    }
+
+    pub fn apply(&self, window: &mut WindowDescriptor) {
+        window.width = self.width;
+        window.height = self.height;
+        if let Some(position) = self.position {
+            window.position = WindowPosition::At(position.as_vec2());
+        }
+    }
+}
+
+pub fn read_window_geometry(path: &str) -> Result<WindowGeometry> {
+    WindowGeometry::from_csv(&std::fs::read_to_string(path)?)
+}
+
+pub fn write_window_geometry(path: &str, geometry: &WindowGeometry) -> Result<()> {
+    std::fs::write(path, geometry.to_csv())?;
+    Ok(())
+}
+
+// The window to open with: the saved geometry when persist_window is set and the file reads, the default
+// otherwise. Runs before logging is set up, so a broken file's error is handed back for
+// WindowGeometryPlugin to report. Browsers place the canvas themselves, so wasm builds always use the default
+pub fn initial_window(config: &MapConfig) -> (WindowDescriptor, Option<Error>) {
+    let mut window = WindowDescriptor::default();
+
+    if !config.persist_window || cfg!(target_arch = "wasm32") {
+        return (window, None);
+    }
+
+    match read_window_geometry(WINDOW_PATH) {
+        Ok(geometry) => geometry.apply(&mut window),
+        Err(Error::Io(_)) => {}
+        Err(error) => return (window, Some(error)),
+    }
+
+    (window, None)
+}
+
+fn report_window_load_error(error: Option<Res<WindowLoadError>>) {
+    if let Some(error) = error {
+        warn!("Failed to load {}: {}, using the default window", WINDOW_PATH, error.0);
+    }
+}
+
+// Follows the primary window's size and position as it is resized and moved
+fn track_window_geometry(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    geometry: Option<ResMut<WindowGeometry>>,
+    mut resized: EventReader<WindowResized>,
+    mut moved: EventReader<WindowMoved>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+
+    let mut geometry = match geometry {
+        Some(geometry) => geometry,
+        None => {
+            commands.insert_resource(WindowGeometry {
+                width: window.width(),
+                height: window.height(),
+                position: window.position(),
+            });
+            return;
+        }
+    };
+
+    for event in resized.iter().filter(|event| event.id == window.id()) {
+        geometry.width = event.width;
+        geometry.height = event.height;
+    }
+
+    for event in moved.iter().filter(|event| event.id == window.id()) {
+        geometry.position = Some(event.position);
+    }
+}
+
+// Writes the last tracked geometry once the primary window is asked to close, before it is gone
+fn save_window_geometry(
+    config: Res<MapConfig>,
+    windows: Res<Windows>,
+    geometry: Option<Res<WindowGeometry>>,
+    mut close_requests: EventReader<WindowCloseRequested>,
+) {
+    let primary = windows.get_primary().map(|window| window.id());
+    let closing = close_requests.iter().any(|event| Some(event.id) == primary);
+
+    let geometry = match geometry {
+        Some(geometry) if closing && config.persist_window && !cfg!(target_arch = "wasm32") => geometry,
+        _ => return,
+    };
+
+    if let Err(error) = write_window_geometry(WINDOW_PATH, &geometry) {
+        warn!("Failed to save {}: {}", WINDOW_PATH, error);
+    }
+}